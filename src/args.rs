@@ -1,17 +1,53 @@
 use crate::irust::options::Options;
 use std::env;
+use std::path::PathBuf;
 
 const VERSION: &str = "0.7.13";
 
-pub fn handle_args() -> std::io::Result<()> {
-    let args: Vec<String> = env::args().skip(1).collect();
+#[derive(Default)]
+pub struct Args {
+    pub project: Option<PathBuf>,
+    pub tour: bool,
+    pub accessible: bool,
+    pub preload: Option<PathBuf>,
+}
+
+pub fn handle_args() -> std::io::Result<Args> {
+    let mut args = env::args().skip(1).peekable();
+    let mut parsed = Args::default();
+
+    // the flags combine, exp: `--config work.conf --project --preload setup.rs`
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => {
+                let config = args
+                    .next()
+                    .ok_or_else(|| missing_value("--config needs a config file path"))?;
+                Options::set_config_path(PathBuf::from(config));
+            }
+
+            "--project" => {
+                // the path is optional
+                let path = args.next_if(|arg| !arg.starts_with('-'));
+                parsed.project = Some(find_project(path.as_ref())?);
+            }
+
+            "--preload" | "-i" => {
+                let script = args
+                    .next()
+                    .ok_or_else(|| missing_value("--preload needs a script path"))?;
+                parsed.preload = Some(PathBuf::from(script));
+            }
+
+            "--accessible" => parsed.accessible = true,
+
+            "--tour" => parsed.tour = true,
 
-    if !args.is_empty() {
-        match args[0].as_str() {
             "--reset-config" => {
                 if let Some(config_path) = Options::config_path() {
                     Options::reset_config(config_path);
                 }
+                std::process::exit(0)
             }
 
             "-h" | "--help" => {
@@ -20,21 +56,57 @@ pub fn handle_args() -> std::io::Result<()> {
         version: {}\n
         config file is in {}\n
         --help => shows this message
-        --reset-config => reset IRust configuration to default",
+        --reset-config => reset IRust configuration to default
+        --config [file] => read and write the configuration in this file instead
+        --project [path] => use the cargo project (current directory by default) from the repl
+        --preload, -i [script] => load the script into the repl like :load, then start the repl
+        --tour => start with a guided tour of the repl
+        --accessible => line based mode without colors nor redraws, for screen readers and braille displays
+        the flags can be combined, exp: irust --config work.conf --project --preload setup.rs",
                     VERSION,
                     Options::config_path()
                         .map(|p| p.to_string_lossy().to_string())
                         .unwrap_or_else(|| "??".into())
                 );
+                std::process::exit(0)
             }
 
-            "-v" | "--version" => println!("{}", VERSION),
+            "-v" | "--version" => {
+                println!("{}", VERSION);
+                std::process::exit(0)
+            }
 
-            _ => (),
+            arg => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("Unknown argument `{}`, see --help", arg),
+                ))
+            }
         }
-
-        std::process::exit(0)
     }
 
-    Ok(())
+    Ok(parsed)
+}
+
+fn missing_value(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, message)
+}
+
+/// The nearest directory containing a Cargo.toml, starting from `path`
+fn find_project(path: Option<&String>) -> std::io::Result<PathBuf> {
+    let start = match path {
+        Some(path) => PathBuf::from(path).canonicalize()?,
+        None => env::current_dir()?,
+    };
+
+    start
+        .ancestors()
+        .find(|dir| dir.join("Cargo.toml").exists())
+        .map(ToOwned::to_owned)
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("No cargo project found in {}", start.display()),
+            )
+        })
 }
@@ -1,33 +1,102 @@
-use crossterm::{Color, InputEvent, KeyEvent, TerminalInput};
+use crossterm::{Color, InputEvent, KeyEvent};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::Duration;
 
+mod accessible;
+mod analyzer;
 mod art;
+mod bench;
 mod cargo_cmds;
+mod cells;
+mod checker;
+mod classify;
+mod clean;
+mod cmd_args;
+mod colors;
+mod completion;
 mod cursor;
 mod debouncer;
+mod diff;
+mod docs;
+mod edit_line;
 mod events;
+mod fix;
+mod fmt;
 mod format;
+mod grep;
 mod help;
 mod highlight;
 mod history;
+mod impls;
+mod input;
 mod irust_error;
+mod jobs;
+mod large_input;
+mod load;
+mod locations;
+mod manifest;
+mod miri;
 pub mod options;
+mod pane;
 mod parser;
+mod plot;
 mod printer;
+mod profile;
 mod racer;
 mod repl;
+mod results;
+mod run_args;
+mod run_stdin;
+mod scrollback;
+mod search;
+mod selection;
+mod set;
+mod shell;
+mod source;
+mod target;
+mod tutorial;
+mod undo;
+mod validator;
+mod versioning;
+mod watcher;
 mod writer;
+use analyzer::Analyzer;
+use cells::Cells;
+use checker::Checker;
+use clean::CleanCandidate;
 use cursor::{Cursor, INPUT_START_COL};
 use debouncer::Debouncer;
+use edit_line::LineEdit;
 use history::History;
+use input::{Input, InputReader};
 use irust_error::IRustError;
 use options::Options;
+use pane::Pane;
+use printer::{Printer, ScrolledOutput};
 use racer::Racer;
-use repl::Repl;
+use repl::{EvalTemplate, Repl};
+use results::Results;
+use shell::Shell;
+use tutorial::Tutorial;
+use undo::UndoHistory;
+use validator::Validator;
+use watcher::Watcher;
 mod buffer;
-use buffer::Buffer;
+use buffer::{Buffer, WordCase};
 mod raw_terminal;
 use raw_terminal::RawTerminal;
 
+// how often background results (checks, watched crates) are looked for while waiting for input
+const POLL_TICK: Duration = Duration::from_millis(100);
+
+// set by `--accessible`, overrides the `accessible` option
+static ACCESSIBLE: AtomicBool = AtomicBool::new(false);
+
+// used when a dumb terminal doesn't report its size
+const DUMB_TERMINAL_SIZE: (usize, usize) = (80, 24);
+
 const IN: &str = "In: ";
 const OUT: &str = "Out: ";
 
@@ -40,26 +109,154 @@ pub struct IRust {
     options: Options,
     racer: Result<Racer, IRustError>,
     debouncer: Debouncer,
+    input: mpsc::Receiver<Input>,
+    input_reader: Option<InputReader>,
+    // input received while a job was running
+    pending_input: VecDeque<Input>,
+    checker: Option<Checker>,
+    validator: Option<Validator>,
+    analyzer: Option<Analyzer>,
+    // crate names listed by the last `:search`
+    search_results: Vec<String>,
+    // what the last `:clean` found
+    clean_candidates: Vec<CleanCandidate>,
+    watcher: Watcher,
+    // the last evaluated expression
+    last_eval: Option<String>,
+    // expressions added with `:watch`
+    watches: Vec<String>,
+    // the last value of each watched expression, to highlight what changed
+    watch_values: HashMap<String, String>,
+    // repl bodies saved with `:checkpoint`, compared by `:diff`
+    checkpoints: Vec<(String, Vec<String>)>,
+    session_start: Vec<String>,
+    undo_history: UndoHistory,
+    // the last output when `horizontal_scroll` is set
+    scrolled_output: Option<ScrolledOutput>,
+    // the hidden columns at the start of the input lines when `horizontal_scroll` is set
+    input_offset: usize,
+    // the input line of the cursor when the relative line numbers were drawn
+    numbered_line: usize,
+    tutorial: Option<Tutorial>,
+    // the script given with `--preload`, loaded when the session starts
+    preload: Option<std::path::PathBuf>,
+    // the part of the last output cut by `output_max_lines` or `output_max_bytes`
+    more_output: Option<Printer>,
+    cells: Cells,
+    pane: Pane,
+    // the row of the next output line when the input is pinned
+    output_row: usize,
+    // the last cut or copied selection
+    clipboard: String,
+    // (input it was suggested for, suggestion), drawn after the input by `print_input`
+    inline_suggestion: Option<(String, String)>,
+    // the inputs and outputs of the session, searched by `:grep` and shown by `:scrollback`
+    transcript: Vec<String>,
+    line_edit: LineEdit,
+    // the values of the evaluated expressions, shown again by `:results`
+    results: Results,
+    // the shell of `:sh`, the `::` commands are spawned one by one without it
+    shell: Option<Shell>,
+    // put in the prompt after the output, exp: by `:format`
+    next_input: Option<String>,
+    // the `input_max_lines` warning is shown once until the input gets smaller
+    input_full_warned: bool,
+    // (the `use` lines rustc suggested, the failed input), applied with F4
+    import_fix: Option<(Vec<String>, String)>,
+    // (the missing crate, the failed input), added when `y` answers the question
+    dependency_fix: Option<(String, String)>,
 }
 
 impl IRust {
     pub fn new() -> Self {
+        locations::migrate();
         let raw_terminal = RawTerminal::new();
-        let repl = Repl::new();
-        let history = History::new(dirs::cache_dir().unwrap().join("irust")).unwrap_or_default();
-        let options = Options::new().unwrap_or_default();
-        let debouncer = Debouncer::new();
-        let racer = if options.enable_racer {
-            Racer::start()
+        let mut repl = Repl::new();
+        let session_start = repl.body.clone();
+        let mut options = Options::new().unwrap_or_default();
+        let mut history = History::new(
+            locations::state_dir().unwrap_or_else(|| cargo_cmds::TMP_DIR.join("irust_state")),
+            options.project_history,
+        )
+        .unwrap_or_default();
+        let history_exclude = Some(&options.history_exclude)
+            .filter(|exclude| !exclude.is_empty())
+            .and_then(|exclude| regex::Regex::new(exclude).ok());
+        history.set_exclusions(options.history_ignore_space, history_exclude);
+        repl.prefer_display = options.prefer_display;
+        repl.show_types = options.show_types;
+        if !options.eval_template.is_empty() {
+            repl.template = EvalTemplate::parse(&options.eval_template).ok();
+        }
+        if let Some(sandbox) = cargo_cmds::sandbox_command(&options.sandbox, &options.sandbox_image)
+        {
+            let _ = cargo_cmds::SANDBOX.set(sandbox);
+        }
+        let _ = cargo_cmds::LIMITS.set(cargo_cmds::Limits {
+            memory: options.memory_limit,
+            cpu_time: options.cpu_time_limit,
+        });
+        // dumb terminals get the same line based interface as the accessible mode
+        options.accessible |= ACCESSIBLE.load(Ordering::Relaxed) || dumb_terminal();
+        let debouncer = Debouncer::new(Duration::from_millis(options.racer_delay));
+        // completion, live check and highlighting are drawn in place, the accessible mode goes without them
+        let completion = options.enable_racer
+            || options.completion_session
+            || options.completion_history
+            || options.completion_snippets;
+        let mut racer = if completion && !options.accessible {
+            Racer::start(options.enable_racer)
         } else {
             Err(IRustError::RacerDisabled)
         };
-        let size = {
-            let (width, height) = raw_terminal.size().expect("Error getting terminal size");
-            (width as usize, height as usize)
+        if options.enable_racer && !options.accessible && racer.is_err() {
+            options.config_errors.push(
+                "enable_racer: racer could not be started, racer is disabled (is it installed?)"
+                    .to_string(),
+            );
+            // the other sources don't need the racer daemon
+            if completion {
+                racer = Racer::start(false);
+            }
+        }
+        let size = match raw_terminal.size() {
+            Ok((width, height)) if width as usize > INPUT_START_COL && height > 0 => {
+                (width as usize, height as usize)
+            }
+            _ if options.accessible => DUMB_TERMINAL_SIZE,
+            _ => panic!("Error getting terminal size"),
+        };
+        let checker = if options.live_check && !options.accessible {
+            Some(Checker::start())
+        } else {
+            None
+        };
+        // the cells are the source of the repl code, lines can't be removed from it
+        let validator = if options.validate_insertions && !options.cells && !options.accessible {
+            Some(Validator::start())
+        } else {
+            None
+        };
+        let analyzer = if options.rust_analyzer && !options.accessible {
+            Some(Analyzer::start())
+        } else {
+            None
+        };
+        let pane = Pane::new(
+            options.body_pane && !options.accessible,
+            options.body_pane_width,
+        );
+        let (main, _) = pane.windows(size.0, size.1);
+        let cursor = Cursor::new(0, 0, main.width, main.height);
+        let buffer = Buffer::new(options.input_row_chars(main.width));
+
+        // the accessible mode reads whole lines from stdin instead
+        let (input_send, input) = mpsc::channel();
+        let input_reader = if !options.accessible {
+            Some(InputReader::start(input_send))
+        } else {
+            None
         };
-        let cursor = Cursor::new(0, 0, size.0, size.1);
-        let buffer = Buffer::new(size.0 - INPUT_START_COL);
 
         IRust {
             cursor,
@@ -70,86 +267,274 @@ impl IRust {
             racer,
             debouncer,
             buffer,
+            input,
+            input_reader,
+            pending_input: VecDeque::new(),
+            checker,
+            validator,
+            analyzer,
+            search_results: Vec::new(),
+            clean_candidates: Vec::new(),
+            watcher: Watcher::start(),
+            last_eval: None,
+            watches: Vec::new(),
+            watch_values: HashMap::new(),
+            checkpoints: Vec::new(),
+            session_start,
+            undo_history: UndoHistory::default(),
+            scrolled_output: None,
+            input_offset: 0,
+            numbered_line: 1,
+            tutorial: None,
+            preload: None,
+            more_output: None,
+            cells: Cells::default(),
+            pane,
+            output_row: 0,
+            clipboard: String::new(),
+            inline_suggestion: None,
+            transcript: Vec::new(),
+            line_edit: LineEdit::default(),
+            results: Results::default(),
+            shell: None,
+            next_input: None,
+            input_full_warned: false,
+            import_fix: None,
+            dependency_fix: None,
         }
     }
 
+    /// Link the repl to an existing cargo project, must be called before `run`
+    pub fn set_project(project: std::path::PathBuf) {
+        let _ = cargo_cmds::PROJECT.set(project);
+    }
+
+    /// Load `script` into the repl like `:load` when the session starts
+    pub fn preload(&mut self, script: std::path::PathBuf) {
+        self.preload = Some(script);
+    }
+
+    /// Use the line based accessible mode, must be called before `new`
+    pub fn set_accessible() {
+        ACCESSIBLE.store(true, Ordering::Relaxed);
+    }
+
+    /// Restore the terminal before the panic message, so an IRust bug doesn't leave it in raw mode
+    pub fn set_panic_hook() {
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            // the other threads panics don't stop IRust
+            if std::thread::current().name() == Some("main") {
+                RawTerminal::restore();
+            }
+            default_hook(info);
+        }));
+    }
+
     fn prepare(&mut self) -> Result<(), IRustError> {
         self.repl.prepare_ground()?;
+        self.repl_changed("session start")?;
         self.debouncer.run();
         self.welcome()?;
+        if self.pinned_input() {
+            self.output_row = self.cursor.pos.starting_pos.1;
+            self.pin_input()?;
+        }
+        if let Some(errors) = self.config_errors() {
+            self.print_output(errors)?;
+        }
+        if let Some(outputs) = self.load_preload() {
+            self.print_output(outputs)?;
+        }
+        if self.tutorial.is_some() {
+            let step = self.tutorial_step();
+            self.print_output(step)?;
+        }
         self.write_from_terminal_start(IN, Color::Yellow)?;
+        self.draw_pane(0)?;
         Ok(())
     }
 
     pub fn run(&mut self) -> Result<(), IRustError> {
+        if self.options.accessible {
+            return self.run_accessible();
+        }
         self.prepare()?;
-        let mut stdin = TerminalInput::new().read_sync();
         let _screen = crossterm::RawScreen::into_raw_mode()?;
 
         loop {
             self.check_racer_callback()?;
-            if let Some(key_event) = stdin.next() {
-                match key_event {
-                    InputEvent::Keyboard(KeyEvent::Char(c)) => {
-                        self.handle_character(c)?;
-                    }
-                    InputEvent::Keyboard(KeyEvent::Enter) => {
-                        self.handle_enter()?;
-                    }
-                    InputEvent::Keyboard(KeyEvent::Tab) => {
-                        self.handle_tab()?;
-                    }
-                    InputEvent::Keyboard(KeyEvent::BackTab) => {
-                        self.handle_back_tab()?;
-                    }
-                    InputEvent::Keyboard(KeyEvent::Left) => {
-                        self.handle_left()?;
-                    }
-                    InputEvent::Keyboard(KeyEvent::Right) => {
-                        self.handle_right()?;
-                    }
-                    InputEvent::Keyboard(KeyEvent::Up) => {
-                        self.handle_up()?;
-                    }
-                    InputEvent::Keyboard(KeyEvent::Down) => {
-                        self.handle_down()?;
-                    }
-                    InputEvent::Keyboard(KeyEvent::Backspace) => {
-                        self.handle_backspace()?;
-                    }
-                    InputEvent::Keyboard(KeyEvent::Ctrl('c')) => {
-                        self.handle_ctrl_c()?;
-                    }
-                    InputEvent::Keyboard(KeyEvent::Ctrl('d')) => {
-                        self.handle_ctrl_d()?;
-                    }
-                    InputEvent::Keyboard(KeyEvent::Ctrl('z')) => {
-                        self.handle_ctrl_z()?;
-                    }
-                    InputEvent::Keyboard(KeyEvent::Ctrl('l')) => {
-                        self.handle_ctrl_l()?;
-                    }
-                    InputEvent::Keyboard(KeyEvent::Alt('\r')) => {
-                        self.handle_alt_enter()?;
-                    }
-                    InputEvent::Keyboard(KeyEvent::Home) => {
-                        self.handle_home_key()?;
-                    }
-                    InputEvent::Keyboard(KeyEvent::End) => {
-                        self.handle_end_key()?;
-                    }
-                    InputEvent::Keyboard(KeyEvent::CtrlLeft) => {
-                        self.handle_ctrl_left();
-                    }
-                    InputEvent::Keyboard(KeyEvent::CtrlRight) => {
-                        self.handle_ctrl_right();
-                    }
-                    InputEvent::Keyboard(KeyEvent::Delete) => {
-                        self.handle_del()?;
-                    }
-                    _ => {}
+            self.request_input_check();
+            self.request_input_highlight();
+            let input = match self.pending_input.pop_front() {
+                Some(input) => input,
+                None => self.next_input_event()?,
+            };
+            let key_event = match input {
+                Input::Event(key_event) => key_event,
+                Input::Key(key) => {
+                    self.handle_key(key)?;
+                    self.follow_cursor()?;
+                    continue;
                 }
+                Input::Resize => {
+                    self.handle_resize()?;
+                    continue;
+                }
+            };
+            if self.dependency_fix.is_some() && self.handle_dependency_answer(&key_event)? {
+                continue;
+            }
+            if !selection::keeps_selection(&key_event) {
+                self.clear_selection()?;
+            }
+            match key_event {
+                InputEvent::Keyboard(KeyEvent::Char(c)) => {
+                    self.handle_character(c)?;
+                }
+                InputEvent::Keyboard(KeyEvent::Enter) => {
+                    self.handle_enter()?;
+                }
+                InputEvent::Keyboard(KeyEvent::Tab) => {
+                    self.handle_tab()?;
+                }
+                InputEvent::Keyboard(KeyEvent::BackTab) => {
+                    self.handle_back_tab()?;
+                }
+                InputEvent::Keyboard(KeyEvent::Left) => {
+                    self.handle_left()?;
+                }
+                InputEvent::Keyboard(KeyEvent::Right) => {
+                    self.handle_right()?;
+                }
+                InputEvent::Keyboard(KeyEvent::Up) => {
+                    self.handle_up()?;
+                }
+                InputEvent::Keyboard(KeyEvent::Down) => {
+                    self.handle_down()?;
+                }
+                InputEvent::Keyboard(KeyEvent::Backspace) => {
+                    self.handle_backspace()?;
+                }
+                InputEvent::Keyboard(KeyEvent::Ctrl('c')) => {
+                    self.handle_ctrl_c()?;
+                }
+                InputEvent::Keyboard(KeyEvent::Ctrl('d')) => {
+                    self.handle_ctrl_d()?;
+                }
+                InputEvent::Keyboard(KeyEvent::Ctrl('z')) => {
+                    self.handle_ctrl_z()?;
+                }
+                InputEvent::Keyboard(KeyEvent::Ctrl('l')) => {
+                    self.handle_ctrl_l()?;
+                }
+                InputEvent::Keyboard(KeyEvent::Alt('\r')) => {
+                    self.handle_alt_enter()?;
+                }
+                InputEvent::Keyboard(KeyEvent::Home) => {
+                    self.handle_home_key()?;
+                }
+                InputEvent::Keyboard(KeyEvent::End) => {
+                    self.handle_end_key()?;
+                }
+                // for the terminals where Ctrl-Home and Ctrl-End are not read
+                InputEvent::Keyboard(KeyEvent::PageUp) => {
+                    self.handle_ctrl_home()?;
+                }
+                InputEvent::Keyboard(KeyEvent::PageDown) => {
+                    self.handle_ctrl_end()?;
+                }
+                InputEvent::Keyboard(KeyEvent::CtrlLeft) => {
+                    self.handle_ctrl_left();
+                }
+                InputEvent::Keyboard(KeyEvent::CtrlRight) => {
+                    self.handle_ctrl_right();
+                }
+                InputEvent::Keyboard(KeyEvent::Delete) => {
+                    self.handle_del()?;
+                }
+                InputEvent::Keyboard(KeyEvent::ShiftLeft) => {
+                    self.handle_select("left")?;
+                }
+                InputEvent::Keyboard(KeyEvent::ShiftRight) => {
+                    self.handle_select("right")?;
+                }
+                InputEvent::Keyboard(KeyEvent::ShiftUp) => {
+                    self.handle_select("up")?;
+                }
+                InputEvent::Keyboard(KeyEvent::ShiftDown) => {
+                    self.handle_select("down")?;
+                }
+                InputEvent::Keyboard(KeyEvent::Ctrl('x')) => {
+                    self.handle_cut()?;
+                }
+                InputEvent::Keyboard(KeyEvent::Alt('w')) => {
+                    self.handle_copy()?;
+                }
+                InputEvent::Keyboard(KeyEvent::Ctrl('y')) => {
+                    self.handle_paste()?;
+                }
+                InputEvent::Keyboard(KeyEvent::Ctrl('e')) => {
+                    self.handle_ctrl_e();
+                }
+                InputEvent::Keyboard(KeyEvent::Ctrl('o')) => {
+                    self.handle_ctrl_o()?;
+                }
+                InputEvent::Keyboard(KeyEvent::Ctrl('t')) => {
+                    self.handle_ctrl_t()?;
+                }
+                InputEvent::Keyboard(KeyEvent::Alt('u')) => {
+                    self.handle_word_case(WordCase::Upper)?;
+                }
+                InputEvent::Keyboard(KeyEvent::Alt('l')) => {
+                    self.handle_word_case(WordCase::Lower)?;
+                }
+                InputEvent::Keyboard(KeyEvent::Alt('c')) => {
+                    self.handle_word_case(WordCase::Capitalize)?;
+                }
+                // like Alt-Left and Alt-Right, for the terminals sending them as Alt-b and Alt-f (macOS)
+                InputEvent::Keyboard(KeyEvent::Alt('b')) => {
+                    self.scroll_output(false)?;
+                }
+                InputEvent::Keyboard(KeyEvent::Alt('f')) => {
+                    self.scroll_output(true)?;
+                }
+                InputEvent::Keyboard(KeyEvent::F(2)) => {
+                    self.toggle_pane()?;
+                }
+                InputEvent::Keyboard(KeyEvent::F(3)) => {
+                    self.handle_format_input()?;
+                }
+                InputEvent::Keyboard(KeyEvent::F(4)) => {
+                    self.handle_import_fix()?;
+                }
+                _ => {}
+            }
+            self.follow_cursor()?;
+        }
+    }
+
+    /// Wait for the next input event, handling background results while waiting
+    fn next_input_event(&mut self) -> Result<Input, IRustError> {
+        loop {
+            match self.input.recv_timeout(POLL_TICK) {
+                Ok(input) => return Ok(input),
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    self.check_input_callback()?;
+                    self.check_highlight_callback()?;
+                    self.check_watch_callback()?;
+                    self.check_validation_callback()?;
+                }
+                // the terminal is gone, like Ctrl-d on an empty input
+                Err(mpsc::RecvTimeoutError::Disconnected) => self.exit()?,
             }
         }
     }
 }
+
+/// `TERM=dumb` or a terminal that can't be put in raw mode (emacs shell, CI logs, piped stdin)
+fn dumb_terminal() -> bool {
+    std::env::var("TERM").is_ok_and(|term| term == "dumb")
+        // raw mode is disabled again when the screen is dropped
+        || crossterm::RawScreen::into_raw_mode().is_err()
+}
@@ -0,0 +1,82 @@
+use super::printer::Printer;
+use crate::irust::printer::{PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError, IN};
+use std::io::{self, BufRead, Write};
+
+const CONTINUATION: &str = "..: ";
+
+impl IRust {
+    /// Line based repl for screen readers and braille displays:
+    /// no raw mode, no redraw in place and no colors, every output is appended after the input
+    pub fn run_accessible(&mut self) -> Result<(), IRustError> {
+        // Ctrl-c cancels the evaluation, the session goes on
+        super::jobs::catch_interrupts();
+        self.repl.prepare_ground()?;
+        self.repl_changed("session start")?;
+
+        let welcome = if self.options.welcome_msg.is_empty() {
+            "Welcome to IRust".to_string()
+        } else {
+            self.options.welcome_msg.clone()
+        };
+        println!("{}\n", welcome);
+        if let Some(outputs) = self.load_preload() {
+            self.print_output(outputs)?;
+        }
+        if self.tutorial.is_some() {
+            let step = self.tutorial_step();
+            self.print_output(step)?;
+        }
+
+        let stdin = io::stdin();
+        let mut stdin = stdin.lock();
+        while let Some(input) = self.read_input(&mut stdin)? {
+            self.buffer.clear();
+            self.buffer.insert_str(&input);
+            if self.should_push_to_history(&input) {
+                self.history.push(input.clone());
+            }
+
+            let mut output = match self.parse() {
+                Ok(out) => out,
+                Err(e) => Printer::new(PrinterItem::new(e.to_string(), PrinterItemType::Err)),
+            };
+            self.buffer.clear();
+
+            let ok = !output.has_errors();
+            if !output.is_empty() {
+                output.add_new_line(1);
+                self.print_output(output)?;
+            }
+            self.check_tutorial_input(&input, ok)?;
+        }
+
+        // end of input (ctrl-d)
+        println!();
+        self.exit()
+    }
+
+    /// Read an input, continuation lines are read while it's incomplete, `None` at the end of stdin
+    fn read_input(&self, stdin: &mut impl BufRead) -> Result<Option<String>, IRustError> {
+        let mut input = String::new();
+        let mut prompt = IN;
+        loop {
+            print!("{}", prompt);
+            io::stdout().flush()?;
+
+            let mut line = String::new();
+            if stdin.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            if !input.is_empty() {
+                input.push('\n');
+            }
+            input.push_str(line.trim_end_matches(['\n', '\r']));
+
+            if !self.incomplete_input(&input) {
+                return Ok(Some(input));
+            }
+            prompt = CONTINUATION;
+        }
+    }
+}
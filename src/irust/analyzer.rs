@@ -0,0 +1,390 @@
+use super::cargo_cmds::{IRUST_DIR, MAIN_FILE};
+use super::repl::Repl;
+use crate::irust::{IRust, IRustError};
+use crossterm::Color;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::ops::Range;
+use std::process::{ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
+
+// wait for the user to stop typing before asking rust-analyzer
+const DEBOUNCE: Duration = Duration::from_millis(150);
+
+#[derive(Clone, Copy)]
+pub enum TokenKind {
+    Type,
+    Function,
+    Macro,
+    Mutable,
+}
+
+/// chars of the input and their kind
+pub type SemanticTokens = Vec<(Range<usize>, TokenKind)>;
+
+struct HighlightRequest {
+    code: String,
+    input: String,
+    // line of the code where the input starts
+    input_line: usize,
+}
+
+/// Asks rust-analyzer for the semantic tokens of the input while it's typed
+pub struct Analyzer {
+    send: mpsc::Sender<HighlightRequest>,
+    recv: mpsc::Receiver<(String, SemanticTokens)>,
+    last_input: String,
+    // (highlighted input, its tokens)
+    tokens: Option<(String, SemanticTokens)>,
+}
+
+impl Analyzer {
+    pub fn start() -> Self {
+        let (send, requests) = mpsc::channel::<HighlightRequest>();
+        let (results, recv) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            // without rust-analyzer the input keeps the lexical highlighting
+            let mut server = match LanguageServer::start() {
+                Ok(server) => server,
+                Err(_) => return,
+            };
+            while let Ok(mut request) = requests.recv() {
+                // only the latest input matters
+                std::thread::sleep(DEBOUNCE);
+                while let Ok(newer) = requests.try_recv() {
+                    request = newer;
+                }
+
+                let tokens = match server.semantic_tokens(&request.code) {
+                    Ok(tokens) => input_tokens(&tokens, &request),
+                    Err(_) => return,
+                };
+                if results.send((request.input, tokens)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            send,
+            recv,
+            last_input: String::new(),
+            tokens: None,
+        }
+    }
+
+    /// Queue a highlight of `input` inside the repl if it changed since the last request
+    fn request(&mut self, repl: &Repl, input: &str) {
+        if input == self.last_input {
+            return;
+        }
+        self.last_input = input.to_owned();
+
+        // irust commands and shell commands are not rust code
+        if input.trim().is_empty() || input.starts_with(':') {
+            return;
+        }
+
+        let (code, input_line) = repl.code_for_input(input);
+        let _ = self.send.send(HighlightRequest {
+            code,
+            input: input.to_owned(),
+            input_line,
+        });
+    }
+
+    /// Returns true if new tokens arrived
+    fn poll(&mut self) -> bool {
+        let mut changed = false;
+        while let Ok(tokens) = self.recv.try_recv() {
+            changed = true;
+            self.tokens = Some(tokens);
+        }
+        changed
+    }
+
+    fn tokens(&self, input: &str) -> Option<&SemanticTokens> {
+        match &self.tokens {
+            Some((highlighted, tokens)) if highlighted == input => Some(tokens),
+            _ => None,
+        }
+    }
+}
+
+/// (line, utf16 start column, utf16 length, kind) of the whole code
+type CodeTokens = Vec<(usize, usize, usize, TokenKind)>;
+
+// keep the tokens that are in the input, as chars ranges of the input
+fn input_tokens(tokens: &CodeTokens, request: &HighlightRequest) -> SemanticTokens {
+    let lines: Vec<&str> = request.input.split('\n').collect();
+    let line_starts: Vec<usize> = lines
+        .iter()
+        .scan(0, |start, line| {
+            let line_start = *start;
+            *start += line.chars().count() + 1;
+            Some(line_start)
+        })
+        .collect();
+
+    // utf16 column => char index in the line
+    let char_idx = |line: &str, col: usize| {
+        let mut utf16 = 0;
+        line.chars()
+            .take_while(|c| {
+                utf16 += c.len_utf16();
+                utf16 <= col
+            })
+            .count()
+    };
+
+    tokens
+        .iter()
+        .filter_map(|(line, col, len, kind)| {
+            let input_line = line.checked_sub(request.input_line)?;
+            let text = lines.get(input_line)?;
+            let start = char_idx(text, *col);
+            let end = char_idx(text, col + len);
+            Some((
+                line_starts[input_line] + start..line_starts[input_line] + end,
+                *kind,
+            ))
+        })
+        .collect()
+}
+
+/// A minimal language server protocol client
+struct LanguageServer {
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+    version: u64,
+    // legend indices
+    token_types: Vec<String>,
+    token_modifiers: Vec<String>,
+}
+
+impl LanguageServer {
+    fn start() -> io::Result<Self> {
+        let mut process = Command::new("rust-analyzer")
+            .current_dir(&*IRUST_DIR)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let mut server = Self {
+            stdin: process.stdin.take().unwrap(),
+            stdout: BufReader::new(process.stdout.take().unwrap()),
+            next_id: 0,
+            version: 0,
+            token_types: vec![],
+            token_modifiers: vec![],
+        };
+
+        let result = server.request(
+            "initialize",
+            json!({
+                "processId": std::process::id(),
+                "rootUri": file_uri(&IRUST_DIR),
+                "capabilities": {
+                    "textDocument": {
+                        "semanticTokens": {
+                            "requests": { "full": true },
+                            "tokenTypes": [],
+                            "tokenModifiers": [],
+                            "formats": ["relative"]
+                        }
+                    }
+                }
+            }),
+        )?;
+        let legend = &result["capabilities"]["semanticTokensProvider"]["legend"];
+        let strings = |value: &Value| -> Vec<String> {
+            value
+                .as_array()
+                .map(|values| {
+                    values
+                        .iter()
+                        .map(|v| v.as_str().unwrap_or_default().to_owned())
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        server.token_types = strings(&legend["tokenTypes"]);
+        server.token_modifiers = strings(&legend["tokenModifiers"]);
+
+        server.notify("initialized", json!({}))?;
+        server.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": {
+                    "uri": file_uri(&MAIN_FILE),
+                    "languageId": "rust",
+                    "version": 0,
+                    "text": ""
+                }
+            }),
+        )?;
+
+        Ok(server)
+    }
+
+    fn semantic_tokens(&mut self, code: &str) -> io::Result<CodeTokens> {
+        self.version += 1;
+        self.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": file_uri(&MAIN_FILE), "version": self.version },
+                "contentChanges": [{ "text": code }]
+            }),
+        )?;
+        let result = self.request(
+            "textDocument/semanticTokens/full",
+            json!({ "textDocument": { "uri": file_uri(&MAIN_FILE) } }),
+        )?;
+
+        // [delta line, delta start, length, type, modifiers] for each token
+        let data: Vec<usize> = result["data"]
+            .as_array()
+            .map(|data| {
+                data.iter()
+                    .filter_map(|v| v.as_u64())
+                    .map(|v| v as usize)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut tokens = vec![];
+        let (mut line, mut col) = (0, 0);
+        for token in data.chunks_exact(5) {
+            if token[0] != 0 {
+                line += token[0];
+                col = 0;
+            }
+            col += token[1];
+            if let Some(kind) = self.token_kind(token[3], token[4]) {
+                tokens.push((line, col, token[2], kind));
+            }
+        }
+        Ok(tokens)
+    }
+
+    fn token_kind(&self, token_type: usize, modifiers: usize) -> Option<TokenKind> {
+        let mutable = self
+            .token_modifiers
+            .iter()
+            .enumerate()
+            .any(|(idx, modifier)| modifier == "mutable" && modifiers & (1 << idx) != 0);
+
+        match self.token_types.get(token_type)?.as_str() {
+            "struct" | "enum" | "union" | "interface" | "typeAlias" | "typeParameter"
+            | "builtinType" | "selfType" => Some(TokenKind::Type),
+            "function" | "method" => Some(TokenKind::Function),
+            "macro" => Some(TokenKind::Macro),
+            "variable" | "parameter" if mutable => Some(TokenKind::Mutable),
+            _ => None,
+        }
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> io::Result<Value> {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))?;
+
+        loop {
+            let message = self.receive()?;
+            match (message.get("id"), message.get("method")) {
+                // a request from the server, none of them needs a real answer
+                (Some(server_id), Some(_)) => {
+                    let server_id = server_id.clone();
+                    self.send(json!({ "jsonrpc": "2.0", "id": server_id, "result": null }))?;
+                }
+                (Some(response_id), None) if response_id.as_u64() == Some(id) => {
+                    return Ok(message["result"].clone());
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> io::Result<()> {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    fn send(&mut self, message: Value) -> io::Result<()> {
+        let message = message.to_string();
+        write!(
+            self.stdin,
+            "Content-Length: {}\r\n\r\n{}",
+            message.len(),
+            message
+        )?;
+        self.stdin.flush()
+    }
+
+    fn receive(&mut self) -> io::Result<Value> {
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            if self.stdout.read_line(&mut header)? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "rust-analyzer exited",
+                ));
+            }
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length:") {
+                length = value.trim().parse().unwrap_or_default();
+            }
+        }
+
+        let mut content = vec![0; length];
+        self.stdout.read_exact(&mut content)?;
+        serde_json::from_slice(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+fn file_uri(path: &std::path::Path) -> String {
+    format!("file://{}", path.display())
+}
+
+impl IRust {
+    pub fn request_input_highlight(&mut self) {
+        if let Some(analyzer) = self.analyzer.as_mut() {
+            analyzer.request(&self.repl, &self.buffer.to_string());
+        }
+    }
+
+    /// Colors of the input chars highlighted by rust-analyzer
+    pub fn semantic_colors(&self) -> Vec<(Range<usize>, Color)> {
+        let tokens = match self.analyzer.as_ref() {
+            Some(analyzer) => analyzer.tokens(&self.buffer.to_string()),
+            None => None,
+        };
+        tokens
+            .into_iter()
+            .flatten()
+            .map(|(range, kind)| {
+                let color = match kind {
+                    TokenKind::Type => self.options.semantic_type_color,
+                    TokenKind::Function => self.options.semantic_function_color,
+                    TokenKind::Macro => self.options.semantic_macro_color,
+                    TokenKind::Mutable => self.options.semantic_mutable_color,
+                };
+                (range.clone(), color)
+            })
+            .collect()
+    }
+
+    /// Redraw the input when its semantic tokens arrive
+    pub fn check_highlight_callback(&mut self) -> Result<(), IRustError> {
+        if self.analyzer.as_mut().map(Analyzer::poll) == Some(true) {
+            self.print_input()?;
+        }
+        Ok(())
+    }
+}
@@ -1,65 +1,24 @@
+use crate::irust::format::format_build_error;
 use crate::irust::{IRust, IRustError};
-use crossterm::{ClearType, Color};
-use std::io::Read;
+use crossterm::ClearType;
 
 impl IRust {
     pub fn wait_add(
         &mut self,
-        mut add_cmd: std::process::Child,
+        add_cmd: std::process::Child,
         msg: &str,
-    ) -> Result<(), IRustError> {
+    ) -> Result<std::process::Output, IRustError> {
         self.cursor.hide();
-        self.raw_terminal.set_fg(Color::Cyan)?;
-
-        match self.wait_add_inner(&mut add_cmd, msg) {
-            Ok(()) => {
-                self.clean_art()?;
-
-                if let Some(stderr) = add_cmd.stderr.as_mut() {
-                    let mut error = String::new();
-                    stderr.read_to_string(&mut error)?;
-                    if !error.is_empty() {
-                        return Err(IRustError::Custom(error));
-                    }
-                }
-                Ok(())
-            }
-            Err(e) => {
-                self.clean_art()?;
-                Err(e)
-            }
-        }
-    }
-
-    fn wait_add_inner(
-        &mut self,
-        add_cmd: &mut std::process::Child,
-        msg: &str,
-    ) -> Result<(), IRustError> {
-        self.write_str_at(
-            &format!(" {}ing dep [\\]", msg),
-            0,
-            self.cursor.pos.current_pos.1,
-        )?;
-        loop {
-            match add_cmd.try_wait() {
-                Ok(None) => {
-                    self.write_str_at("\\", msg.len() + 10, self.cursor.pos.current_pos.1)?;
-                    self.write_str_at("|", msg.len() + 10, self.cursor.pos.current_pos.1)?;
-                    self.write_str_at("/", msg.len() + 10, self.cursor.pos.current_pos.1)?;
-                    self.write_str_at("-", msg.len() + 10, self.cursor.pos.current_pos.1)?;
-                    self.write_str_at("\\", msg.len() + 10, self.cursor.pos.current_pos.1)?;
-                    self.write_str_at("|", msg.len() + 10, self.cursor.pos.current_pos.1)?;
-                    self.write_str_at("/", msg.len() + 10, self.cursor.pos.current_pos.1)?;
-                    self.write_str_at("-", msg.len() + 10, self.cursor.pos.current_pos.1)?;
-                    continue;
-                }
-                Err(e) => {
-                    return Err(e.into());
-                }
-                Ok(Some(_)) => return Ok(()),
-            }
+        let output = self.wait_job(add_cmd, msg);
+        self.clean_art()?;
+
+        let output = output?;
+        if !output.status.success() {
+            return Err(IRustError::Custom(format_build_error(
+                &String::from_utf8_lossy(&output.stderr),
+            )));
         }
+        Ok(output)
     }
 
     fn clean_art(&mut self) -> Result<(), IRustError> {
@@ -92,9 +51,7 @@ impl IRust {
 
     fn fit_msg(&mut self, msg: &str) -> String {
         let slash_num = self.cursor.bound.width - msg.len();
-        let slash = std::iter::repeat('-')
-            .take(slash_num / 2)
-            .collect::<String>();
+        let slash = "-".repeat(slash_num / 2);
 
         format!("{0}{1}{0}", slash, msg)
     }
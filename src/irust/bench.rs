@@ -0,0 +1,86 @@
+use super::cmd_args::CmdArgs;
+use crate::irust::format::format_eval_output;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::repl::Repl;
+use crate::irust::{IRust, IRustError};
+use crate::utils::stdout_and_stderr;
+
+const DEFAULT_ITERATIONS: usize = 100;
+
+impl IRust {
+    pub fn bench(&mut self) -> Result<Printer, IRustError> {
+        // exp: :bench v.iter().sum::<u64>(), :bench -n 1000 s.to_uppercase()
+        let mut args = CmdArgs::parse_code("bench", &self.buffer.to_string());
+        let iterations = if args.take_flag("-n") {
+            let n = args.take_arg()?.ok_or_else(|| args.usage_error())?;
+            match n.parse::<usize>() {
+                Ok(n) if n > 0 => n,
+                _ => {
+                    return Err(IRustError::Custom(format!(
+                        "Incorrect iterations count `{}`",
+                        n
+                    )))
+                }
+            }
+        } else {
+            DEFAULT_ITERATIONS
+        };
+        let expression = args.expression();
+        if expression.is_empty() {
+            return Err(IRustError::Custom("No expression specified".to_string()));
+        }
+
+        let job = self.repl.bench(expression, iterations)?;
+        let output = self.wait_job(job, "Benchmarking")?;
+        if !output.status.success() {
+            let mut outputs = format_eval_output(&stdout_and_stderr(output));
+            outputs.add_new_line(1);
+            return Ok(outputs);
+        }
+        let times = Repl::bench_output(&String::from_utf8_lossy(&output.stdout))
+            .filter(|times| !times.is_empty())
+            .ok_or_else(|| IRustError::Custom("Unexpected benchmark output".to_string()))?;
+
+        let (min, mean, max, std_dev) = stats(&times);
+        let mut outputs = Printer::new(PrinterItem::new(
+            format!(
+                "min {} · mean {} · max {} · std dev {}",
+                format_nanos(min),
+                format_nanos(mean),
+                format_nanos(max),
+                format_nanos(std_dev)
+            ),
+            PrinterItemType::Eval,
+        ));
+        outputs.add_new_line(1);
+        outputs.push(PrinterItem::new(
+            format!("{} iterations in release mode", times.len()),
+            PrinterItemType::Warn,
+        ));
+        outputs.add_new_line(1);
+
+        Ok(outputs)
+    }
+}
+
+// (min, mean, max, standard deviation)
+fn stats(times: &[f64]) -> (f64, f64, f64, f64) {
+    let min = times.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = times.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let mean = times.iter().sum::<f64>() / times.len() as f64;
+    let variance = times.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / times.len() as f64;
+    (min, mean, max, variance.sqrt())
+}
+
+// exp: 850ns, 12.3µs, 4.56ms, 1.20s
+fn format_nanos(nanos: f64) -> String {
+    if nanos < 1e3 {
+        format!("{:.0}ns", nanos)
+    } else if nanos < 1e6 {
+        format!("{:.1}µs", nanos / 1e3)
+    } else if nanos < 1e9 {
+        format!("{:.2}ms", nanos / 1e6)
+    } else {
+        format!("{:.2}s", nanos / 1e9)
+    }
+}
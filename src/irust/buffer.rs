@@ -1,8 +1,18 @@
+use std::ops::Range;
+
+pub enum WordCase {
+    Upper,
+    Lower,
+    Capitalize,
+}
+
 #[derive(Clone, Default)]
 pub struct Buffer {
     pub buffer: Vec<char>,
     pub buffer_pos: usize,
     max_line_char: usize,
+    // the other end of the selection, set by Shift+arrows
+    selection_anchor: Option<usize>,
 }
 
 impl Buffer {
@@ -22,12 +32,16 @@ impl Buffer {
         s.chars().for_each(|c| self.insert(c));
     }
 
+    pub fn set_max_line_char(&mut self, max_line_char: usize) {
+        self.max_line_char = max_line_char;
+    }
+
     pub fn set_buffer_pos(&mut self, pos: usize) {
         self.buffer_pos = pos;
     }
 
     pub fn remove_current_char(&mut self) -> Option<char> {
-        if !self.is_empty() {
+        if !self.is_at_end() {
             let character = self.buffer.remove(self.buffer_pos);
             Some(character)
         } else {
@@ -64,6 +78,79 @@ impl Buffer {
     pub fn clear(&mut self) {
         self.buffer.clear();
         self.buffer_pos = 0;
+        self.selection_anchor = None;
+    }
+
+    /// readline's transpose-chars, at the end of the buffer the two last characters are swapped
+    pub fn transpose_chars(&mut self) -> bool {
+        if self.buffer.len() < 2 || self.is_at_start() {
+            return false;
+        }
+        if self.is_at_end() {
+            self.buffer_pos -= 1;
+        }
+        self.buffer.swap(self.buffer_pos - 1, self.buffer_pos);
+        self.buffer_pos += 1;
+        true
+    }
+
+    /// readline's upcase/downcase/capitalize-word: change the case of the rest of the word,
+    /// or of the next word, and move after it
+    pub fn change_word_case(&mut self, case: WordCase) {
+        let mut pos = self.buffer_pos;
+        while pos < self.buffer.len() && !self.buffer[pos].is_alphanumeric() {
+            pos += 1;
+        }
+        let mut first = true;
+        while pos < self.buffer.len() && self.buffer[pos].is_alphanumeric() {
+            let c = self.buffer[pos];
+            // some characters change length with their case, example ß -> SS
+            let changed: Vec<char> = match case {
+                WordCase::Upper => c.to_uppercase().collect(),
+                WordCase::Capitalize if first => c.to_uppercase().collect(),
+                WordCase::Lower | WordCase::Capitalize => c.to_lowercase().collect(),
+            };
+            first = false;
+            let len = changed.len();
+            self.buffer.splice(pos..=pos, changed);
+            pos += len;
+        }
+        self.buffer_pos = pos;
+    }
+
+    pub fn start_selection(&mut self) {
+        if self.selection_anchor.is_none() {
+            self.selection_anchor = Some(self.buffer_pos);
+        }
+    }
+
+    /// Returns whether there was a selection
+    pub fn clear_selection(&mut self) -> bool {
+        self.selection_anchor.take().is_some()
+    }
+
+    /// The selected range, None if nothing is selected
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let anchor = std::cmp::min(self.selection_anchor?, self.buffer.len());
+        let pos = std::cmp::min(self.buffer_pos, self.buffer.len());
+        if anchor == pos {
+            None
+        } else {
+            Some(std::cmp::min(anchor, pos)..std::cmp::max(anchor, pos))
+        }
+    }
+
+    pub fn selected_text(&self) -> Option<String> {
+        self.selection()
+            .map(|selection| self.buffer[selection].iter().collect())
+    }
+
+    /// Remove the selected text and return it, the buffer position is put at its start
+    pub fn remove_selection(&mut self) -> Option<String> {
+        let selection = self.selection()?;
+        self.selection_anchor = None;
+        self.buffer_pos = selection.start;
+        Some(self.buffer.drain(selection).collect())
     }
 
     pub fn len(&self) -> usize {
@@ -124,6 +211,48 @@ impl Buffer {
         (x, y)
     }
 
+    /// The reverse of `buffer_pos_to_relative_cursor_pos`, a column past the end of
+    /// the row gives the row end
+    pub fn relative_cursor_pos_to_buffer_pos(&self, pos: (usize, usize)) -> usize {
+        let (mut x, mut y) = (0, 0);
+        let mut buffer_pos = self.buffer.len();
+        for i in 0..=self.buffer.len() {
+            if y == pos.1 && x <= pos.0 {
+                buffer_pos = i;
+            } else if y > pos.1 {
+                break;
+            }
+            match self.buffer.get(i) {
+                Some('\n') => {
+                    x = 0;
+                    y += 1;
+                }
+                _ => x += 1,
+            };
+            if x == self.max_line_char {
+                x = 0;
+                y += 1;
+            }
+        }
+        buffer_pos
+    }
+
+    /// Start of the visual row of the buffer position
+    pub fn row_start(&self) -> usize {
+        let row = self.buffer_pos_to_relative_cursor_pos(self.buffer_pos).1;
+        self.relative_cursor_pos_to_buffer_pos((0, row))
+    }
+
+    /// End of the visual row of the buffer position, a wrapped row ends at its last character
+    pub fn row_end(&self) -> usize {
+        let row = self.buffer_pos_to_relative_cursor_pos(self.buffer_pos).1;
+        self.relative_cursor_pos_to_buffer_pos((usize::MAX, row))
+    }
+
+    pub fn last_row(&self) -> usize {
+        self.last_buffer_pos_to_relative_cursor_pos().1
+    }
+
     pub fn last_buffer_pos_to_relative_cursor_pos(&self) -> (usize, usize) {
         self.buffer_pos_to_relative_cursor_pos(self.buffer.len())
     }
@@ -133,6 +262,7 @@ impl Buffer {
             buffer: str.chars().collect(),
             buffer_pos: 0,
             max_line_char,
+            selection_anchor: None,
         }
     }
 
@@ -149,8 +279,8 @@ impl Buffer {
     }
 }
 
-impl ToString for Buffer {
-    fn to_string(&self) -> String {
-        self.buffer.iter().collect()
+impl std::fmt::Display for Buffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.buffer.iter().collect::<String>())
     }
 }
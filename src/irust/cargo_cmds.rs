@@ -1,62 +1,851 @@
-use crate::utils::stdout_and_stderr;
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
 use std::env::temp_dir;
 use std::fs;
 use std::io;
 use std::io::prelude::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 
 pub static TMP_DIR: Lazy<PathBuf> = Lazy::new(temp_dir);
 pub static IRUST_DIR: Lazy<PathBuf> = Lazy::new(|| TMP_DIR.join("irust"));
 pub static MAIN_FILE: Lazy<PathBuf> = Lazy::new(|| IRUST_DIR.join("src/main.rs"));
+pub static CARGO_CONFIG: Lazy<PathBuf> = Lazy::new(|| IRUST_DIR.join(".cargo/config.toml"));
+// kept outside of IRUST_DIR so compiled dependencies (especially native ones) survive across sessions
+pub static TARGET_DIR: Lazy<PathBuf> = Lazy::new(|| {
+    super::locations::cache_dir()
+        .unwrap_or_else(|| TMP_DIR.join("irust_cache"))
+        .join("target")
+});
+// `cargo check` runs while typing, it gets its own project and target dir so it never waits for the repl builds
+pub static CHECK_DIR: Lazy<PathBuf> = Lazy::new(|| TMP_DIR.join("irust_check"));
+pub static CHECK_TARGET_DIR: Lazy<PathBuf> = Lazy::new(|| {
+    super::locations::cache_dir()
+        .unwrap_or_else(|| TMP_DIR.join("irust_cache"))
+        .join("check_target")
+});
+// nightly builds don't share the stable artifacts
+pub static NIGHTLY_TARGET_DIR: Lazy<PathBuf> = Lazy::new(|| {
+    super::locations::cache_dir()
+        .unwrap_or_else(|| TMP_DIR.join("irust_cache"))
+        .join("nightly_target")
+});
+// `path = "` in a dependency, the quote is captured
+static PATH_KEY: Lazy<regex::Regex> =
+    Lazy::new(|| regex::Regex::new(r#"(?:^|[\s{,.])path\s*=\s*(["'])"#).unwrap());
+// the cargo project linked with `--project`
+pub static PROJECT: OnceCell<PathBuf> = OnceCell::new();
+// the `:cargo` additions to the repl Cargo.toml, written again when the repl crate is recreated
+pub static CARGO_FRAGMENTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+// the `:cargo` additions start after this line
+const FRAGMENTS_MARK: &str = "# added with :cargo";
+// the command the evaluations are run in, set from the `sandbox` option
+pub static SANDBOX: OnceCell<Vec<String>> = OnceCell::new();
+pub const SANDBOXES: [&str; 4] = ["none", "bwrap", "docker", "podman"];
+// set from the `memory_limit` and `cpu_time_limit` options
+pub static LIMITS: OnceCell<Limits> = OnceCell::new();
+// the target selected with `:target`, the host when `None`
+pub static TARGET: Lazy<Mutex<Option<Target>>> = Lazy::new(|| Mutex::new(None));
+// set with `:miri on`, the evaluations are interpreted by miri to detect undefined behavior
+pub static MIRI: AtomicBool = AtomicBool::new(false);
+// set with `:set allocator`, the global allocator of the evaluations
+pub static ALLOCATOR: Mutex<Allocator> = Mutex::new(Allocator::System);
+// set with `:args`, the arguments the evaluations are run with
+pub static RUN_ARGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+// set with `:stdin`, the data piped to the evaluations stdin
+pub static RUN_STDIN: Mutex<Option<Vec<u8>>> = Mutex::new(None);
+// set with `:set log <level>`, the RUST_LOG of the evaluations, an env_logger is set up for them
+pub static RUST_LOG: Mutex<Option<String>> = Mutex::new(None);
+// ends the dependency lines added by `:set allocator` and `:set log`
+const SESSION_DEPENDENCY_MARK: &str = "# added with :set";
+// set with `:set panic abort`, panics abort the evaluations instead of unwinding
+pub static PANIC_ABORT: AtomicBool = AtomicBool::new(false);
+
+pub struct Target {
+    pub triple: String,
+    // the program the compiled binaries are run with (exp: wasmtime, qemu-aarch64),
+    // without one the inputs are only built
+    pub runner: Option<Vec<String>>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Allocator {
+    System,
+    Jemalloc,
+    Mimalloc,
+}
+
+impl Allocator {
+    pub const NAMES: [&'static str; 3] = ["system", "jemalloc", "mimalloc"];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "system" => Some(Allocator::System),
+            "jemalloc" => Some(Allocator::Jemalloc),
+            "mimalloc" => Some(Allocator::Mimalloc),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Allocator::System => "system",
+            Allocator::Jemalloc => "jemalloc",
+            Allocator::Mimalloc => "mimalloc",
+        }
+    }
+
+    fn dependency(self) -> Option<&'static str> {
+        match self {
+            Allocator::System => None,
+            Allocator::Jemalloc => Some("tikv-jemallocator = \"0.5\""),
+            Allocator::Mimalloc => Some("mimalloc = \"0.1\""),
+        }
+    }
+
+    /// The `#[global_allocator]` item added after the repl main function,
+    /// the system allocator is already the default one
+    pub fn item(self) -> Option<String> {
+        let ty = match self {
+            Allocator::System => return None,
+            Allocator::Jemalloc => "tikv_jemallocator::Jemalloc",
+            Allocator::Mimalloc => "mimalloc::MiMalloc",
+        };
+        Some(format!(
+            "#[global_allocator] static IRUST_ALLOCATOR: {0} = {0};",
+            ty
+        ))
+    }
+}
+
+/// Resource limits of the evaluated binaries, 0 means no limit
+pub struct Limits {
+    // in MiB
+    pub memory: usize,
+    // in seconds
+    pub cpu_time: usize,
+}
+
+impl Limits {
+    /// Set the limits with the shell `ulimit` before running the binary given as argument
+    fn wrapper(&self) -> Option<Vec<String>> {
+        let mut script = String::new();
+        if self.memory != 0 {
+            // ulimit -v is in KiB
+            script.push_str(&format!("ulimit -v {}; ", self.memory * 1024));
+        }
+        if self.cpu_time != 0 {
+            script.push_str(&format!("ulimit -t {}; ", self.cpu_time));
+        }
+        if script.is_empty() {
+            return None;
+        }
+        script.push_str("exec \"$0\" \"$@\"");
+        Some(vec!["sh".to_string(), "-c".to_string(), script])
+    }
+
+    /// Why the evaluation was killed, if it was because of a limit
+    pub fn exceeded(&self, output: &std::process::Output) -> Option<String> {
+        // cargo run replaces itself with the binary, its signal is the binary one
+        #[cfg(unix)]
+        let signal = {
+            use std::os::unix::process::ExitStatusExt;
+            output.status.signal()
+        };
+        #[cfg(not(unix))]
+        let signal: Option<i32> = None;
+
+        // SIGABRT, SIGKILL, SIGXCPU
+        match signal? {
+            6 if self.memory != 0
+                && String::from_utf8_lossy(&output.stderr).contains("memory allocation of") =>
+            {
+                Some(format!(
+                    "killed: exceeded memory limit ({} MiB)",
+                    self.memory
+                ))
+            }
+            9 | 24 if self.cpu_time != 0 => Some(format!(
+                "killed: exceeded CPU time limit ({} s)",
+                self.cpu_time
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// The command the compiled binaries are run with: in the sandbox, with the limits
+/// and with the target runner
+fn runner(target_runner: Option<&[String]>) -> Vec<String> {
+    let mut runner = SANDBOX.get().cloned().unwrap_or_default();
+    if let Some(wrapper) = LIMITS.get().and_then(Limits::wrapper) {
+        runner.extend(wrapper);
+    }
+    if let Some(target_runner) = target_runner {
+        runner.extend_from_slice(target_runner);
+    }
+    runner
+}
+
+/// Build for the `:target` target, its binaries are run with the target runner,
+/// inside the sandbox if there is one
+fn with_target(cmd: &mut Command) -> &mut Command {
+    let target = TARGET.lock().unwrap();
+    let (key, runner) = match target.as_ref() {
+        Some(target) => {
+            cmd.args(["--target", &target.triple]);
+            match &target.runner {
+                // exp: target.wasm32-wasip1.runner
+                Some(target_runner) => (
+                    format!("target.{}.runner", toml_key(&target.triple)),
+                    runner(Some(target_runner)),
+                ),
+                None => return cmd,
+            }
+        }
+        None => ("target.'cfg(all())'.runner".to_string(), runner(None)),
+    };
+    if !runner.is_empty() {
+        // the toml array keeps the arguments as they are, exp: ["bwrap", "--ro-bind", "/", "/"..]
+        cmd.arg("--config")
+            .arg(format!("{} = {}", key, toml_array(&runner)));
+    }
+    cmd
+}
+
+/// The command the evaluations are run in: no network, a read-only filesystem and an empty `/tmp`
+pub fn sandbox_command(sandbox: &str, image: &str) -> Option<Vec<String>> {
+    let args: Vec<String> = match sandbox {
+        "bwrap" => [
+            "bwrap",
+            "--ro-bind",
+            "/",
+            "/",
+            "--dev",
+            "/dev",
+            "--proc",
+            "/proc",
+            "--tmpfs",
+            "/tmp",
+            "--unshare-all",
+            "--die-with-parent",
+        ]
+        .iter()
+        .map(ToString::to_string)
+        .collect(),
+        "docker" | "podman" => {
+            // the compiled binary is mounted at the same path
+            let target_dir = TARGET_DIR.to_string_lossy();
+            vec![
+                sandbox.to_string(),
+                "run".to_string(),
+                "--rm".to_string(),
+                "--interactive".to_string(),
+                "--network=none".to_string(),
+                "--read-only".to_string(),
+                "--tmpfs=/tmp".to_string(),
+                format!("--volume={0}:{0}:ro", target_dir),
+                "--workdir=/tmp".to_string(),
+                image.to_string(),
+            ]
+        }
+        _ => return None,
+    };
+    Some(args)
+}
+
+/// The `:target` triple if its binaries can't be run
+pub fn build_only_target() -> Option<String> {
+    match TARGET.lock().unwrap().as_ref() {
+        Some(target) if target.runner.is_none() => Some(target.triple.clone()),
+        _ => None,
+    }
+}
 
 pub fn cargo_new() -> Result<(), io::Error> {
     let _ = std::fs::remove_dir_all(&*IRUST_DIR);
 
     Command::new("cargo")
         .current_dir(&*TMP_DIR)
-        .args(&["new", "irust"])
+        .args(["new", "irust"])
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::null())
         .spawn()?
         .wait()?;
 
     clean_main_file()?;
-    cargo_build()?.wait()?;
+    create_cargo_config()?;
+    if let Some(project) = PROJECT.get() {
+        link_project(project)?;
+    }
+    write_session_dependencies()?;
+    write_panic_strategy()?;
+    write_cargo_fragments()?;
+    cargo_build()?.wait_with_output()?;
     Ok(())
 }
 
-pub fn cargo_run(color: bool) -> Result<String, io::Error> {
+/// Make the repl depend on the project (by path) and on the project own dependencies
+fn link_project(project: &Path) -> io::Result<()> {
+    let manifest = fs::read_to_string(project.join("Cargo.toml"))?;
+    let (name, mut dependencies) = project_dependencies(&manifest, project)?;
+
+    let name = name.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "project Cargo.toml has no package name",
+        )
+    })?;
+    if project.join("src/lib.rs").exists() {
+        dependencies.push(format!(
+            "{} = {{ path = {} }}",
+            name,
+            toml_string(&project.to_string_lossy())
+        ));
+    }
+
+    let mut cargo_toml = fs::OpenOptions::new()
+        .append(true)
+        .open(IRUST_DIR.join("Cargo.toml"))?;
+    writeln!(cargo_toml, "{}", dependencies.join("\n"))
+}
+
+/// The package name and the `[dependencies]` of a manifest as `name = value` lines,
+/// `[dependencies.<name>]` tables become inline tables
+fn project_dependencies(
+    manifest: &str,
+    project: &Path,
+) -> io::Result<(Option<String>, Vec<String>)> {
+    let mut name = None;
+    let mut dependencies = vec![];
+    let mut section = String::new();
+    // the entries of the current `[dependencies.<name>]` table
+    let mut table = vec![];
+    for entry in manifest_entries(manifest) {
+        if entry.starts_with('[') {
+            dependencies.extend(inline_table(&section, &table));
+            table.clear();
+            section = entry;
+            continue;
+        }
+        match section.as_str() {
+            "[package]" if entry.starts_with("name") => {
+                name = entry
+                    .split('=')
+                    .nth(1)
+                    .map(|n| n.trim().trim_matches('"').to_owned());
+            }
+            "[dependencies]" if entry.contains('=') => {
+                // workspace inherited dependencies can't be resolved outside of the workspace
+                if entry.contains("workspace") {
+                    continue;
+                }
+                dependencies.push(absolute_path(&entry, project)?);
+            }
+            section if dependency_table(section).is_some() => {
+                table.push(absolute_path(&entry, project)?);
+            }
+            _ => (),
+        }
+    }
+    dependencies.extend(inline_table(&section, &table));
+
+    Ok((name, dependencies))
+}
+
+// `foo` for `[dependencies.foo]`
+fn dependency_table(section: &str) -> Option<&str> {
+    section
+        .strip_prefix("[dependencies.")?
+        .strip_suffix(']')
+        .map(str::trim)
+}
+
+// the entries of a `[dependencies.<name>]` table as `name = { .. }`
+fn inline_table(section: &str, entries: &[String]) -> Option<String> {
+    let name = dependency_table(section)?;
+    if entries.iter().any(|entry| entry.starts_with("workspace")) {
+        return None;
+    }
+    Some(format!("{} = {{ {} }}", name, entries.join(", ")))
+}
+
+/// The manifest lines without comments and empty lines,
+/// the lines of a value spanning several lines (an array) are joined
+fn manifest_entries(manifest: &str) -> Vec<String> {
+    let mut entries = vec![];
+    let mut entry = String::new();
+    // open brackets and braces
+    let mut depth = 0;
+    for line in manifest.lines() {
+        let mut quote = None;
+        let mut escaped = false;
+        let mut content = String::new();
+        for c in line.chars() {
+            match (quote, c) {
+                (Some('"'), '\\') if !escaped => {
+                    escaped = true;
+                    content.push(c);
+                    continue;
+                }
+                (Some(q), c) if c == q && !escaped => quote = None,
+                (Some(_), _) => (),
+                (None, '#') => break,
+                (None, '"') | (None, '\'') => quote = Some(c),
+                (None, '[') | (None, '{') => depth += 1,
+                (None, ']') | (None, '}') => depth -= 1,
+                _ => (),
+            }
+            escaped = false;
+            content.push(c);
+        }
+
+        let content = content.trim();
+        if content.is_empty() {
+            continue;
+        }
+        if !entry.is_empty() {
+            entry.push(' ');
+        }
+        entry.push_str(content);
+        if depth <= 0 {
+            depth = 0;
+            entries.push(std::mem::take(&mut entry));
+        }
+    }
+    if !entry.is_empty() {
+        entries.push(entry);
+    }
+    entries
+}
+
+// relative paths are relative to the project
+fn absolute_path(dependency: &str, project: &Path) -> io::Result<String> {
+    let quote = match PATH_KEY.captures(dependency).and_then(|c| c.get(1)) {
+        Some(quote) => quote,
+        None => return Ok(dependency.to_owned()),
+    };
+    let start = quote.end();
+    let end = match dependency[start..].find(quote.as_str()) {
+        Some(len) => start + len,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "unterminated path in the project Cargo.toml: {}",
+                    dependency
+                ),
+            ))
+        }
+    };
+    let path = project.join(&dependency[start..end]);
+    Ok(format!(
+        "{}{}{}",
+        &dependency[..quote.start()],
+        toml_string(&path.to_string_lossy()),
+        &dependency[end + 1..]
+    ))
+}
+
+/// Write the `:cargo` additions at the end of the repl Cargo.toml, replacing the previous ones
+pub fn write_cargo_fragments() -> io::Result<()> {
+    let path = IRUST_DIR.join("Cargo.toml");
+    let manifest = fs::read_to_string(&path)?;
+    let mut manifest = manifest
+        .split(FRAGMENTS_MARK)
+        .next()
+        .unwrap_or_default()
+        .trim_end()
+        .to_owned();
+    manifest.push('\n');
+
+    let fragments = CARGO_FRAGMENTS.lock().unwrap_or_else(|e| e.into_inner());
+    if !fragments.is_empty() {
+        manifest += &format!("\n{}\n{}\n", FRAGMENTS_MARK, fragments.join("\n"));
+    }
+    fs::write(path, manifest)
+}
+
+/// The error of an invalid repl Cargo.toml, cargo reads it without building anything
+pub fn cargo_manifest_error() -> io::Result<Option<String>> {
+    let output = Command::new("cargo")
+        .current_dir(&*IRUST_DIR)
+        .args(["metadata", "--no-deps", "--format-version", "1"])
+        .output()?;
+    if output.status.success() {
+        Ok(None)
+    } else {
+        Ok(Some(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        ))
+    }
+}
+
+fn create_cargo_config() -> io::Result<()> {
+    fs::create_dir_all(IRUST_DIR.join(".cargo"))?;
+    // `[env]` must stay the last table, `cargo_env` appends to it
+    let config = format!(
+        "[build]\ntarget-dir = {}\n\n[env]\n",
+        toml_string(&TARGET_DIR.to_string_lossy())
+    );
+    fs::write(&*CARGO_CONFIG, config)
+}
+
+/// Add the crates needed by `:set allocator` and `:set log` to the repl dependencies,
+/// removing the ones not needed anymore, the dependencies added by the user are kept
+pub fn write_session_dependencies() -> io::Result<()> {
+    let path = IRUST_DIR.join("Cargo.toml");
+    let manifest = fs::read_to_string(&path)?;
+    let allocator = *ALLOCATOR.lock().unwrap_or_else(|e| e.into_inner());
+    let log = RUST_LOG.lock().unwrap_or_else(|e| e.into_inner()).is_some();
+
+    let mut manifest: Vec<String> = manifest
+        .lines()
+        .filter(|line| !line.ends_with(SESSION_DEPENDENCY_MARK))
+        .map(ToOwned::to_owned)
+        .collect();
+    let mut dependencies: Vec<&str> = allocator.dependency().into_iter().collect();
+    if log {
+        dependencies.extend(["log = \"0.4\"", "env_logger = \"0.11\""]);
+    }
+
+    let header = match manifest.iter().position(|line| line == "[dependencies]") {
+        Some(idx) => idx,
+        None => {
+            manifest.push("[dependencies]".to_owned());
+            manifest.len() - 1
+        }
+    };
+    for dependency in dependencies {
+        let name = dependency.split(" =").next().unwrap_or_default();
+        if !manifest
+            .iter()
+            .any(|line| line.starts_with(&format!("{} =", name)))
+        {
+            manifest.insert(
+                header + 1,
+                format!("{} {}", dependency, SESSION_DEPENDENCY_MARK),
+            );
+        }
+    }
+    fs::write(path, manifest.join("\n") + "\n")
+}
+
+/// Write the `:set panic` strategy of the dev and release profiles in the repl cargo config
+pub fn write_panic_strategy() -> io::Result<()> {
+    let config = fs::read_to_string(&*CARGO_CONFIG)?;
+    let (head, env) = config.split_once("[env]\n").unwrap_or((&config, ""));
+    let head = head
+        .split("[profile.")
+        .next()
+        .unwrap_or_default()
+        .trim_end();
+
+    let profiles = if PANIC_ABORT.load(Ordering::Relaxed) {
+        "[profile.dev]\npanic = \"abort\"\n\n[profile.release]\npanic = \"abort\"\n\n"
+    } else {
+        ""
+    };
+    // `[env]` stays the last table
+    fs::write(
+        &*CARGO_CONFIG,
+        format!("{}\n\n{}[env]\n{}", head, profiles, env),
+    )
+}
+
+/// Set environment variables for every following build (build scripts included) and run
+pub fn cargo_env(vars: &[(String, String)]) -> io::Result<()> {
+    let config = fs::read_to_string(&*CARGO_CONFIG)?;
+    let mut config: Vec<String> = config
+        .lines()
+        .filter(|line| {
+            !vars
+                .iter()
+                .any(|(key, _)| line.starts_with(&format!("{} =", toml_key(key))))
+        })
+        .map(ToOwned::to_owned)
+        .collect();
+
+    for (key, value) in vars {
+        config.push(format!("{} = {}", toml_key(key), toml_string(value)));
+    }
+
+    fs::write(&*CARGO_CONFIG, config.join("\n") + "\n")
+}
+
+/// `value` as a TOML basic string
+fn toml_string(value: &str) -> String {
+    let mut string = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => string.push_str("\\\""),
+            '\\' => string.push_str("\\\\"),
+            '\n' => string.push_str("\\n"),
+            '\r' => string.push_str("\\r"),
+            '\t' => string.push_str("\\t"),
+            c if c.is_control() => string.push_str(&format!("\\u{:04X}", c as u32)),
+            c => string.push(c),
+        }
+    }
+    string.push('"');
+    string
+}
+
+/// `values` as a TOML array of strings
+fn toml_array(values: &[String]) -> String {
+    let values: Vec<String> = values.iter().map(|value| toml_string(value)).collect();
+    format!("[{}]", values.join(", "))
+}
+
+// a bare key if possible, env variables names can be anything
+fn toml_key(key: &str) -> String {
+    if !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        key.to_owned()
+    } else {
+        toml_string(key)
+    }
+}
+
+pub fn cargo_run(color: bool) -> Result<std::process::Child, io::Error> {
+    if MIRI.load(Ordering::Relaxed) {
+        return cargo_miri_run(color);
+    }
     let color = if color { "always" } else { "never" };
 
-    Ok(stdout_and_stderr(
+    // binaries of a target without runner are only built
+    let subcommand = if build_only_target().is_some() {
+        "build"
+    } else {
+        "run"
+    };
+
+    let mut cmd = Command::new("cargo");
+    cmd.current_dir(&*IRUST_DIR)
+        .args([subcommand, "--color", color])
+        .env("RUSTFLAGS", "-Awarnings")
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped());
+    spawn_run(with_target(&mut cmd).args(program_args(subcommand)))
+}
+
+/// Run the repl optimized, used to time expressions
+pub fn cargo_run_release() -> io::Result<std::process::Child> {
+    let subcommand = if build_only_target().is_some() {
+        "build"
+    } else {
+        "run"
+    };
+
+    let mut cmd = Command::new("cargo");
+    cmd.current_dir(&*IRUST_DIR)
+        .args([subcommand, "--release", "--color", "always"])
+        .env("RUSTFLAGS", "-Awarnings")
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped());
+    spawn_run(with_target(&mut cmd).args(program_args(subcommand)))
+}
+
+// cargo gives its stdin and environment to the binary: the `:stdin` data is written from
+// another thread, so a program that outputs before reading all of it doesn't block,
+// and RUST_LOG is set from `:set log`
+fn spawn_run(cmd: &mut Command) -> io::Result<std::process::Child> {
+    if let Some(level) = RUST_LOG.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        cmd.env("RUST_LOG", level);
+    }
+    let data = match RUN_STDIN.lock().unwrap_or_else(|e| e.into_inner()).clone() {
+        Some(data) => data,
+        None => return cmd.spawn(),
+    };
+    let mut child = cmd.stdin(std::process::Stdio::piped()).spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        std::thread::spawn(move || stdin.write_all(&data));
+    }
+    Ok(child)
+}
+
+// the `:args` arguments, after `--` so cargo gives them to the binary
+fn program_args(subcommand: &str) -> Vec<String> {
+    let args = RUN_ARGS.lock().unwrap_or_else(|e| e.into_inner());
+    if subcommand != "run" || args.is_empty() {
+        return vec![];
+    }
+    std::iter::once("--".to_string())
+        .chain(args.iter().cloned())
+        .collect()
+}
+
+/// Run the repl with nightly miri, the sandbox, the limits and the target are not used
+pub fn cargo_miri_run(color: bool) -> io::Result<std::process::Child> {
+    let color = if color { "always" } else { "never" };
+    spawn_run(
         Command::new("cargo")
             .current_dir(&*IRUST_DIR)
-            .args(&["run", "--color", color])
+            .args(["+nightly", "miri", "run", "--color", color])
+            .args(program_args("run"))
             .env("RUSTFLAGS", "-Awarnings")
-            .output()?,
-    ))
+            .env("CARGO_TARGET_DIR", &*NIGHTLY_TARGET_DIR)
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped()),
+    )
+}
+
+/// Build the repl with nightly `-Zprint-type-sizes`, the layouts are written to stdout
+pub fn cargo_print_type_sizes() -> io::Result<std::process::Child> {
+    Command::new("cargo")
+        .current_dir(&*IRUST_DIR)
+        .args([
+            "+nightly",
+            "rustc",
+            "--color",
+            "never",
+            "--",
+            "-Zprint-type-sizes",
+        ])
+        .env("RUSTFLAGS", "-Awarnings")
+        .env("CARGO_TARGET_DIR", &*NIGHTLY_TARGET_DIR)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+}
+
+pub fn cargo_check(code: &str) -> io::Result<std::process::Output> {
+    check_code(code, "-Awarnings")
+}
+
+/// Like `cargo_check` but the warnings are kept, used to find dead code
+pub fn cargo_check_warnings(code: &str) -> io::Result<std::process::Output> {
+    check_code(code, "")
+}
+
+fn check_code(code: &str, rustflags: &str) -> io::Result<std::process::Output> {
+    // the live check, the insertions validation and :clean share the check project
+    static CHECK_LOCK: Mutex<()> = Mutex::new(());
+    let _lock = CHECK_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    fs::create_dir_all(CHECK_DIR.join("src"))?;
+    fs::create_dir_all(CHECK_DIR.join(".cargo"))?;
+    // use the same dependencies and environment as the repl
+    fs::copy(IRUST_DIR.join("Cargo.toml"), CHECK_DIR.join("Cargo.toml"))?;
+    let _ = fs::copy(IRUST_DIR.join("Cargo.lock"), CHECK_DIR.join("Cargo.lock"));
+    let _ = fs::copy(&*CARGO_CONFIG, CHECK_DIR.join(".cargo/config.toml"));
+    fs::write(CHECK_DIR.join("src/main.rs"), code)?;
+
+    let mut cmd = Command::new("cargo");
+    cmd.current_dir(&*CHECK_DIR)
+        .args(["check", "--message-format", "short", "--color", "never"])
+        .env("RUSTFLAGS", rustflags)
+        // overrides the config target-dir
+        .env("CARGO_TARGET_DIR", &*CHECK_TARGET_DIR);
+    with_target(&mut cmd).output()
 }
 
 pub fn cargo_add(dep: &[String]) -> io::Result<std::process::Child> {
     clean_main_file()?;
-    Ok(Command::new("cargo-add")
+    Command::new("cargo-add")
         .current_dir(&*IRUST_DIR)
         .arg("add")
         .args(dep)
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::piped())
-        .spawn()?)
+        .spawn()
 }
 
-pub fn cargo_build() -> Result<std::process::Child, io::Error> {
-    Ok(Command::new("cargo")
+pub fn cargo_update(krate: Option<&str>) -> io::Result<std::process::Child> {
+    let mut cmd = Command::new("cargo");
+    cmd.current_dir(&*IRUST_DIR).arg("update");
+    if let Some(krate) = krate {
+        cmd.args(["--package", krate]);
+    }
+    // cargo reports the updated packages on stderr
+    cmd.stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+}
+
+/// Local crates the repl depends on (`--project` and `path = ".."` dependencies)
+pub fn path_dependencies() -> Vec<PathBuf> {
+    let manifest = fs::read_to_string(IRUST_DIR.join("Cargo.toml")).unwrap_or_default();
+    manifest
+        .lines()
+        .filter_map(|line| {
+            let quote = PATH_KEY.captures(line)?.get(1)?;
+            let start = quote.end();
+            let end = start + line[start..].find(quote.as_str())?;
+            Some(PathBuf::from(&line[start..end]))
+        })
+        .collect()
+}
+
+/// Versions of `name` in the repl Cargo.lock
+pub fn locked_versions(name: &str) -> Vec<String> {
+    let lock = fs::read_to_string(IRUST_DIR.join("Cargo.lock")).unwrap_or_default();
+    let mut versions = vec![];
+    let mut lines = lock.lines();
+    while let Some(line) = lines.next() {
+        if line == format!("name = {}", toml_string(name)) {
+            if let Some(version) = lines
+                .next()
+                .and_then(|l| l.strip_prefix("version = "))
+                .map(|v| v.trim_matches('"'))
+            {
+                versions.push(version.to_owned());
+            }
+        }
+    }
+    versions
+}
+
+/// Document the repl crate and its dependencies in `TARGET_DIR/doc`
+pub fn cargo_doc() -> io::Result<std::process::Child> {
+    Command::new("cargo")
         .current_dir(&*IRUST_DIR)
+        .args(["doc", "--color", "never"])
+        .env("RUSTDOCFLAGS", "-Awarnings")
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+}
+
+/// Apply the machine-applicable suggestions of the warnings to the repl code,
+/// the warnings are not silenced here since they are what gets fixed
+pub fn cargo_fix() -> io::Result<std::process::Child> {
+    Command::new("cargo")
+        .current_dir(&*IRUST_DIR)
+        .args(["fix", "--allow-dirty", "--allow-no-vcs", "--color", "never"])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+}
+
+/// Run the repl in release mode under `perf` or `dtrace` with cargo-flamegraph,
+/// the flamegraph is written to `svg`
+pub fn cargo_flamegraph(svg: &Path) -> io::Result<std::process::Child> {
+    Command::new("cargo")
+        .current_dir(&*IRUST_DIR)
+        .arg("flamegraph")
+        .arg("--output")
+        .arg(svg)
+        .env("RUSTFLAGS", "-Awarnings")
+        // symbols for the flamegraph frames
+        .env("CARGO_PROFILE_RELEASE_DEBUG", "true")
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+}
+
+pub fn cargo_build() -> Result<std::process::Child, io::Error> {
+    let mut cmd = Command::new("cargo");
+    cmd.current_dir(&*IRUST_DIR)
         .arg("build")
+        .env("RUSTFLAGS", "-Awarnings")
         .stdout(std::process::Stdio::null())
-        .stderr(std::process::Stdio::null())
-        .spawn()?)
+        .stderr(std::process::Stdio::piped());
+    with_target(&mut cmd).spawn()
 }
 
 fn clean_main_file() -> io::Result<()> {
@@ -72,6 +861,7 @@ pub fn cargo_fmt(c: &str) -> std::io::Result<String> {
 
     let mut fmt_file = fs::OpenOptions::new()
         .create(true)
+        .truncate(true)
         .read(true)
         .write(true)
         .open(&fmt_path)?;
@@ -92,6 +882,28 @@ pub fn cargo_fmt(c: &str) -> std::io::Result<String> {
     Ok(fmt_c)
 }
 
+/// Like `cargo_fmt` but rustfmt errors are returned, with the first error line as the message
+pub fn cargo_fmt_checked(c: &str) -> io::Result<String> {
+    let fmt_path = IRUST_DIR.join("fmt_file");
+    fs::write(&fmt_path, c)?;
+
+    let output = Command::new("rustfmt")
+        .arg("--edition")
+        .arg("2018")
+        .arg(&fmt_path)
+        .output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let error = stderr
+            .lines()
+            .find(|line| line.starts_with("error"))
+            .unwrap_or("rustfmt failed");
+        return Err(io::Error::other(error.to_owned()));
+    }
+
+    fs::read_to_string(&fmt_path)
+}
+
 pub fn cargo_fmt_file(file: &PathBuf) -> io::Result<()> {
     std::process::Command::new("rustfmt")
         .stdout(std::process::Stdio::null())
@@ -101,3 +913,144 @@ pub fn cargo_fmt_file(file: &PathBuf) -> io::Result<()> {
         .wait()?;
     Ok(())
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+
+    // a wait status: the signal that killed the process, or the exit code
+    fn output(status: i32, stderr: &str) -> Output {
+        Output {
+            status: ExitStatus::from_raw(status),
+            stdout: vec![],
+            stderr: stderr.as_bytes().to_vec(),
+        }
+    }
+
+    const LIMITS: Limits = Limits {
+        memory: 512,
+        cpu_time: 10,
+    };
+
+    #[test]
+    fn exceeded_limits() {
+        assert_eq!(
+            LIMITS.exceeded(&output(9, "")).as_deref(),
+            Some("killed: exceeded CPU time limit (10 s)")
+        );
+        assert_eq!(
+            LIMITS
+                .exceeded(&output(6, "memory allocation of 1024 bytes failed"))
+                .as_deref(),
+            Some("killed: exceeded memory limit (512 MiB)")
+        );
+    }
+
+    #[test]
+    fn exceeded_needs_a_signal() {
+        // exit code 137, not a SIGKILL
+        assert_eq!(LIMITS.exceeded(&output(137 << 8, "")), None);
+        assert_eq!(LIMITS.exceeded(&output(0, "")), None);
+        // a panic abort is not the memory limit
+        assert_eq!(LIMITS.exceeded(&output(6, "thread 'main' panicked")), None);
+    }
+
+    #[test]
+    fn exceeded_without_limits() {
+        let limits = Limits {
+            memory: 0,
+            cpu_time: 0,
+        };
+        assert_eq!(limits.exceeded(&output(9, "")), None);
+    }
+}
+
+#[cfg(test)]
+mod toml_tests {
+    use super::*;
+
+    #[test]
+    fn toml_string_escapes() {
+        assert_eq!(toml_string("/usr/local/ssl"), "\"/usr/local/ssl\"");
+        assert_eq!(
+            toml_string("say \"hi\" it's C:\\dir"),
+            r#""say \"hi\" it's C:\\dir""#
+        );
+        assert_eq!(
+            toml_string("a\tb\nc\r\u{1b}[0m\u{7f}é"),
+            r#""a\tb\nc\r\u001B[0m\u007Fé""#
+        );
+    }
+
+    #[test]
+    fn toml_array_of_arguments() {
+        let runner = [
+            "qemu-aarch64".to_string(),
+            "-L".to_string(),
+            "C:\\a \"b\"".to_string(),
+        ];
+        assert_eq!(
+            toml_array(&runner),
+            r#"["qemu-aarch64", "-L", "C:\\a \"b\""]"#
+        );
+        assert_eq!(toml_array(&[]), "[]");
+    }
+
+    const MANIFEST: &str = r#"
+[package]
+name = "proj" # the name
+
+[dependencies]
+serde = "1"
+local={path="../local"}
+shared = { workspace = true }
+multi = { version = "1", features = [
+    "a", # first
+    "b",
+] }
+
+[dependencies.table]
+version = "0.2"
+path = 'crates/table'
+features = ["x"]
+
+[dependencies.inherited]
+workspace = true
+
+[dev-dependencies]
+dev = "1"
+"#;
+
+    #[cfg(unix)]
+    #[test]
+    fn project_dependencies_of_a_manifest() {
+        let (name, dependencies) = project_dependencies(MANIFEST, Path::new("/p")).unwrap();
+        assert_eq!(name.as_deref(), Some("proj"));
+        assert_eq!(
+            dependencies,
+            [
+                r#"serde = "1""#,
+                r#"local={path="/p/../local"}"#,
+                r#"multi = { version = "1", features = [ "a", "b", ] }"#,
+                r#"table = { version = "0.2", path = "/p/crates/table", features = ["x"] }"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn project_dependencies_unterminated_path() {
+        let manifest = "[dependencies]\nlocal = { path = \"../local }\n";
+        assert!(project_dependencies(manifest, Path::new("/p")).is_err());
+        let manifest = "[dependencies]\nlocal = { path = \"";
+        assert!(project_dependencies(manifest, Path::new("/p")).is_err());
+    }
+
+    #[test]
+    fn toml_key_quotes_when_needed() {
+        assert_eq!(toml_key("OPENSSL_DIR"), "OPENSSL_DIR");
+        assert_eq!(toml_key("A.B"), "\"A.B\"");
+        assert_eq!(toml_key(""), "\"\"");
+    }
+}
@@ -0,0 +1,212 @@
+use super::buffer::Buffer;
+use super::cmd_args::CmdArgs;
+use super::help::ColoredPrinterItem;
+use super::highlight::highlight;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::repl::Repl;
+use crate::irust::{IRust, IRustError};
+use crossterm::Color;
+
+const LABEL_COLOR: Color = Color::DarkGrey;
+
+/// An input and its output, kept when the `cells` option is set
+struct Cell {
+    input: String,
+    output: Printer,
+    // the repl before the input, restored to run the cell again
+    before: Repl,
+    // statements and items are part of the repl code, expressions are only evaluated
+    in_body: bool,
+}
+
+/// The notebook of the session, inputs are numbered cells that can be run again, edited or deleted
+#[derive(Default)]
+pub struct Cells {
+    cells: Vec<Cell>,
+    // the next input replaces this cell, set by `:edit-cell`
+    editing: Option<usize>,
+    // the input of the edited cell, put in the prompt after `:edit-cell`
+    edit_input: Option<String>,
+}
+
+impl IRust {
+    /// Evaluate the input as a new cell, or as the new input of the edited cell
+    pub fn eval_cell(&mut self) -> Result<Printer, IRustError> {
+        let input = self.buffer.to_string();
+        if input.trim().is_empty() {
+            return self.parse_second_order();
+        }
+
+        let idx = match self.cells.editing.take() {
+            Some(idx) => {
+                self.cells.cells[idx].input = input;
+                return self.rerun_cells(idx, false);
+            }
+            None => {
+                self.cells.cells.push(Cell {
+                    input,
+                    output: Printer::default(),
+                    before: self.repl.clone(),
+                    in_body: false,
+                });
+                self.cells.cells.len() - 1
+            }
+        };
+        Ok(self.run_cell(idx))
+    }
+
+    pub fn cells(&mut self) -> Result<Printer, IRustError> {
+        self.cells_enabled()?;
+        if self.cells.cells.is_empty() {
+            return Err(IRustError::Custom("No cells yet".to_string()));
+        }
+
+        let mut outputs = Printer::default();
+        for (idx, cell) in self.cells.cells.iter().enumerate() {
+            outputs.push(format!("[{}] ", idx + 1).to_output(LABEL_COLOR));
+            outputs.append(&mut highlight(&cell.input));
+            outputs.add_new_line(1);
+            if !cell.output.is_empty() {
+                outputs.append(&mut cell.output.clone());
+                outputs.add_new_line(1);
+            }
+        }
+
+        if self.page(&outputs).is_ok() {
+            return Ok(Printer::default());
+        }
+        Ok(outputs)
+    }
+
+    pub fn rerun(&mut self) -> Result<Printer, IRustError> {
+        // exp: :rerun 3, :rerun 3 --all
+        let mut args = CmdArgs::parse("rerun", &self.buffer.to_string())?;
+        let all = args.flag("--all");
+        let idx = self.cell_arg(&args)?;
+        self.rerun_cells(idx, all)
+    }
+
+    pub fn edit_cell(&mut self) -> Result<Printer, IRustError> {
+        // exp: :edit-cell 3
+        let idx = self.cell_arg(&CmdArgs::parse("edit-cell", &self.buffer.to_string())?)?;
+        let input = self.cells.cells[idx].input.clone();
+        self.cells.editing = Some(idx);
+
+        let mut outputs = Printer::new(
+            format!(
+                "Editing cell [{}], press Enter to run it again with the later cells",
+                idx + 1
+            )
+            .to_output(LABEL_COLOR),
+        );
+        outputs.add_new_line(1);
+        // the accessible mode can't put the input in the prompt, it's printed to be copied instead
+        if self.options.accessible {
+            outputs.append(&mut highlight(&input));
+            outputs.add_new_line(1);
+        } else {
+            self.cells.edit_input = Some(input);
+        }
+        Ok(outputs)
+    }
+
+    pub fn del_cell(&mut self) -> Result<Printer, IRustError> {
+        // exp: :del-cell 3
+        let idx = self.cell_arg(&CmdArgs::parse("del-cell", &self.buffer.to_string())?)?;
+        self.cells.editing = None;
+        let cell = self.cells.cells.remove(idx);
+        self.repl.restore_code(&cell.before);
+        self.repl_changed(&format!(":del-cell {}", idx + 1))?;
+        // the later cells may have used what the deleted cell defined
+        self.replay_cells(idx, false);
+
+        let mut outputs = Printer::new(PrinterItem::new("Ok!".to_string(), PrinterItemType::Ok));
+        outputs.add_new_line(1);
+        Ok(outputs)
+    }
+
+    /// Put the input of the cell being edited in the prompt
+    pub fn take_cell_edit(&mut self) -> Result<(), IRustError> {
+        if let Some(input) = self.cells.edit_input.take() {
+            self.replace_input(&input)?;
+        }
+        Ok(())
+    }
+
+    /// Run the cell again, then the later cells: only the ones changing the repl code,
+    /// or all of them with `all`
+    fn rerun_cells(&mut self, idx: usize, all: bool) -> Result<Printer, IRustError> {
+        let mut outputs = self.run_cell(idx);
+        outputs.append(&mut self.replay_cells(idx + 1, all));
+        Ok(outputs)
+    }
+
+    fn replay_cells(&mut self, from: usize, all: bool) -> Printer {
+        let mut outputs = Printer::default();
+        for idx in from..self.cells.cells.len() {
+            self.cells.cells[idx].before = self.repl.clone();
+            if all {
+                outputs.append(&mut self.run_cell(idx));
+            } else if self.cells.cells[idx].in_body {
+                self.run_cell(idx);
+            }
+        }
+        outputs
+    }
+
+    /// Evaluate the cell input on the repl it was first evaluated on, the output is labeled with the cell number
+    fn run_cell(&mut self, idx: usize) -> Printer {
+        let cell = &self.cells.cells[idx];
+        self.repl.restore_code(&cell.before);
+        self.buffer = Buffer::from_str(
+            &cell.input,
+            self.options.input_row_chars(self.cursor.bound.width),
+        );
+
+        let mut output = match self.parse_second_order() {
+            Ok(output) => output,
+            Err(e) => Printer::new(PrinterItem::new(e.to_string(), PrinterItemType::Err)),
+        };
+        self.buffer.clear();
+        output.trim_new_lines();
+
+        let cell = &mut self.cells.cells[idx];
+        cell.in_body = self.repl.body != cell.before.body;
+        cell.output = output.clone();
+
+        let mut outputs = Printer::new(format!("[{}]", idx + 1).to_output(LABEL_COLOR));
+        outputs.add_new_line(1);
+        if !output.is_empty() {
+            outputs.append(&mut output);
+            outputs.add_new_line(1);
+        }
+        outputs
+    }
+
+    /// The cell index of `:cmd <number>`, cells are numbered from 1
+    fn cell_arg(&self, args: &CmdArgs) -> Result<usize, IRustError> {
+        self.cells_enabled()?;
+        let number = match args.as_strs().as_slice() {
+            [number] => number.parse::<usize>().ok(),
+            _ => return Err(args.usage_error()),
+        };
+        let idx = number
+            .filter(|number| (1..=self.cells.cells.len()).contains(number))
+            .ok_or_else(|| {
+                IRustError::Custom(format!(
+                    "Incorrect cell number, the cells go from 1 to {}",
+                    self.cells.cells.len()
+                ))
+            })?;
+        Ok(idx - 1)
+    }
+
+    fn cells_enabled(&self) -> Result<(), IRustError> {
+        if !self.options.cells {
+            return Err(IRustError::Custom(
+                "The cells are disabled, set `cells = true` in the config".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+use super::cargo_cmds::cargo_check;
+use super::parser::split_pipe;
+use super::repl::Repl;
+use crate::irust::{IRust, IRustError};
+use std::ops::Range;
+use std::sync::mpsc;
+use std::time::Duration;
+
+// wait for the user to stop typing before checking
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// First error of the checked input
+pub struct InputDiagnostic {
+    /// chars of the input to underline
+    pub span: Range<usize>,
+    pub message: String,
+}
+
+struct CheckRequest {
+    code: String,
+    input: String,
+    // line of the code where the input starts
+    input_line: usize,
+}
+
+/// Runs `cargo check` on the input in the background while it's typed
+pub struct Checker {
+    send: mpsc::Sender<CheckRequest>,
+    recv: mpsc::Receiver<(String, Option<InputDiagnostic>)>,
+    last_input: String,
+    // (checked input, its first error)
+    diagnostic: Option<(String, InputDiagnostic)>,
+}
+
+impl Checker {
+    pub fn start() -> Self {
+        let (send, requests) = mpsc::channel::<CheckRequest>();
+        let (results, recv) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            while let Ok(mut request) = requests.recv() {
+                // only the latest input matters
+                std::thread::sleep(DEBOUNCE);
+                while let Ok(newer) = requests.try_recv() {
+                    request = newer;
+                }
+
+                let diagnostic = cargo_check(&request.code).ok().and_then(|output| {
+                    first_error(&String::from_utf8_lossy(&output.stderr), &request)
+                });
+                if results.send((request.input, diagnostic)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            send,
+            recv,
+            last_input: String::new(),
+            diagnostic: None,
+        }
+    }
+
+    /// Queue a check of `input` inside the repl if it changed since the last request
+    fn request(&mut self, repl: &Repl, input: &str) {
+        if input == self.last_input {
+            return;
+        }
+        self.last_input = input.to_owned();
+
+        // irust commands and shell commands are not rust code
+        if input.trim().is_empty() || input.starts_with(':') || split_pipe(input).is_some() {
+            return;
+        }
+
+        // expressions are checked the same way they are evaluated
+        let (code, input_line) = repl.code_for_input(input);
+
+        let _ = self.send.send(CheckRequest {
+            code,
+            input: input.to_owned(),
+            input_line,
+        });
+    }
+
+    /// Returns true if the diagnostic to show changed
+    fn poll(&mut self) -> bool {
+        let mut changed = false;
+        while let Ok((input, diagnostic)) = self.recv.try_recv() {
+            changed |= diagnostic.is_some() || self.diagnostic.is_some();
+            self.diagnostic = diagnostic.map(|diagnostic| (input, diagnostic));
+        }
+        changed
+    }
+
+    fn diagnostic(&self, input: &str) -> Option<&InputDiagnostic> {
+        match &self.diagnostic {
+            Some((checked, diagnostic)) if checked == input => Some(diagnostic),
+            _ => None,
+        }
+    }
+}
+
+// exp: src/main.rs:3:5: error[E0425]: cannot find value `x` in this scope
+fn first_error(stderr: &str, request: &CheckRequest) -> Option<InputDiagnostic> {
+    let (line, col, message) = stderr.lines().find_map(|line| {
+        let mut parts = line.strip_prefix("src/main.rs:")?.splitn(3, ':');
+        let line: usize = parts.next()?.parse().ok()?;
+        let col: usize = parts.next()?.parse().ok()?;
+        let message = parts.next()?.trim();
+        if message.starts_with("error") {
+            Some((line, col, message.to_owned()))
+        } else {
+            None
+        }
+    })?;
+
+    // errors outside of the input are not underlined
+    let input_line = line.checked_sub(1)?.checked_sub(request.input_line)?;
+    let line_start: usize = request
+        .input
+        .split('\n')
+        .take(input_line)
+        .map(|line| line.chars().count() + 1)
+        .sum();
+    let line_chars: Vec<char> = request.input.split('\n').nth(input_line)?.chars().collect();
+
+    // underline the whole token the error points at
+    let start = std::cmp::min(col.saturating_sub(1), line_chars.len());
+    let token_len = line_chars[start..]
+        .iter()
+        .take_while(|c| c.is_alphanumeric() || **c == '_')
+        .count();
+
+    Some(InputDiagnostic {
+        span: line_start + start..line_start + start + std::cmp::max(token_len, 1),
+        message,
+    })
+}
+
+impl IRust {
+    pub fn request_input_check(&mut self) {
+        if let Some(checker) = self.checker.as_mut() {
+            checker.request(&self.repl, &self.buffer.to_string());
+        }
+    }
+
+    pub fn input_diagnostic(&self) -> Option<&InputDiagnostic> {
+        self.checker.as_ref()?.diagnostic(&self.buffer.to_string())
+    }
+
+    /// Show the check results that arrived
+    pub fn check_input_callback(&mut self) -> Result<(), IRustError> {
+        if self.checker.as_mut().map(Checker::poll) == Some(true) {
+            self.print_input()?;
+        }
+        Ok(())
+    }
+
+    /// The diagnostic line is not part of the input, remove it before writing under the input
+    pub fn clear_input_diagnostic(&mut self) -> Result<(), IRustError> {
+        let shown = self.input_diagnostic().is_some();
+        if let Some(checker) = self.checker.as_mut() {
+            checker.diagnostic = None;
+        }
+        if shown {
+            self.print_input()?;
+        }
+        Ok(())
+    }
+}
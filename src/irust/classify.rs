@@ -0,0 +1,123 @@
+use syn::Stmt;
+
+#[derive(Debug, PartialEq)]
+pub enum InputKind {
+    /// fn, struct, impl, use.. definitions
+    Item,
+    /// let bindings and `;` terminated expressions
+    Statement,
+    /// anything that produces a value to print
+    Expression,
+    /// nothing but comments
+    Comment,
+    /// syn couldn't parse the input (incomplete input, exotic macros..)
+    Unknown,
+}
+
+pub fn classify(input: &str) -> InputKind {
+    // parse the input as the content of a block, this way items, statements
+    // and a trailing expression are all accepted
+    let block: syn::Block = match syn::parse_str(&format!("{{\n{}\n}}", input)) {
+        Ok(block) => block,
+        Err(_) => return InputKind::Unknown,
+    };
+
+    match block.stmts.last() {
+        Some(Stmt::Expr(_)) => InputKind::Expression,
+        Some(Stmt::Item(_)) => InputKind::Item,
+        Some(Stmt::Local(_)) | Some(Stmt::Semi(..)) => InputKind::Statement,
+        None => InputKind::Comment,
+    }
+}
+
+/// Split an input made of statements/items followed by an expression
+///
+/// exp: `let a = 1; fn f() {} a + 1` => (`let a = 1; fn f() {}`, ` a + 1`)
+pub fn split_trailing_expression(input: &str) -> Option<(&str, &str)> {
+    if classify(input) != InputKind::Expression {
+        return None;
+    }
+
+    // the statements end with a `;` or a `}`, try the candidates starting from the end
+    input
+        .char_indices()
+        .rev()
+        .filter(|(_, c)| *c == ';' || *c == '}')
+        .find_map(|(idx, _)| {
+            let (statements, expression) = input.split_at(idx + 1);
+            if expression.trim().is_empty() || syn::parse_str::<syn::Expr>(expression).is_err() {
+                return None;
+            }
+            match classify(statements) {
+                InputKind::Item | InputKind::Statement => Some((statements, expression)),
+                _ => None,
+            }
+        })
+}
+
+/// Whether `input` looks like a type rather than an expression,
+/// exp: `Vec<u8>`, `&str`, `[u8; 4]` but not `v` or `&v`
+pub fn is_type(input: &str) -> bool {
+    match syn::parse_str::<syn::Type>(input) {
+        Ok(ty) => type_like(&ty),
+        Err(_) => false,
+    }
+}
+
+fn type_like(ty: &syn::Type) -> bool {
+    const PRIMITIVES: [&str; 17] = [
+        "bool", "char", "str", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32",
+        "u64", "u128", "usize", "f32", "f64",
+    ];
+
+    match ty {
+        // a lowercase path is a variable
+        syn::Type::Path(path) => path.path.segments.last().is_some_and(|segment| {
+            let name = segment.ident.to_string();
+            name.starts_with(char::is_uppercase)
+                || PRIMITIVES.contains(&name.as_str())
+                || !segment.arguments.is_empty()
+        }),
+        syn::Type::Reference(reference) => type_like(&reference.elem),
+        syn::Type::Slice(slice) => type_like(&slice.elem),
+        syn::Type::Array(array) => type_like(&array.elem),
+        syn::Type::Paren(paren) => type_like(&paren.elem),
+        syn::Type::Tuple(tuple) => tuple.elems.iter().all(type_like),
+        _ => true,
+    }
+}
+
+/// A simple `let [mut] name [: Type] = expr;` statement
+pub struct LetBinding {
+    pub name: String,
+    pub mutable: bool,
+    /// true if `expr` is already a literal, so there is nothing to save by persisting it
+    pub literal: bool,
+}
+
+pub fn let_binding(input: &str) -> Option<LetBinding> {
+    let block: syn::Block = syn::parse_str(&format!("{{\n{}\n}}", input)).ok()?;
+    if block.stmts.len() != 1 {
+        return None;
+    }
+
+    let local = match &block.stmts[0] {
+        Stmt::Local(local) => local,
+        _ => return None,
+    };
+    let pat = match &local.pat {
+        syn::Pat::Type(pat_type) => &*pat_type.pat,
+        pat => pat,
+    };
+    let ident = match pat {
+        syn::Pat::Ident(ident) if ident.by_ref.is_none() && ident.subpat.is_none() => ident,
+        _ => return None,
+    };
+    let (_, expr) = local.init.as_ref()?;
+
+    Some(LetBinding {
+        name: ident.ident.to_string(),
+        mutable: ident.mutability.is_some(),
+        literal: matches!(**expr, syn::Expr::Lit(_)),
+    })
+}
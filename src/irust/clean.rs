@@ -0,0 +1,200 @@
+use super::cargo_cmds::cargo_check_warnings;
+use super::cmd_args::CmdArgs;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+
+/// Lines of the repl code that `:clean` can remove
+pub struct CleanCandidate {
+    lines: Vec<String>,
+    // index of the first line in the repl code
+    line: usize,
+    reason: String,
+}
+
+impl IRust {
+    pub fn clean(&mut self) -> Result<Printer, IRustError> {
+        // exp: :clean, :clean #1 #3, :clean all
+        let args = CmdArgs::parse("clean", &self.buffer.to_string())?;
+        if !args.is_empty() {
+            return self.remove_clean_candidates(&args.as_strs());
+        }
+
+        let output = cargo_check_warnings(&self.repl.code_with("").0)?;
+        self.clean_candidates =
+            clean_candidates(&self.repl.body, &String::from_utf8_lossy(&output.stderr));
+        if self.clean_candidates.is_empty() {
+            let mut outputs = Printer::new(PrinterItem::new(
+                "Nothing to clean".to_string(),
+                PrinterItemType::Ok,
+            ));
+            outputs.add_new_line(1);
+            return Ok(outputs);
+        }
+
+        let mut outputs = Printer::default();
+        for (idx, candidate) in self.clean_candidates.iter().enumerate() {
+            outputs.push(PrinterItem::new(
+                format!("#{} repl:{} ", idx + 1, candidate.line),
+                PrinterItemType::Ok,
+            ));
+            outputs.push(PrinterItem::new(
+                candidate.reason.clone(),
+                PrinterItemType::Warn,
+            ));
+            outputs.add_new_line(1);
+            outputs.push(PrinterItem::new(
+                format!("   {}", candidate.lines[0].trim()),
+                PrinterItemType::Eval,
+            ));
+            outputs.add_new_line(1);
+        }
+        outputs.push(PrinterItem::new(
+            "IRust: use `:clean #<number>..` to remove some of them, or `:clean all`".to_string(),
+            PrinterItemType::Warn,
+        ));
+        outputs.add_new_line(1);
+
+        Ok(outputs)
+    }
+
+    fn remove_clean_candidates(&mut self, args: &[&str]) -> Result<Printer, IRustError> {
+        let mut selected: Vec<usize> = if args == ["all"] {
+            (0..self.clean_candidates.len()).collect()
+        } else {
+            args.iter()
+                .map(|arg| {
+                    arg.strip_prefix('#')
+                        .and_then(|n| n.parse::<usize>().ok())
+                        .and_then(|n| n.checked_sub(1))
+                        .filter(|idx| *idx < self.clean_candidates.len())
+                        .ok_or_else(|| {
+                            IRustError::Custom(format!("No clean candidate {}, use :clean", arg))
+                        })
+                })
+                .collect::<Result<_, _>>()?
+        };
+        if selected.is_empty() {
+            return Err(IRustError::Custom(
+                "Nothing to clean, use :clean first".to_string(),
+            ));
+        }
+
+        // the last lines first, so the lines of the other candidates don't move
+        selected.sort_by_key(|idx| std::cmp::Reverse(self.clean_candidates[*idx].line));
+        selected.dedup();
+        let mut removed = 0;
+        for idx in selected {
+            let candidate = &self.clean_candidates[idx];
+            if self.repl.remove_lines(candidate.line, &candidate.lines) {
+                removed += 1;
+            }
+        }
+        self.clean_candidates.clear();
+        self.repl_changed(":clean")?;
+
+        let mut outputs = Printer::new(PrinterItem::new(
+            format!("Removed {} item(s), use :undo to get them back", removed),
+            PrinterItemType::Ok,
+        ));
+        outputs.add_new_line(1);
+        Ok(outputs)
+    }
+}
+
+/// Duplicate `use` statements, unused imports, shadowed `let` bindings that are never read
+/// and functions that are never used, found in the `cargo check` warnings
+fn clean_candidates(body: &[String], warnings: &str) -> Vec<CleanCandidate> {
+    let mut candidates = vec![];
+    for (idx, line) in body.iter().enumerate() {
+        let line = line.trim();
+        if line.starts_with("use ") && body[..idx].iter().any(|l| l.trim() == line) {
+            candidates.push(CleanCandidate {
+                lines: vec![body[idx].clone()],
+                line: idx,
+                reason: "duplicate use".to_string(),
+            });
+        }
+    }
+
+    // exp: src/main.rs:3:9: warning: unused variable: `x`
+    for warning in warnings.lines() {
+        let mut parts = match warning.strip_prefix("src/main.rs:") {
+            Some(warning) => warning.splitn(3, ':'),
+            None => continue,
+        };
+        let idx = match parts.next().and_then(|line| line.parse::<usize>().ok()) {
+            Some(line) if line > 1 && line <= body.len() => line - 1,
+            _ => continue,
+        };
+        let message = match parts
+            .nth(1)
+            .and_then(|m| m.trim().strip_prefix("warning: "))
+        {
+            Some(message) => message,
+            None => continue,
+        };
+        let line = body[idx].trim();
+
+        let reason = if message.starts_with("unused import") {
+            // only some names of a `use a::{b, c}` may be unused
+            if !line.starts_with("use ") || line.contains('{') {
+                continue;
+            }
+            message.to_owned()
+        } else if message.starts_with("unused variable") && line.starts_with("let ") {
+            // the repl bindings are used by the evaluations, only the shadowed ones are dead
+            let name = message.split('`').nth(1).unwrap_or_default();
+            let shadowed = body[idx + 1..]
+                .iter()
+                .any(|l| let_name(l.trim()) == Some(name));
+            if !shadowed {
+                continue;
+            }
+            format!("{}, shadowed later", message)
+        } else if message.starts_with("function") && message.ends_with("is never used") {
+            format!("{} in the repl code", message)
+        } else {
+            continue;
+        };
+
+        if candidates.iter().any(|c: &CleanCandidate| c.line == idx) {
+            continue;
+        }
+        candidates.push(CleanCandidate {
+            lines: body[idx..item_end(body, idx)].to_vec(),
+            line: idx,
+            reason,
+        });
+    }
+    candidates.sort_by_key(|candidate| candidate.line);
+    candidates
+}
+
+// exp: `let mut x: u8 = 1;` => x
+fn let_name(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix("let ")?.trim_start();
+    let rest = rest.strip_prefix("mut ").unwrap_or(rest).trim_start();
+    let end = rest
+        .find(|c: char| !c.is_alphanumeric() && c != '_')
+        .unwrap_or(rest.len());
+    Some(&rest[..end])
+}
+
+// the index after the last line of the statement or item starting at `start`
+fn item_end(body: &[String], start: usize) -> usize {
+    let mut depth = 0i32;
+    for (idx, line) in body.iter().enumerate().skip(start) {
+        for c in line.chars() {
+            match c {
+                '{' | '(' | '[' => depth += 1,
+                '}' | ')' | ']' => depth -= 1,
+                _ => (),
+            }
+        }
+        let line = line.trim_end();
+        if depth <= 0 && (line.ends_with(';') || line.ends_with('}')) {
+            return idx + 1;
+        }
+    }
+    start + 1
+}
@@ -0,0 +1,249 @@
+use super::help::usage_error;
+use super::IRustError;
+
+/// The arguments of a `:` command, split like a shell does: quotes keep the spaces of an
+/// argument and a backslash escapes the next character, exp: `:add "my crate" --features "a b"`
+///
+/// The commands taking Rust code, a pattern or a TOML fragment use `parse_code` instead, which
+/// keeps that argument as is since its quotes and backslashes are its own, exp: `:peek "a b".len()`
+pub struct CmdArgs {
+    // the command name, for the usage errors
+    name: &'static str,
+    args: Vec<String>,
+    // the part of the command that isn't split yet, see `parse_code`
+    code: String,
+}
+
+impl CmdArgs {
+    /// The arguments after the command name of `buffer` (`:<name> ...`)
+    pub fn parse(name: &'static str, buffer: &str) -> Result<Self, IRustError> {
+        match split_args(command_args(buffer)) {
+            Some(args) => Ok(Self {
+                name,
+                args,
+                code: String::new(),
+            }),
+            None => Err(unterminated_quote(name)),
+        }
+    }
+
+    /// Everything after the command name of `buffer` kept as is, the leading options are taken
+    /// off it with `take_flag` and `take_arg`, exp: `:bench -n 100 v.sort()`
+    pub fn parse_code(name: &'static str, buffer: &str) -> Self {
+        Self {
+            name,
+            args: vec![],
+            code: command_args(buffer).trim().to_owned(),
+        }
+    }
+
+    /// Remove every `option` and its value from the arguments, exp: `--env KEY=VALUE`
+    pub fn option_values(&mut self, option: &str) -> Result<Vec<String>, IRustError> {
+        let mut values = vec![];
+        while let Some(idx) = self.args.iter().position(|arg| arg == option) {
+            if idx + 1 == self.args.len() {
+                return Err(self.usage_error());
+            }
+            self.args.remove(idx);
+            values.push(self.args.remove(idx));
+        }
+        Ok(values)
+    }
+
+    /// Remove every `flag` from the arguments, whether it was there
+    pub fn flag(&mut self, flag: &str) -> bool {
+        let len = self.args.len();
+        self.args.retain(|arg| arg != flag);
+        self.args.len() != len
+    }
+
+    /// Take the first argument off the code if it's `flag`, exp: `--open` of `:profile --open f()`
+    pub fn take_flag(&mut self, flag: &str) -> bool {
+        // the code may have quotes of its own further on, they aren't an error here
+        match next_arg(&self.code) {
+            Ok(Some((arg, rest))) if arg == flag => {
+                self.code = rest.trim_start().to_owned();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Take the first argument off the code, exp: the number of `:bench -n 100 v.sort()`
+    pub fn take_arg(&mut self) -> Result<Option<String>, IRustError> {
+        match next_arg(&self.code) {
+            Ok(Some((arg, rest))) => {
+                self.code = rest.trim_start().to_owned();
+                Ok(Some(arg))
+            }
+            Ok(None) => Ok(None),
+            Err(UnterminatedQuote) => Err(unterminated_quote(self.name)),
+        }
+    }
+
+    /// The code that's left, it can be empty
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// The code that's left as an expression, without a trailing `;` which would make it
+    /// evaluate to `()`
+    pub fn expression(&self) -> &str {
+        self.code.trim_end_matches(';').trim_end()
+    }
+
+    pub fn usage_error(&self) -> IRustError {
+        IRustError::Custom(usage_error(self.name))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty()
+    }
+
+    /// The arguments as string slices, to match them against literals
+    pub fn as_strs(&self) -> Vec<&str> {
+        self.args.iter().map(String::as_str).collect()
+    }
+
+    pub fn into_vec(self) -> Vec<String> {
+        self.args
+    }
+}
+
+// what follows the command name of `buffer`
+fn command_args(buffer: &str) -> &str {
+    buffer
+        .trim_start()
+        .split_once(char::is_whitespace)
+        .map(|(_, args)| args)
+        .unwrap_or_default()
+}
+
+fn unterminated_quote(name: &str) -> IRustError {
+    IRustError::Custom(format!(
+        "Unterminated quote in the arguments\n{}",
+        usage_error(name)
+    ))
+}
+
+struct UnterminatedQuote;
+
+/// Split `args` like a shell does, `None` if a quote isn't closed
+pub fn split_args(args: &str) -> Option<Vec<String>> {
+    let mut split = vec![];
+    let mut rest = args;
+    while let Some((arg, after)) = next_arg(rest).ok()? {
+        split.push(arg);
+        rest = after;
+    }
+    Some(split)
+}
+
+// the first argument of `args` and what follows it
+fn next_arg(args: &str) -> Result<Option<(String, &str)>, UnterminatedQuote> {
+    // `None` until the argument starts, so `""` is an argument
+    let mut current: Option<String> = None;
+    let mut quote: Option<char> = None;
+    let mut chars = args.char_indices();
+
+    while let Some((idx, c)) = chars.next() {
+        match (c, quote) {
+            ('\\', Some('\'')) => current.get_or_insert_with(String::new).push(c),
+            ('\\', _) => {
+                let escaped = chars.next().map_or('\\', |(_, c)| c);
+                current.get_or_insert_with(String::new).push(escaped);
+            }
+            (c, Some(q)) if c == q => quote = None,
+            (c, Some(_)) => current.get_or_insert_with(String::new).push(c),
+            ('"', None) | ('\'', None) => {
+                quote = Some(c);
+                current.get_or_insert_with(String::new);
+            }
+            (c, None) if c.is_whitespace() => {
+                if let Some(arg) = current {
+                    return Ok(Some((arg, &args[idx..])));
+                }
+            }
+            (c, None) => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err(UnterminatedQuote);
+    }
+    Ok(current.map(|arg| (arg, "")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(args: &str) -> Vec<String> {
+        split_args(args).unwrap()
+    }
+
+    #[test]
+    fn split_words_and_quotes() {
+        assert_eq!(split("  a  b\tc "), ["a", "b", "c"]);
+        assert_eq!(split(r#"a "b c" 'd e'"#), ["a", "b c", "d e"]);
+        assert_eq!(split(r#"--features "a b"x"#), ["--features", "a bx"]);
+        assert_eq!(split(r#"'' """#), ["", ""]);
+        assert!(split("").is_empty());
+    }
+
+    #[test]
+    fn split_escapes() {
+        assert_eq!(split(r"a\ b"), ["a b"]);
+        assert_eq!(split(r#""a \" b""#), [r#"a " b"#]);
+        assert_eq!(split(r"'a\b'"), [r"a\b"]);
+        assert_eq!(split(r"a\"), [r"a\"]);
+    }
+
+    #[test]
+    fn split_unterminated_quote() {
+        assert_eq!(split_args(r#"a "b"#), None);
+        assert_eq!(split_args("'a"), None);
+        assert_eq!(split_args(r#""a\""#), None);
+    }
+
+    #[test]
+    fn parse_after_the_command_name() {
+        let args = CmdArgs::parse("add", r#":add "my crate" --features "a b""#).unwrap();
+        assert_eq!(args.into_vec(), ["my crate", "--features", "a b"]);
+        assert!(CmdArgs::parse("add", ":add").unwrap().is_empty());
+        assert!(CmdArgs::parse("add", r#":add "my crate"#).is_err());
+    }
+
+    #[test]
+    fn options_and_flags() {
+        let mut args = CmdArgs::parse("rerun", ":rerun --all 3 --env A=1 --env B=2").unwrap();
+        assert_eq!(args.option_values("--env").unwrap(), ["A=1", "B=2"]);
+        assert!(args.flag("--all"));
+        assert!(!args.flag("--all"));
+        assert_eq!(args.as_strs(), ["3"]);
+
+        let mut args = CmdArgs::parse("add", ":add a --features").unwrap();
+        assert!(args.option_values("--features").is_err());
+    }
+
+    #[test]
+    fn code_keeps_its_quotes() {
+        let args = CmdArgs::parse_code("peek", r#":peek "a b".replace('\\', "") ;"#);
+        assert_eq!(args.expression(), r#""a b".replace('\\', "")"#);
+        assert_eq!(CmdArgs::parse_code("peek", ":peek ; ").expression(), "");
+    }
+
+    #[test]
+    fn code_leading_options() {
+        let mut args = CmdArgs::parse_code("bench", r#":bench -n "100" s.split(" ")"#);
+        assert!(args.take_flag("-n"));
+        assert_eq!(args.take_arg().unwrap().as_deref(), Some("100"));
+        assert!(!args.take_flag("-n"));
+        assert_eq!(args.code(), r#"s.split(" ")"#);
+
+        // a quote of the code isn't an argument error
+        let mut args = CmdArgs::parse_code("profile", r#":profile "a"#);
+        assert!(!args.take_flag("--open"));
+        assert!(args.take_arg().is_err());
+        assert_eq!(args.code(), r#""a"#);
+    }
+}
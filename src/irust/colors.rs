@@ -0,0 +1,67 @@
+use super::options::COLOR_OPTIONS;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+use crossterm::Color;
+
+const SAMPLE: &str = "let answer = 42;";
+
+impl IRust {
+    /// Preview every color option with its current value
+    pub fn colors(&mut self) -> Result<Printer, IRustError> {
+        let mut options = self.options.clone();
+        let width = COLOR_OPTIONS
+            .iter()
+            .map(|name| name.len())
+            .max()
+            .unwrap_or(0);
+
+        let mut outputs = Printer::default();
+        for name in COLOR_OPTIONS.iter() {
+            let color = match options.color_mut(name) {
+                Some(color) => *color,
+                None => continue,
+            };
+            outputs.push(PrinterItem::new(
+                format!("{:width$}  {:12}  ", name, color_name(color), width = width),
+                PrinterItemType::Eval,
+            ));
+            outputs.push(PrinterItem::new(
+                SAMPLE.to_string(),
+                PrinterItemType::Custom(color),
+            ));
+            outputs.add_new_line(1);
+        }
+        outputs.push(PrinterItem::new(
+            "IRust: set them in the [Colors] section of the config file, with a name or #rrggbb"
+                .to_string(),
+            PrinterItemType::Warn,
+        ));
+        outputs.add_new_line(1);
+
+        Ok(outputs)
+    }
+
+    /// The errors found in the config file, printed at startup
+    pub fn config_errors(&self) -> Option<Printer> {
+        if self.options.config_errors.is_empty() {
+            return None;
+        }
+        let mut outputs = Printer::default();
+        for error in &self.options.config_errors {
+            outputs.push(PrinterItem::new(
+                format!("IRust config: {}", error),
+                PrinterItemType::Warn,
+            ));
+            outputs.add_new_line(1);
+        }
+        Some(outputs)
+    }
+}
+
+// exp: DarkBlue, #1e90ff
+fn color_name(color: Color) -> String {
+    match color {
+        Color::Rgb { r, g, b } => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        color => format!("{:?}", color),
+    }
+}
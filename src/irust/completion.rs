@@ -0,0 +1,198 @@
+use std::collections::{HashMap, HashSet};
+
+// scanning the whole history on every keystroke is not worth it
+const HISTORY_SCAN: usize = 500;
+
+/// Where a completion candidate comes from, the declaration order is the rank of
+/// candidates matching the typed word equally well
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Source {
+    Command,
+    Racer,
+    Session,
+    Snippet,
+    History,
+}
+
+impl Source {
+    pub fn tag(self) -> &'static str {
+        match self {
+            Source::Command => "command",
+            Source::Racer => "racer",
+            Source::Session => "session",
+            Source::Snippet => "snippet",
+            Source::History => "history",
+        }
+    }
+}
+
+// (Name, definition, first line of the docs, source)
+pub type Suggestion = (String, String, String, Source);
+
+// (snippet, description), the snippet is completed from the word it starts with
+const SNIPPETS: [(&str, &str); 12] = [
+    ("println!(\"{:?}\", )", "print the Debug representation"),
+    ("println!(\"{}\", )", "print the Display representation"),
+    ("format!(\"{}\", )", "format a String"),
+    ("vec![]", "create a Vec"),
+    ("assert_eq!(, )", "assert two values are equal"),
+    ("dbg!()", "print and return a value"),
+    ("for i in 0.. {}", "loop over a range"),
+    ("if let Some() =  {}", "match an Option"),
+    ("match  {}", "match a value"),
+    ("impl  {}", "implement a type"),
+    ("struct  {}", "declare a struct"),
+    ("fn () {}", "declare a function"),
+];
+
+const KEYWORDS: [&str; 38] = [
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+];
+
+// the identifier after them is declared on that line
+const DECLARATIONS: [&str; 9] = [
+    "let", "mut", "fn", "struct", "enum", "trait", "type", "const", "static",
+];
+
+/// Identifiers declared in the repl body, each with the line declaring it
+pub fn session_candidates(body: &[String], prefix: &str) -> Vec<Suggestion> {
+    let mut declared = vec![];
+    let mut seen = HashSet::new();
+    for line in body {
+        for name in declared_names(line) {
+            if is_candidate(name, prefix) && seen.insert(name.to_owned()) {
+                declared.push((
+                    name.to_owned(),
+                    line.trim().to_owned(),
+                    String::new(),
+                    Source::Session,
+                ));
+            }
+        }
+    }
+    declared
+}
+
+/// Identifiers used in the last history entries, the most used first
+pub fn history_candidates(history: &[String], prefix: &str) -> Vec<Suggestion> {
+    let mut uses: HashMap<&str, usize> = HashMap::new();
+    for entry in history.iter().rev().take(HISTORY_SCAN) {
+        for word in identifiers(entry) {
+            if is_candidate(word, prefix) {
+                *uses.entry(word).or_default() += 1;
+            }
+        }
+    }
+    let mut uses: Vec<(&str, usize)> = uses.into_iter().collect();
+    uses.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    uses.into_iter()
+        .map(|(word, _)| {
+            (
+                word.to_owned(),
+                String::new(),
+                String::new(),
+                Source::History,
+            )
+        })
+        .collect()
+}
+
+pub fn snippet_candidates(prefix: &str) -> Vec<Suggestion> {
+    if prefix.is_empty() {
+        return vec![];
+    }
+    SNIPPETS
+        .iter()
+        .filter(|(snippet, _)| snippet.starts_with(prefix))
+        .map(|(snippet, description)| {
+            (
+                (*snippet).to_owned(),
+                (*description).to_owned(),
+                String::new(),
+                Source::Snippet,
+            )
+        })
+        .collect()
+}
+
+/// One list ranked by how well the candidates match `prefix`, then by their source,
+/// a name suggested by several sources is kept once with its best source
+pub fn merge(prefix: &str, sources: Vec<Vec<Suggestion>>) -> Vec<Suggestion> {
+    let mut candidates: Vec<Suggestion> = sources.into_iter().flatten().collect();
+    // the sort is stable, every source keeps its own order
+    candidates.sort_by_key(|c| (!c.0.starts_with(prefix), c.3));
+
+    let mut seen = HashSet::new();
+    candidates.retain(|c| seen.insert(c.0.clone()));
+    candidates
+}
+
+/// The identifiers declared on the line
+pub fn declared_names(line: &str) -> Vec<&str> {
+    identifiers(line)
+        .windows(2)
+        .filter(|pair| DECLARATIONS.contains(&pair[0]))
+        .map(|pair| pair[1])
+        .collect()
+}
+
+pub fn identifiers(code: &str) -> Vec<&str> {
+    code.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|word| {
+            word.chars()
+                .next()
+                .is_some_and(|c| c.is_alphabetic() || c == '_')
+        })
+        .collect()
+}
+
+// the word being typed is not worth suggesting
+fn is_candidate(word: &str, prefix: &str) -> bool {
+    word.starts_with(prefix) && word != prefix && word.len() > 1 && !KEYWORDS.contains(&word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suggestion(name: &str, source: Source) -> Suggestion {
+        (name.to_owned(), String::new(), String::new(), source)
+    }
+
+    #[test]
+    fn merge_ranks_by_prefix_then_source() {
+        let merged = merge(
+            "pr",
+            vec![
+                vec![suggestion("sprint", Source::Racer)],
+                vec![suggestion("print", Source::History)],
+                vec![suggestion("price", Source::Session)],
+            ],
+        );
+        let names: Vec<&str> = merged.iter().map(|c| c.0.as_str()).collect();
+        assert_eq!(names, ["price", "print", "sprint"]);
+    }
+
+    #[test]
+    fn merge_keeps_a_name_once_with_its_best_source() {
+        let merged = merge(
+            "",
+            vec![
+                vec![suggestion("x1", Source::History)],
+                vec![suggestion("x1", Source::Session)],
+                vec![suggestion("x2", Source::History)],
+            ],
+        );
+        assert_eq!(
+            merged,
+            [
+                suggestion("x1", Source::Session),
+                suggestion("x2", Source::History)
+            ]
+        );
+    }
+}
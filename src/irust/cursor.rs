@@ -38,6 +38,15 @@ impl Cursor {
         }
     }
 
+    /// Change the width the cursor wraps at, exp: when the repl code pane is toggled
+    pub fn set_width(&mut self, width: usize) {
+        self.bound = Bound::new(width, self.bound.height);
+    }
+
+    pub fn set_size(&mut self, width: usize, height: usize) {
+        self.bound = Bound::new(width, height);
+    }
+
     pub fn save_position(&mut self) -> Result<(), IRustError> {
         self.copy = Some(Box::new(self.pos.clone()));
         self.cursor.save_position()?;
@@ -72,23 +81,11 @@ impl Cursor {
         let _ = self.goto_internal_pos();
     }
 
-    pub fn move_up_bounded(&mut self, count: u16) {
-        self.move_up(count);
-        self.pos.current_pos.0 = std::cmp::min(self.pos.current_pos.0, self.current_row_bound());
-        let _ = self.goto_internal_pos();
-    }
-
     pub fn move_up(&mut self, count: u16) {
         self.pos.current_pos.1 = self.pos.current_pos.1.saturating_sub(count as usize);
         let _ = self.cursor.move_up(count);
     }
 
-    pub fn move_down_bounded(&mut self, count: u16) {
-        self.move_down(count);
-        self.pos.current_pos.0 = std::cmp::min(self.pos.current_pos.0, self.current_row_bound());
-        let _ = self.goto_internal_pos();
-    }
-
     pub fn move_down(&mut self, count: u16) {
         self.pos.current_pos.1 += count as usize;
         let _ = self.cursor.move_down(count);
@@ -190,21 +187,6 @@ impl Cursor {
         self.goto(0, input_last_row);
     }
 
-    pub fn is_at_first_input_line(&self) -> bool {
-        self.pos.current_pos.1 == self.pos.starting_pos.1
-    }
-
-    pub fn is_at_last_input_line(&self, buffer: &Buffer) -> bool {
-        self.pos.current_pos.1 == self.input_last_pos(buffer).1
-    }
-
-    pub fn cursor_pos_to_buffer_pos(&self) -> usize {
-        self.pos.current_pos.0 - INPUT_START_COL
-            + self
-                .bound
-                .bounds_sum(self.pos.starting_pos.1, self.pos.current_pos.1)
-    }
-
     pub fn goto_next_row_terminal_start(&mut self) {
         self.goto(0, self.pos.current_pos.1 + 1);
     }
@@ -1,20 +1,22 @@
 use std::sync::{mpsc, Arc, Mutex};
 use std::time::{Duration, Instant};
 
-const WAIT_TIMEOUT: u64 = 50;
 const SLEEP_TIME: u64 = 300;
 
 pub struct Debouncer {
     timer: Arc<Mutex<Instant>>,
+    // time without a keystroke before the completion is requested
+    wait: Duration,
     send: mpsc::Sender<usize>,
     pub recv: mpsc::Receiver<usize>,
 }
 
 impl Debouncer {
-    pub fn new() -> Self {
+    pub fn new(wait: Duration) -> Self {
         let (send, recv) = mpsc::channel();
         Self {
             timer: Arc::new(Mutex::new(Instant::now())),
+            wait,
             send,
             recv,
         }
@@ -23,8 +25,9 @@ impl Debouncer {
     pub fn run(&mut self) {
         let send = self.send.clone();
         let timer = self.timer.clone();
+        let wait = self.wait;
         std::thread::spawn(move || loop {
-            if timer.lock().unwrap().elapsed() >= Duration::from_millis(WAIT_TIMEOUT) {
+            if timer.lock().unwrap().elapsed() >= wait {
                 send.send(1).unwrap();
             }
             std::thread::sleep(std::time::Duration::from_millis(SLEEP_TIME));
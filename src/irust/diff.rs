@@ -0,0 +1,238 @@
+use super::cmd_args::CmdArgs;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+
+// unchanged lines shown around the changes
+const CONTEXT: usize = 3;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Change {
+    Same,
+    Removed,
+    Added,
+}
+
+// line by line diff using the longest common subsequence
+fn diff_lines<'a>(old: &'a [String], new: &'a [String]) -> Vec<(Change, &'a str)> {
+    // lcs[i][j] = longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                std::cmp::max(lcs[i + 1][j], lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            changes.push((Change::Same, old[i].as_str()));
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            changes.push((Change::Added, new[j].as_str()));
+            j += 1;
+        } else {
+            changes.push((Change::Removed, old[i].as_str()));
+            i += 1;
+        }
+    }
+    changes
+}
+
+// above this many character pairs the values are compared position by position, like `watch -d`
+const CHAR_DIFF_MAX: usize = 1_000_000;
+
+/// For each character of `new`, whether it's not in `old`
+pub fn changed_chars(old: &str, new: &str) -> Vec<bool> {
+    let old: Vec<char> = old.chars().collect();
+    let new: Vec<char> = new.chars().collect();
+    if old.len() * new.len() > CHAR_DIFF_MAX {
+        return new
+            .iter()
+            .enumerate()
+            .map(|(idx, c)| old.get(idx) != Some(c))
+            .collect();
+    }
+
+    // same longest common subsequence as `diff_lines`
+    let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                std::cmp::max(lcs[i + 1][j], lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changed = vec![true; new.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            changed[j] = false;
+            i += 1;
+            j += 1;
+        } else if lcs[i][j + 1] >= lcs[i + 1][j] {
+            j += 1;
+        } else {
+            i += 1;
+        }
+    }
+    changed
+}
+
+impl IRust {
+    pub fn checkpoint(&mut self) -> Result<Printer, IRustError> {
+        // exp: :checkpoint parsing
+        let args = CmdArgs::parse("checkpoint", &self.buffer.to_string())?;
+        let name = match args.as_strs().as_slice() {
+            [name] => name.to_string(),
+            [] => {
+                return Err(IRustError::Custom(
+                    "No checkpoint name specified".to_string(),
+                ))
+            }
+            _ => return Err(args.usage_error()),
+        };
+
+        self.checkpoints
+            .retain(|(checkpoint, _)| *checkpoint != name);
+        self.checkpoints.push((name, self.repl.body.clone()));
+
+        let mut outputs = Printer::new(PrinterItem::new("Ok!".to_string(), PrinterItemType::Ok));
+        outputs.add_new_line(1);
+
+        Ok(outputs)
+    }
+
+    pub fn diff(&mut self) -> Result<Printer, IRustError> {
+        // exp: :diff, :diff parsing
+        let args = CmdArgs::parse("diff", &self.buffer.to_string())?;
+        let (name, old) = match args.as_strs().as_slice() {
+            [name] => match self
+                .checkpoints
+                .iter()
+                .find(|(checkpoint, _)| checkpoint == name)
+            {
+                Some((name, body)) => (name.clone(), body.clone()),
+                None => return Err(IRustError::Custom(format!("No checkpoint named {}", name))),
+            },
+            [] => ("session start".to_string(), self.session_start.clone()),
+            _ => return Err(args.usage_error()),
+        };
+
+        Ok(unified_diff(&old, &self.repl.body, &name, "current"))
+    }
+}
+
+/// Colored unified diff of the repl code, `No changes since <old_name>` if there are none
+pub fn unified_diff(old: &[String], new: &[String], old_name: &str, new_name: &str) -> Printer {
+    let changes = diff_lines(old, new);
+    if changes.iter().all(|(change, _)| *change == Change::Same) {
+        let mut outputs = Printer::new(PrinterItem::new(
+            format!("No changes since {}", old_name),
+            PrinterItemType::Warn,
+        ));
+        outputs.add_new_line(1);
+        return outputs;
+    }
+
+    let mut outputs = Printer::default();
+    outputs.push(PrinterItem::new(
+        format!("--- {}", old_name),
+        PrinterItemType::Eval,
+    ));
+    outputs.add_new_line(1);
+    outputs.push(PrinterItem::new(
+        format!("+++ {}", new_name),
+        PrinterItemType::Eval,
+    ));
+    outputs.add_new_line(1);
+
+    // group the changes with their context into hunks
+    let near_change = |idx: usize| {
+        changes[idx.saturating_sub(CONTEXT)..std::cmp::min(idx + CONTEXT + 1, changes.len())]
+            .iter()
+            .any(|(change, _)| *change != Change::Same)
+    };
+    let (mut old_line, mut new_line) = (1, 1);
+    let mut idx = 0;
+    while idx < changes.len() {
+        if !near_change(idx) {
+            old_line += 1;
+            new_line += 1;
+            idx += 1;
+            continue;
+        }
+
+        let hunk_start = idx;
+        while idx < changes.len() && near_change(idx) {
+            idx += 1;
+        }
+        let hunk = &changes[hunk_start..idx];
+        let old_len = hunk.iter().filter(|(c, _)| *c != Change::Added).count();
+        let new_len = hunk.iter().filter(|(c, _)| *c != Change::Removed).count();
+
+        outputs.push(PrinterItem::new(
+            format!("@@ -{},{} +{},{} @@", old_line, old_len, new_line, new_len),
+            PrinterItemType::Shell,
+        ));
+        outputs.add_new_line(1);
+        for (change, line) in hunk {
+            let (prefix, item_type) = match change {
+                Change::Same => (' ', PrinterItemType::Eval),
+                Change::Removed => ('-', PrinterItemType::Err),
+                Change::Added => ('+', PrinterItemType::Ok),
+            };
+            outputs.push(PrinterItem::new(format!("{}{}", prefix, line), item_type));
+            outputs.add_new_line(1);
+        }
+        old_line += old_len;
+        new_line += new_len;
+    }
+
+    outputs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // exp: [" a", "-b", "+c"]
+    fn diff(old: &[&str], new: &[&str]) -> Vec<String> {
+        let old: Vec<String> = old.iter().map(ToString::to_string).collect();
+        let new: Vec<String> = new.iter().map(ToString::to_string).collect();
+        diff_lines(&old, &new)
+            .into_iter()
+            .map(|(change, line)| {
+                let mark = match change {
+                    Change::Same => ' ',
+                    Change::Removed => '-',
+                    Change::Added => '+',
+                };
+                format!("{}{}", mark, line)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn diff_lines_changes() {
+        assert_eq!(
+            diff(&["a", "b", "c"], &["a", "x", "c", "d"]),
+            [" a", "+x", "-b", " c", "+d"]
+        );
+    }
+
+    #[test]
+    fn diff_lines_empty_sides() {
+        assert_eq!(diff(&[], &["a"]), ["+a"]);
+        assert_eq!(diff(&["a"], &[]), ["-a"]);
+        assert!(diff(&[], &[]).is_empty());
+    }
+}
@@ -0,0 +1,103 @@
+use super::cargo_cmds::{cargo_doc, locked_versions, TARGET_DIR};
+use super::cmd_args::CmdArgs;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+use std::process::{Command, Stdio};
+
+// documented on doc.rust-lang.org and shipped with `rustup doc`
+const STD_CRATES: [&str; 5] = ["std", "core", "alloc", "proc_macro", "test"];
+
+impl IRust {
+    pub fn docs(&mut self) -> Result<Printer, IRustError> {
+        // exp: :docs std::collections::HashMap, :docs rand::Rng --offline-docs
+        let mut args = CmdArgs::parse("docs", &self.buffer.to_string())?;
+        let offline = args.flag("--offline-docs");
+        let paths = args.as_strs();
+        let path = match paths.as_slice() {
+            [path] => path.trim_start_matches("::"),
+            [] => return Err(IRustError::Custom("No item specified".to_string())),
+            _ => return Err(args.usage_error()),
+        };
+        let (krate, item) = match path.find("::") {
+            Some(idx) => (&path[..idx], Some(path)),
+            None => (path, None),
+        };
+        // the rustdoc search finds the item page
+        let search = item.map_or(String::new(), |item| format!("?search={}", item));
+
+        let url = if offline {
+            self.local_docs(krate)? + &search
+        } else if STD_CRATES.contains(&krate) {
+            format!("https://doc.rust-lang.org/{}/index.html{}", krate, search)
+        } else {
+            // crates imported as `foo_bar` are often published as `foo-bar`
+            let version = locked_versions(krate)
+                .pop()
+                .or_else(|| locked_versions(&krate.replace('_', "-")).pop())
+                .unwrap_or_else(|| "latest".to_string());
+            format!("https://docs.rs/{}/{}/{}/{}", krate, version, krate, search)
+        };
+
+        let msg = if open(&url) { "Opened" } else { "Docs" };
+        // an OSC-8 hyperlink, clickable in the terminals supporting it
+        let link = format!("\x1b]8;;{url}\x1b\\{url}\x1b]8;;\x1b\\", url = url);
+        let mut outputs = Printer::new(PrinterItem::new(
+            format!("{}: {}", msg, link),
+            PrinterItemType::Ok,
+        ));
+        outputs.add_new_line(1);
+
+        Ok(outputs)
+    }
+
+    /// `file://` url of the local docs of `krate`, the repl docs are built with `cargo doc`
+    fn local_docs(&mut self, krate: &str) -> Result<String, IRustError> {
+        let index = if STD_CRATES.contains(&krate) {
+            let output = Command::new("rustup")
+                .args(["doc", "--path"])
+                .output()
+                .map_err(|_| IRustError::Custom("Local std docs require rustup".to_string()))?;
+            let path = String::from_utf8_lossy(&output.stdout);
+            std::path::Path::new(path.trim())
+                .with_file_name(krate)
+                .join("index.html")
+        } else {
+            let job = cargo_doc()?;
+            let output = self.wait_job(job, "Building docs")?;
+            if !output.status.success() {
+                return Err(IRustError::Custom(
+                    String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+                ));
+            }
+            TARGET_DIR.join("doc").join(krate).join("index.html")
+        };
+
+        if !index.exists() {
+            return Err(IRustError::Custom(format!(
+                "No local docs found for `{}`",
+                krate
+            )));
+        }
+        Ok(format!("file://{}", index.display()))
+    }
+}
+
+/// Open the url with the system default browser, returns false if it couldn't be opened
+pub fn open(url: &str) -> bool {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", "start", ""]);
+        cmd
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+
+    cmd.arg(url)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
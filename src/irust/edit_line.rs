@@ -0,0 +1,108 @@
+use super::cargo_cmds::cargo_check;
+use super::cmd_args::CmdArgs;
+use super::help::ColoredPrinterItem;
+use super::highlight::highlight;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+use crossterm::Color;
+
+/// The repl code line edited in the prompt, set by `:edit-line`
+#[derive(Default)]
+pub struct LineEdit {
+    // the repl line replaced by the next input
+    line: Option<usize>,
+    // the line put in the prompt after `:edit-line`
+    input: Option<String>,
+}
+
+impl LineEdit {
+    pub fn cancel(&mut self) {
+        *self = Self::default();
+    }
+
+    /// The edited line, the edit ends with the next input whatever it is
+    pub fn take_line(&mut self) -> Option<usize> {
+        self.line.take()
+    }
+}
+
+impl IRust {
+    pub fn edit_line(&mut self) -> Result<Printer, IRustError> {
+        // exp: :edit-line 3
+        let args = CmdArgs::parse("edit-line", &self.buffer.to_string())?;
+        let line = match args.as_strs().as_slice() {
+            [line] => line.parse::<usize>().ok(),
+            _ => return Err(args.usage_error()),
+        };
+        let line = line
+            .filter(|line| *line != 0 && line + 1 < self.repl.body.len())
+            .ok_or_else(|| IRustError::Custom("Incorrect line number".into()))?;
+        let input = self.repl.body[line].clone();
+        self.line_edit.line = Some(line);
+
+        let mut outputs = Printer::new(
+            format!(
+                "Editing repl line {}, press Enter to replace it (an empty input cancels)",
+                line
+            )
+            .to_output(Color::DarkGrey),
+        );
+        outputs.add_new_line(1);
+        // the accessible mode can't put the input in the prompt, it's printed to be copied instead
+        if self.options.accessible {
+            outputs.append(&mut highlight(&input));
+            outputs.add_new_line(1);
+        } else {
+            self.line_edit.input = Some(input);
+        }
+        Ok(outputs)
+    }
+
+    /// Put the edited line in the prompt
+    pub fn take_line_edit(&mut self) -> Result<(), IRustError> {
+        if let Some(input) = self.line_edit.input.take() {
+            self.replace_input(&input)?;
+        }
+        Ok(())
+    }
+
+    /// Replace the edited line with the input, the change is kept only if the repl code still compiles
+    pub fn replace_edited_line(&mut self, line: usize) -> Result<Printer, IRustError> {
+        let input = self.buffer.to_string();
+        if input.trim().is_empty() {
+            let mut outputs = Printer::new("Edit cancelled".to_output(Color::DarkGrey));
+            outputs.add_new_line(1);
+            return Ok(outputs);
+        }
+
+        let old_repl = self.repl.clone();
+        self.repl.replace_line(line, &input)?;
+        let output = cargo_check(&self.repl.code_with("").0)?;
+        if !output.status.success() {
+            self.repl = old_repl;
+            // the input stays in the prompt to be fixed
+            self.line_edit = LineEdit {
+                line: Some(line),
+                input: Some(input),
+            };
+            let mut outputs = Printer::new(PrinterItem::new(
+                String::from_utf8_lossy(&output.stderr)
+                    .trim_end()
+                    .to_owned(),
+                PrinterItemType::Err,
+            ));
+            outputs.add_new_line(1);
+            outputs.push(PrinterItem::new(
+                format!("IRust: repl line {} was not replaced", line),
+                PrinterItemType::Warn,
+            ));
+            outputs.add_new_line(1);
+            return Ok(outputs);
+        }
+        self.repl_changed(&format!(":edit-line {}", line))?;
+
+        let mut outputs = Printer::new(PrinterItem::new("Ok!".to_string(), PrinterItemType::Ok));
+        outputs.add_new_line(1);
+        Ok(outputs)
+    }
+}
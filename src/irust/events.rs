@@ -1,4 +1,6 @@
-use super::buffer::Buffer;
+use super::buffer::{Buffer, WordCase};
+use super::input::Key;
+use super::large_input::PASTE_CHUNK;
 use super::racer::Cycle;
 use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
 use crate::irust::{IRust, IRustError};
@@ -7,10 +9,27 @@ use crossterm::{ClearType, Color};
 
 impl IRust {
     pub fn handle_character(&mut self, c: char) -> Result<(), IRustError> {
+        if c == '\n' && self.input_is_full() {
+            return Ok(());
+        }
         self.buffer.insert(c);
+        // the characters already waiting, exp: a paste, are inserted before drawing the input once
+        let mut inserted = 1;
+        while inserted < PASTE_CHUNK {
+            match self.next_pending_char() {
+                Some('\n') if self.input_is_full() => break,
+                Some(c) => self.buffer.insert(c),
+                None => break,
+            }
+            inserted += 1;
+        }
         self.history.update_buffer_copy(&self.buffer.to_string());
         self.print_input()?;
-        self.cursor.move_right_unbounded();
+        for _ in 0..inserted {
+            self.cursor.move_right_unbounded();
+        }
+        // completion waits for a pause in the typing
+        self.debouncer.reset_timer();
 
         // Ignore RacerDisabled error
         let _ = self.unlock_racer_update();
@@ -18,6 +37,8 @@ impl IRust {
     }
 
     pub fn handle_enter(&mut self) -> Result<(), IRustError> {
+        // no suggestion is left behind the submitted input
+        self.clear_inline_suggestion()?;
         let buffer = self.buffer.to_string();
 
         if self.incomplete_input(&buffer) {
@@ -25,14 +46,19 @@ impl IRust {
             return Ok(());
         }
 
+        self.clear_input_diagnostic()?;
         self.cursor.hide();
 
-        // create a new line
-        self.write_newline()?;
+        // create a new line, a pinned input is copied above instead
+        if self.pinned_input() {
+            self.echo_input()?;
+        } else {
+            self.write_newline()?;
+        }
 
         // add commands to history
         if self.should_push_to_history(&buffer) {
-            self.history.push(buffer);
+            self.history.push(buffer.clone());
         }
 
         // parse and handle errors
@@ -40,6 +66,7 @@ impl IRust {
             Ok(out) => out,
             Err(e) => Printer::new(PrinterItem::new(e.to_string(), PrinterItemType::Err)),
         };
+        self.record_transcript(&buffer, &output);
 
         // ensure buffer is cleaned
         self.buffer.clear();
@@ -48,13 +75,22 @@ impl IRust {
         self.history.reset_buffer_copy();
 
         // write out
+        let ok = !output.has_errors();
         if !output.is_empty() {
             output.add_new_line(1);
             self.print_output(output)?;
         }
+        self.check_tutorial_input(&buffer, ok)?;
 
         self.print_input()?;
         self.write_from_terminal_start(super::IN, Color::Yellow)?;
+        self.take_cell_edit()?;
+        self.take_line_edit()?;
+        if let Some(input) = self.next_input.take() {
+            self.replace_input(&input)?;
+        }
+        // the outputs may have scrolled the pane, and the input may have changed the repl code
+        self.draw_pane(0)?;
 
         self.cursor.show();
         Ok(())
@@ -100,13 +136,16 @@ impl IRust {
     }
 
     pub fn handle_up(&mut self) -> Result<(), IRustError> {
-        if self.cursor.is_at_first_input_line() {
+        // the history is reached from the first row of the input only
+        let (x, y) = self
+            .buffer
+            .buffer_pos_to_relative_cursor_pos(self.buffer.buffer_pos);
+        if y == 0 {
             self.handle_history("up")?;
         } else {
-            self.cursor.move_up_bounded(1);
-            // set buffer cursor
-            let buffer_pos = self.cursor.cursor_pos_to_buffer_pos();
+            let buffer_pos = self.buffer.relative_cursor_pos_to_buffer_pos((x, y - 1));
             self.buffer.set_buffer_pos(buffer_pos);
+            self.goto_buffer_pos();
         }
         Ok(())
     }
@@ -115,13 +154,42 @@ impl IRust {
         if self.buffer.is_empty() {
             return Ok(());
         }
-        if self.cursor.is_at_last_input_line(&self.buffer) {
+        // the history is reached from the last row of the input only
+        let (x, y) = self
+            .buffer
+            .buffer_pos_to_relative_cursor_pos(self.buffer.buffer_pos);
+        if y == self.buffer.last_row() {
             self.handle_history("down")?;
         } else {
-            self.cursor.move_down_bounded(1);
-            // set buffer cursor
-            let buffer_pos = self.cursor.cursor_pos_to_buffer_pos();
+            let buffer_pos = self.buffer.relative_cursor_pos_to_buffer_pos((x, y + 1));
             self.buffer.set_buffer_pos(buffer_pos);
+            self.goto_buffer_pos();
+        }
+        Ok(())
+    }
+
+    /// Move the cursor to the buffer position
+    pub fn goto_buffer_pos(&mut self) {
+        let (x, y) = self
+            .buffer
+            .buffer_pos_to_relative_cursor_pos(self.buffer.buffer_pos);
+        self.cursor.goto(
+            x.saturating_sub(self.input_offset) + super::INPUT_START_COL,
+            self.cursor.pos.starting_pos.1 + y,
+        );
+    }
+
+    /// Edit the history entry being shown, Up and Down continue from it
+    pub fn handle_ctrl_e(&mut self) {
+        self.history.edit_current();
+    }
+
+    /// bash's operate-and-get-next: run the history entry being shown, then show the next one
+    pub fn handle_ctrl_o(&mut self) -> Result<(), IRustError> {
+        let next = self.history.next_index();
+        self.handle_enter()?;
+        if let Some(entry) = next.and_then(|idx| self.history.edit_entry(idx)) {
+            self.replace_input(&entry)?;
         }
         Ok(())
     }
@@ -134,15 +202,24 @@ impl IRust {
         };
 
         if let Some(history) = history {
-            self.buffer =
-                Buffer::from_str(&history, self.cursor.bound.width - super::INPUT_START_COL);
+            self.replace_input(&history)?;
+        }
+        Ok(())
+    }
 
-            self.print_input()?;
+    /// Replace the input with `input`, the cursor goes to its end
+    pub fn replace_input(&mut self, input: &str) -> Result<(), IRustError> {
+        self.buffer =
+            Buffer::from_str(input, self.options.input_row_chars(self.cursor.bound.width));
+
+        self.print_input()?;
 
-            let last_input_pos = self.cursor.input_last_pos(&self.buffer);
-            self.buffer.goto_end();
-            self.cursor.goto(last_input_pos.0, last_input_pos.1);
+        let last_input_pos = self.cursor.input_last_pos(&self.buffer);
+        self.buffer.goto_end();
+        if self.options.horizontal_scroll {
+            return self.scroll_input();
         }
+        self.cursor.goto(last_input_pos.0, last_input_pos.1);
         Ok(())
     }
 
@@ -165,6 +242,9 @@ impl IRust {
     }
 
     pub fn handle_backspace(&mut self) -> Result<(), IRustError> {
+        if self.delete_selection()? {
+            return Ok(());
+        }
         if !self.buffer.is_at_start() {
             self.buffer.move_backward();
             self.cursor.move_left();
@@ -182,7 +262,10 @@ impl IRust {
     }
 
     pub fn handle_del(&mut self) -> Result<(), IRustError> {
-        if !self.buffer.is_empty() {
+        if self.delete_selection()? {
+            return Ok(());
+        }
+        if !self.buffer.is_at_end() {
             self.buffer.remove_current_char();
             self.history.update_buffer_copy(&self.buffer.to_string());
             self.print_input()?;
@@ -194,6 +277,8 @@ impl IRust {
         if self.buffer.is_empty() {
             self.exit()?;
         } else {
+            self.clear_inline_suggestion()?;
+            self.line_edit.cancel();
             self.write_newline()?;
             self.raw_terminal.clear(ClearType::FromCursorDown)?;
             self.write_from_terminal_start(super::IN, Color::Yellow)?;
@@ -203,15 +288,20 @@ impl IRust {
     }
 
     pub fn handle_ctrl_d(&mut self) -> Result<(), IRustError> {
+        // readline behavior: exit on empty buffer, else delete the character under the cursor
         if self.buffer.is_empty() {
             self.exit()?;
+        } else {
+            self.handle_del()?;
         }
         Ok(())
     }
 
-    fn exit(&mut self) -> Result<(), IRustError> {
+    pub fn exit(&mut self) -> Result<(), IRustError> {
         self.history.save();
-        self.raw_terminal.clear(ClearType::All)?;
+        if !self.options.accessible {
+            self.raw_terminal.clear(ClearType::All)?;
+        }
         super::RawTerminal::exit(0);
         Ok(())
     }
@@ -223,11 +313,22 @@ impl IRust {
                 sys::signal::{kill, Signal},
                 unistd::Pid,
             };
+            // the shell gets the terminal back in its normal mode
             self.raw_terminal.clear(ClearType::All)?;
+            self.raw_terminal.reset_color()?;
+            self.cursor.goto(0, 0);
+            self.cursor.show();
+            self.raw_terminal.leave_raw_mode()?;
+
+            // returns after SIGCONT
             let _ = kill(Pid::this(), Some(Signal::SIGTSTP));
 
-            // display empty prompt after SIGCONT
+            self.raw_terminal.enter_raw_mode()?;
+            // redraw the prompt with the current input, the cursor where it was in the input
+            let buffer_pos = self.buffer.buffer_pos;
             self.clear()?;
+            self.buffer.set_buffer_pos(buffer_pos);
+            self.goto_buffer_pos();
         }
         Ok(())
     }
@@ -238,15 +339,60 @@ impl IRust {
     }
 
     pub fn handle_home_key(&mut self) -> Result<(), IRustError> {
-        self.buffer.goto_start();
-        self.cursor.goto(4, self.cursor.pos.starting_pos.1);
+        let row_start = self.buffer.row_start();
+        self.buffer.set_buffer_pos(row_start);
+        self.goto_buffer_pos();
         Ok(())
     }
 
     pub fn handle_end_key(&mut self) -> Result<(), IRustError> {
-        let last_input_pos = self.cursor.input_last_pos(&self.buffer);
+        let row_end = self.buffer.row_end();
+        self.buffer.set_buffer_pos(row_end);
+        self.goto_buffer_pos();
+        Ok(())
+    }
+
+    /// The keys crossterm doesn't read
+    pub fn handle_key(&mut self, key: Key) -> Result<(), IRustError> {
+        match key {
+            Key::CtrlHome => self.handle_ctrl_home(),
+            Key::CtrlEnd => self.handle_ctrl_end(),
+            Key::AltLeft => self.scroll_output(false),
+            Key::AltRight => self.scroll_output(true),
+        }
+    }
+
+    pub fn handle_ctrl_home(&mut self) -> Result<(), IRustError> {
+        self.buffer.goto_start();
+        self.goto_buffer_pos();
+        Ok(())
+    }
+
+    pub fn handle_ctrl_end(&mut self) -> Result<(), IRustError> {
         self.buffer.goto_end();
-        self.cursor.goto(last_input_pos.0, last_input_pos.1);
+        self.goto_buffer_pos();
+        Ok(())
+    }
+
+    pub fn handle_ctrl_t(&mut self) -> Result<(), IRustError> {
+        if self.buffer.transpose_chars() {
+            self.buffer_edited()?;
+        }
+        Ok(())
+    }
+
+    pub fn handle_word_case(&mut self, case: WordCase) -> Result<(), IRustError> {
+        self.buffer.change_word_case(case);
+        self.buffer_edited()
+    }
+
+    /// Redraw the input after the buffer was changed in place
+    pub fn buffer_edited(&mut self) -> Result<(), IRustError> {
+        self.history.update_buffer_copy(&self.buffer.to_string());
+        self.print_input()?;
+        self.goto_buffer_pos();
+        // Ignore RacerDisabled error
+        let _ = self.unlock_racer_update();
         Ok(())
     }
 
@@ -296,7 +442,7 @@ impl IRust {
             self.cursor.move_right();
             self.buffer.move_forward();
         } else {
-            let _ = self.use_suggestion();
+            let _ = self.use_suggestion_word();
         }
 
         if let Some(current_char) = self.buffer.current_char() {
@@ -332,10 +478,7 @@ impl IRust {
         }
     }
 
-    fn incomplete_input(&self, buffer: &str) -> bool {
-        StringTools::unmatched_brackets(&buffer)
-            || buffer
-                .trim_end()
-                .ends_with(|c| c == ':' || c == '.' || c == '=')
+    pub fn incomplete_input(&self, buffer: &str) -> bool {
+        StringTools::unmatched_brackets(buffer) || buffer.trim_end().ends_with([':', '.', '='])
     }
 }
@@ -0,0 +1,77 @@
+use super::cargo_cmds::cargo_fix;
+use super::diff::unified_diff;
+use crate::irust::format::format_build_error;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+use crossterm::{InputEvent, KeyEvent};
+
+impl IRust {
+    /// F4 adds the `use` lines suggested for the last failed evaluation to the repl code
+    /// and evaluates it again
+    pub fn handle_import_fix(&mut self) -> Result<(), IRustError> {
+        let (uses, input) = match self.import_fix.take() {
+            Some(import_fix) => import_fix,
+            None => return Ok(()),
+        };
+        for use_line in &uses {
+            self.repl.insert(use_line.clone());
+        }
+        self.repl_changed(&uses.join("\n"))?;
+
+        self.replace_input(&input)?;
+        self.handle_enter()
+    }
+
+    /// The answer to "add dependency `foo`? [y/N]": `y` adds it and evaluates the failed
+    /// input again, returns false if the key isn't an answer and must be handled as usual
+    pub fn handle_dependency_answer(&mut self, key: &InputEvent) -> Result<bool, IRustError> {
+        let (krate, input) = match self.dependency_fix.take() {
+            Some(dependency_fix) => dependency_fix,
+            None => return Ok(false),
+        };
+        match key {
+            InputEvent::Keyboard(KeyEvent::Char('y'))
+            | InputEvent::Keyboard(KeyEvent::Char('Y')) => {
+                self.replace_input(&format!(":add {}", krate))?;
+                self.handle_enter()?;
+                self.replace_input(&input)?;
+                self.handle_enter()?;
+                Ok(true)
+            }
+            InputEvent::Keyboard(KeyEvent::Char('n'))
+            | InputEvent::Keyboard(KeyEvent::Char('N'))
+            | InputEvent::Keyboard(KeyEvent::Enter)
+            | InputEvent::Keyboard(KeyEvent::Esc) => Ok(true),
+            _ => Ok(false),
+        }
+    }
+
+    /// Apply the compiler machine-applicable suggestions to the repl code and show what changed
+    pub fn fix(&mut self) -> Result<Printer, IRustError> {
+        let old = self.repl.body.clone();
+        self.repl.write()?;
+
+        let job = cargo_fix()?;
+        let output = self.wait_job(job, "Fixing")?;
+        if !output.status.success() {
+            let error = format_build_error(&String::from_utf8_lossy(&output.stderr));
+            return Err(IRustError::Custom(error));
+        }
+
+        if let Err(e) = self.repl.update_from_main_file() {
+            self.repl.reset();
+            return Err(e);
+        }
+        if self.repl.body == old {
+            let mut outputs = Printer::new(PrinterItem::new(
+                "Nothing to fix".to_string(),
+                PrinterItemType::Warn,
+            ));
+            outputs.add_new_line(1);
+            return Ok(outputs);
+        }
+        self.repl_changed(":fix")?;
+
+        Ok(unified_diff(&old, &self.repl.body, "before :fix", "fixed"))
+    }
+}
@@ -0,0 +1,90 @@
+use super::cargo_cmds::cargo_fmt_checked;
+use super::cmd_args::CmdArgs;
+use super::highlight::highlight;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+use std::io;
+
+impl IRust {
+    /// Format the input with rustfmt in place, exp: to expand a long one-liner before running it
+    pub fn handle_format_input(&mut self) -> Result<(), IRustError> {
+        let input = self.buffer.to_string();
+        if input.trim().is_empty() || input.starts_with(':') {
+            return Ok(());
+        }
+        if let Ok(formatted) = format_input(&input) {
+            if formatted != input {
+                self.replace_input(&formatted)?;
+                self.history.update_buffer_copy(&formatted);
+            }
+        }
+        Ok(())
+    }
+
+    /// `:format <code>` puts the formatted code in the prompt
+    pub fn format(&mut self) -> Result<Printer, IRustError> {
+        let args = CmdArgs::parse_code("format", &self.buffer.to_string());
+        let code = args.code();
+        if code.is_empty() {
+            return Err(IRustError::Custom("No code specified".to_string()));
+        }
+        let formatted = format_input(code)?;
+
+        // the accessible mode can't put the input in the prompt, it's printed to be copied instead
+        if self.options.accessible {
+            let mut outputs = highlight(&formatted);
+            outputs.add_new_line(1);
+            return Ok(outputs);
+        }
+        self.next_input = Some(formatted);
+        Ok(Printer::default())
+    }
+
+    /// With the `autofmt` option, the input is formatted before it's inserted in the repl code,
+    /// a rustfmt failure is reported and the input is inserted as is
+    pub fn autofmt(&self, input: String, outputs: &mut Printer) -> String {
+        if !self.options.autofmt {
+            return input;
+        }
+        match format_in_main(&input) {
+            Ok(lines) if !lines.is_empty() => lines.join("\n"),
+            Ok(_) => input,
+            Err(e) => {
+                outputs.push(PrinterItem::new(
+                    format!("IRust: autofmt: {}", e),
+                    PrinterItemType::Warn,
+                ));
+                outputs.add_new_line(1);
+                input
+            }
+        }
+    }
+}
+
+/// Format statements, items or an expression
+fn format_input(input: &str) -> io::Result<String> {
+    Ok(format_in_main(input)?
+        .iter()
+        .map(|line| line.strip_prefix("    ").unwrap_or(line))
+        .collect::<Vec<&str>>()
+        .join("\n"))
+}
+
+/// The input formatted as the body of `main`, so with one level of indentation
+fn format_in_main(input: &str) -> io::Result<Vec<String>> {
+    let wrapped = format!("fn main() {{\n{}\n}}\n", input);
+    let formatted = cargo_fmt_checked(&wrapped)?;
+
+    // without the `fn main() {` and `}` lines
+    let mut lines: Vec<String> = formatted
+        .trim_end()
+        .lines()
+        .map(ToOwned::to_owned)
+        .collect();
+    if lines.len() < 2 {
+        return Ok(Vec::new());
+    }
+    lines.pop();
+    lines.remove(0);
+    Ok(lines)
+}
@@ -1,7 +1,12 @@
 use crate::irust::{
-    printer::{Printer, PrinterItem, PrinterItemType},
+    highlight::highlight,
+    printer::{strip_escapes, Printer, PrinterItem, PrinterItemType},
+    repl::LAST_OUT_BINDING,
     OUT,
 };
+use std::time::Duration;
+
+const ERROR_INDEX: &str = "https://doc.rust-lang.org/error_codes";
 
 pub fn format_eval_output(output: &str) -> Printer {
     let mut eval_output = Printer::default();
@@ -9,7 +14,7 @@ pub fn format_eval_output(output: &str) -> Printer {
         // Consider this an error
 
         let lines_count = output.lines().count();
-        let actual_error: String = if main_panic(&output) {
+        let actual_error: String = if main_panic(output) {
             // example:
             // thread 'main' panicked at 'attempt to multiply with overflow',
             let mut output: Vec<&str> = output.lines().nth(3).unwrap().split(',').collect();
@@ -24,7 +29,18 @@ pub fn format_eval_output(output: &str) -> Printer {
                 .collect::<Vec<&str>>()
                 .join("\n")
         };
-        eval_output.push(PrinterItem::new(actual_error, PrinterItemType::Err));
+        let error_code = error_code(&actual_error);
+        eval_output.push(PrinterItem::new(
+            link_error_codes(&actual_error),
+            PrinterItemType::Err,
+        ));
+        if let Some(code) = error_code {
+            eval_output.add_new_line(1);
+            eval_output.push(PrinterItem::new(
+                format!("IRust: use `:explain {}` for more details", code),
+                PrinterItemType::Warn,
+            ));
+        }
     } else {
         eval_output.push(PrinterItem::new(OUT.into(), PrinterItemType::Out));
 
@@ -42,6 +58,421 @@ pub fn format_eval_output(output: &str) -> Printer {
     eval_output
 }
 
+/// Where the evaluated input is in the generated main.rs, errors are reported
+/// with the input and repl line numbers (the ones of `:show` and `:del`) instead of main.rs ones
+pub struct InputLines {
+    // index of the first input line in main.rs
+    pub start: usize,
+    pub len: usize,
+    // lines inserted in the repl code for the evaluation, the input included
+    pub inserted: usize,
+}
+
+impl InputLines {
+    // main.rs line number (from 1) => (input or repl, line number)
+    fn map(&self, line: usize) -> (&'static str, usize) {
+        let idx = line.saturating_sub(1);
+        if idx < self.start {
+            ("repl", idx)
+        } else if idx < self.start + self.len {
+            ("input", idx - self.start + 1)
+        } else {
+            ("repl", idx.saturating_sub(self.inserted))
+        }
+    }
+
+    /// Replace the `src/main.rs:line` locations and the line numbers in the margin
+    /// of the source snippets of a cargo error
+    pub fn map_error(&self, error: &str) -> String {
+        let lines = error
+            .lines()
+            .map(|line| {
+                // the last output binding on the `fn main() {` line is not the user code
+                let line = match line.find(LAST_OUT_BINDING) {
+                    Some(idx) => line[..idx].trim_end(),
+                    None => line,
+                };
+                if let Some(idx) = line.find("src/main.rs:") {
+                    let rest = &line[idx + "src/main.rs:".len()..];
+                    let digits = rest.chars().take_while(char::is_ascii_digit).count();
+                    if let Ok(number) = rest[..digits].parse() {
+                        let (label, number) = self.map(number);
+                        return format!("{}{}:{}{}", &line[..idx], label, number, &rest[digits..]);
+                    }
+                }
+                // exp: 5 |     let x: u8 = "a";
+                let visible = strip_escapes(line);
+                let margin = visible.trim_start();
+                let digits = margin.chars().take_while(char::is_ascii_digit).count();
+                if digits > 0 && margin[digits..].starts_with(" |") {
+                    if let Ok(number) = margin[..digits].parse() {
+                        let (_, number) = self.map(number);
+                        return replace_first_number(line, number);
+                    }
+                }
+                line.to_owned()
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        if error.ends_with('\n') {
+            lines + "\n"
+        } else {
+            lines
+        }
+    }
+}
+
+// the first number outside of the escape sequences, right aligned to keep the margin width
+fn replace_first_number(line: &str, number: usize) -> String {
+    let mut chars = line.char_indices();
+    while let Some((idx, c)) = chars.next() {
+        if c == '\x1b' {
+            // exp: \x1b[38;5;12m
+            for (_, c) in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else if c.is_ascii_digit() {
+            let end = line[idx..]
+                .find(|c: char| !c.is_ascii_digit())
+                .map_or(line.len(), |len| idx + len);
+            return format!(
+                "{}{:>width$}{}",
+                &line[..idx],
+                number,
+                &line[end..],
+                width = end - idx
+            );
+        }
+    }
+    line.to_owned()
+}
+
+/// The numbers of a printed sequence of at least two numbers, exp: `[1, 2.5, -3]`
+pub fn parse_numbers(output: &str) -> Option<Vec<f64>> {
+    let numbers = output
+        .trim()
+        .strip_prefix('[')?
+        .strip_suffix(']')?
+        .split(',')
+        .map(|number| number.trim().parse().ok())
+        .collect::<Option<Vec<f64>>>()?;
+    if numbers.len() < 2 {
+        return None;
+    }
+    Some(numbers)
+}
+
 fn main_panic(s: &str) -> bool {
     s.contains("thread 'main' panicked")
 }
+
+// exp: error[E0308]: mismatched types
+fn error_code(error: &str) -> Option<&str> {
+    let start = error.find("error[E")? + "error[".len();
+    let end = start + error[start..].find(']')?;
+    Some(&error[start..end])
+}
+
+/// Make the error codes links to the error index (OSC 8) if the terminal supports it
+fn link_error_codes(error: &str) -> String {
+    if !hyperlinks_supported() {
+        return error.to_owned();
+    }
+
+    let mut linked = String::new();
+    let mut rest = error;
+    while let Some(code) = error_code(rest) {
+        let start = rest.find(code).unwrap();
+        linked.push_str(&rest[..start]);
+        linked.push_str(&format!(
+            "\x1b]8;;{}/{}.html\x1b\\{}\x1b]8;;\x1b\\",
+            ERROR_INDEX, code, code
+        ));
+        rest = &rest[start + code.len()..];
+    }
+    linked.push_str(rest);
+    linked
+}
+
+// there is no way to query the terminal, so only known terminals get links
+fn hyperlinks_supported() -> bool {
+    let var = |name| std::env::var(name).unwrap_or_default();
+
+    std::env::var("VTE_VERSION")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .is_some_and(|v| v >= 5000)
+        || ["iTerm.app", "WezTerm", "vscode"].contains(&var("TERM_PROGRAM").as_str())
+        || ["xterm-kitty", "foot", "alacritty"].contains(&var("TERM").as_str())
+        || std::env::var("WT_SESSION").is_ok()
+        || std::env::var("KONSOLE_VERSION").is_ok()
+}
+
+/// `rustc --explain` output is markdown, code blocks are highlighted
+pub fn format_explanation(explanation: &str) -> Printer {
+    let mut printer = Printer::default();
+    let mut code = String::new();
+    let mut in_code = false;
+
+    for line in explanation.lines() {
+        if line.trim_start().starts_with("```") {
+            if in_code {
+                printer.append(&mut highlight(&code));
+                code.clear();
+            }
+            in_code = !in_code;
+        } else if in_code {
+            code.push_str(line);
+            code.push('\n');
+        } else {
+            printer.push(PrinterItem::new(line.to_owned(), PrinterItemType::Eval));
+            printer.add_new_line(1);
+        }
+    }
+
+    printer
+}
+
+/// Keep only what matters from a failed `cargo add` / `cargo build`,
+/// build scripts failures (native dependencies) are summarized with a hint
+pub fn format_build_error(error: &str) -> String {
+    const BUILD_SCRIPT_ERR: &str = "failed to run custom build command for";
+    const MAX_LINES: usize = 15;
+
+    if let Some(idx) = error.find(BUILD_SCRIPT_ERR) {
+        let header = error[idx..].lines().next().unwrap_or_default();
+        // the build script own stderr is what usually explains the failure
+        let details = match error.find("--- stderr") {
+            Some(idx) => &error[idx + "--- stderr".len()..],
+            None => &error[idx..],
+        };
+        let details: Vec<&str> = details
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .take(MAX_LINES)
+            .collect();
+
+        format!(
+            "error: {}\n{}\nIRust: if the crate needs some environment variables, use `:add <dep> --env KEY=VALUE`",
+            header,
+            details.join("\n")
+        )
+    } else if let Some(conflict) = format_version_conflict(error) {
+        conflict
+    } else {
+        error
+            .lines()
+            .filter(|l| {
+                !l.trim_start().starts_with("Compiling") && !l.trim_start().starts_with("Updating")
+            })
+            .collect::<Vec<&str>>()
+            .join("\n")
+    }
+}
+
+/// Summarize cargo resolver failures (conflicting requirements) and suggest a version to pin
+fn format_version_conflict<'a>(error: &'a str) -> Option<String> {
+    const SELECT_ERR: &str = "failed to select a version for";
+
+    // the text between the first pair of backticks after `pattern` in `text`
+    let quoted = |text: &'a str, pattern: &str| -> Option<&'a str> {
+        let rest = &text[text.find(pattern)? + pattern.len()..];
+        let start = rest.find('`')? + 1;
+        let end = start + rest[start..].find('`')?;
+        Some(&rest[start..end])
+    };
+    // `a, b, c` after `pattern` till the end of the line
+    let versions = |pattern: &str| -> Option<Vec<&str>> {
+        let rest = &error[error.find(pattern)? + pattern.len()..];
+        let line = rest.lines().next()?.rsplit(": ").next()?;
+        Some(
+            line.split(',')
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .collect(),
+        )
+    };
+
+    // exp: failed to select a version for `rand`.
+    // exp: failed to select a version for the requirement `rand = "^9"`
+    let failed = quoted(error, SELECT_ERR)?;
+    let krate = failed.split_whitespace().next()?.to_owned();
+
+    let mut explanation = vec![format!("error: conflicting requirements for `{}`", krate)];
+
+    if failed.contains('=') {
+        explanation.push(format!("  no version matches `{}`", failed));
+    }
+    if let Some(package) = quoted(error, "required by package") {
+        let requirement = quoted(error, "versions that meet the requirements").unwrap_or("?");
+        explanation.push(format!(
+            "  `{}` requires `{} {}`",
+            package, krate, requirement
+        ));
+    }
+    if let Some(idx) = error.find("previously selected package") {
+        let previous = &error[idx..];
+        let selected = quoted(previous, "previously selected package").unwrap_or("?");
+        explanation.push(format!("  but `{}` was already selected", selected));
+        if let Some(dependency) = quoted(previous, "which satisfies dependency") {
+            explanation.push(format!("    (to satisfy `{}`)", dependency));
+        }
+    }
+    if let Some(native) = quoted(error, "links to the native library") {
+        explanation.push(format!(
+            "  only one version of a crate linking the native library `{}` can be used",
+            native
+        ));
+    }
+
+    // versions that would work for the failing requirement
+    let candidates = versions("versions that meet the requirements")
+        .or_else(|| versions("candidate versions found which didn't match:"))
+        .unwrap_or_default();
+    match candidates.first() {
+        Some(candidate) => {
+            explanation.push(format!("  available versions: {}", candidates.join(", ")));
+            explanation.push(format!(
+                "IRust: try pinning a compatible version, example `:add {}@{}`",
+                krate, candidate
+            ));
+        }
+        None => explanation.push(format!(
+            "IRust: try `:add {}@<version>` with a version compatible with the other dependencies",
+            krate
+        )),
+    }
+
+    Some(explanation.join("\n"))
+}
+
+/// `compile 1.80s · run 12ms`, the compile time is the one cargo reports
+/// and the rest of the job `total` time is counted as run time
+/// The `use` lines rustc suggests for the unresolved names, exp:
+/// ```text
+/// help: consider importing this struct
+///   |
+/// 1 + use std::collections::HashMap;
+/// ```
+/// the names with several candidates are left to the user
+pub fn import_suggestions(stderr: &str) -> Vec<String> {
+    let stderr = strip_escapes(stderr);
+    let mut uses: Vec<String> = vec![];
+    let mut lines = stderr.lines();
+    while let Some(line) = lines.next() {
+        if !line.trim_start().starts_with("help: consider importing") {
+            continue;
+        }
+        let mut candidates = vec![];
+        for line in lines.by_ref() {
+            let line = line.trim();
+            let code = line
+                .trim_start_matches(|c: char| c.is_ascii_digit())
+                .trim_start();
+            let code = code
+                .strip_prefix('+')
+                .or_else(|| code.strip_prefix('|'))
+                .unwrap_or(code)
+                .trim();
+            if code.starts_with("use ") {
+                candidates.push(code.to_owned());
+            } else if !line.starts_with('|') {
+                // the end of the snippet
+                break;
+            }
+        }
+        if let [candidate] = candidates.as_slice() {
+            if !uses.contains(candidate) {
+                uses.push(candidate.clone());
+            }
+        }
+    }
+    uses
+}
+
+/// The crate a compilation error says is missing, exp: ``use of undeclared crate or module `rand` ``
+pub fn missing_crate(stderr: &str) -> Option<String> {
+    const MISSING: [&str; 4] = [
+        "use of undeclared crate or module `",
+        "use of unresolved module or unlinked crate `",
+        "can't find crate for `",
+        "you might be missing crate `",
+    ];
+    let stderr = strip_escapes(stderr);
+    MISSING
+        .iter()
+        .filter_map(|missing| {
+            let start = stderr.find(missing)? + missing.len();
+            let name = &stderr[start..start + stderr[start..].find('`')?];
+            Some(name.to_owned())
+        })
+        .find(|name| {
+            !["std", "core", "alloc", "crate", "self", "super"].contains(&name.as_str())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+}
+
+/// How the evaluated binary ended when it failed, from the line cargo reports it with:
+/// ``error: process didn't exit successfully: `target/debug/irust` (signal: 6, SIGABRT: process abort signal)``
+pub fn exit_status(stderr: &str) -> Option<String> {
+    let line = stderr
+        .lines()
+        .find(|line| line.contains("process didn't exit successfully"))?;
+    let status = line.trim_end().strip_suffix(')')?;
+    let status = &status[status.rfind(" (")? + 2..];
+
+    if let Some(code) = status
+        .strip_prefix("exit status: ")
+        .or_else(|| status.strip_prefix("exit code: "))
+    {
+        return Some(format!("process exited with code {}", code));
+    }
+    // exp: signal: 6, SIGABRT: process abort signal
+    let signal = status.strip_prefix("signal: ")?;
+    match signal.split_once(", ") {
+        Some((number, name)) => Some(format!(
+            "process terminated by signal {} ({})",
+            number,
+            name.split(':').next().unwrap_or(name)
+        )),
+        None => Some(format!("process terminated by signal {}", signal)),
+    }
+}
+
+pub fn format_timings(stderr: &[u8], total: Duration) -> Option<String> {
+    let compile = build_time(&String::from_utf8_lossy(stderr))?;
+    let run = total.checked_sub(compile).unwrap_or_default();
+    Some(format!(
+        "compile {} · run {}",
+        format_duration(compile),
+        format_duration(run)
+    ))
+}
+
+// exp: Finished `dev` profile [unoptimized + debuginfo] target(s) in 1.80s
+// or in 1m 05s for long builds
+fn build_time(stderr: &str) -> Option<Duration> {
+    let line = stderr.lines().find(|line| line.contains("Finished"))?;
+    let time = line.rsplit_once(" in ")?.1.trim();
+    let (minutes, seconds) = match time.split_once("m ") {
+        Some((minutes, seconds)) => (minutes.parse::<u64>().ok()?, seconds),
+        None => (0, time),
+    };
+    let seconds = seconds
+        .strip_suffix('s')?
+        .parse::<f64>()
+        .ok()
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)?;
+    Some(Duration::from_secs(minutes * 60) + Duration::from_secs_f64(seconds))
+}
+
+fn format_duration(duration: Duration) -> String {
+    if duration < Duration::from_secs(1) {
+        format!("{}ms", duration.as_millis())
+    } else {
+        format!("{:.1}s", duration.as_secs_f64())
+    }
+}
@@ -0,0 +1,73 @@
+use super::cmd_args::CmdArgs;
+use super::help::ColoredPrinterItem;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+use crossterm::Color;
+use regex::Regex;
+
+// lines of the session kept for `:grep`, the oldest ones are dropped
+const TRANSCRIPT_MAX_LINES: usize = 10_000;
+
+impl IRust {
+    /// Search the repl code and the inputs and outputs of the session, exp: `:grep fn \w+_parser`
+    pub fn grep(&mut self) -> Result<Printer, IRustError> {
+        let args = CmdArgs::parse_code("grep", &self.buffer.to_string());
+        let pattern = args.code();
+        if pattern.is_empty() {
+            return Err(IRustError::Custom("No pattern specified".to_string()));
+        }
+        let regex = Regex::new(pattern)
+            .map_err(|e| IRustError::Custom(format!("Invalid pattern: {}", e)))?;
+
+        // the repl code without `fn main() {` and its closing brace, numbered like `:del` expects
+        let body = &self.repl.body[1..self.repl.body.len() - 1];
+        let mut outputs = Printer::default();
+        let mut matches = grep_lines(&regex, body, "repl", &mut outputs);
+        matches += grep_lines(&regex, &self.transcript, "session", &mut outputs);
+
+        if matches == 0 {
+            return Ok(Printer::new(PrinterItem::new(
+                format!("No match for `{}`", pattern),
+                PrinterItemType::Warn,
+            )));
+        }
+        Ok(outputs)
+    }
+
+    /// Keep an input and its output for `:grep`
+    pub fn record_transcript(&mut self, input: &str, output: &Printer) {
+        for (idx, line) in input.lines().enumerate() {
+            let prompt = if idx == 0 { super::IN } else { "..: " };
+            self.transcript.push(format!("{}{}", prompt, line));
+        }
+        self.transcript
+            .extend(output.to_plain_text().lines().map(ToOwned::to_owned));
+
+        let overflow = self.transcript.len().saturating_sub(TRANSCRIPT_MAX_LINES);
+        self.transcript.drain(..overflow);
+    }
+}
+
+/// Push the matching lines, `name:index` first and the matches highlighted, returns their number
+fn grep_lines(regex: &Regex, lines: &[String], name: &str, outputs: &mut Printer) -> usize {
+    let width = lines.len().to_string().len();
+    let mut count = 0;
+    for (idx, line) in lines.iter().enumerate() {
+        if !regex.is_match(line) {
+            continue;
+        }
+        count += 1;
+        outputs.push(
+            format!("{}:{:<width$}  ", name, idx + 1, width = width).to_output(Color::DarkYellow),
+        );
+        let mut last_end = 0;
+        for found in regex.find_iter(line) {
+            outputs.push((&line[last_end..found.start()]).to_output(Color::White));
+            outputs.push(found.as_str().to_output(Color::Red));
+            last_end = found.end();
+        }
+        outputs.push((&line[last_end..]).to_output(Color::White));
+        outputs.add_new_line(1);
+    }
+    count
+}
@@ -1,3 +1,4 @@
+use super::cmd_args::CmdArgs;
 use super::printer::{Printer, PrinterItem, PrinterItemType};
 use crate::irust::{IRust, IRustError};
 use crossterm::Color;
@@ -12,62 +13,637 @@ impl<T: ToString> ColoredPrinterItem for T {
     }
 }
 
-impl IRust {
-    pub fn help(&mut self) -> Result<Printer, IRustError> {
-        let mut outputs = Printer::default();
-
-        outputs.push("### Keywords / Tips & Tricks ###".to_output(Color::DarkYellow));
-        outputs.push(
-            "
-:help => print help
-
-:reset => reset repl
-
-:show => show repl current code (optionally depends on rustfmt to format output)
+struct CommandHelp {
+    name: &'static str,
+    usage: &'static str,
+    summary: &'static str,
+    // shown by `:help <name>`, one line per usage note or example
+    details: &'static [&'static str],
+}
 
-:add <dep_list> => add dependencies (requires cargo-edit)
+const COMMANDS: [CommandHelp; 50] = [
+    CommandHelp {
+        name: "help",
+        usage: ":help [command|keys]",
+        summary: "print help",
+        details: &[
+            ":help lists the commands, :help <command> shows the details of one of them",
+            ":help keys lists the active keybindings",
+            "example: :help add",
+        ],
+    },
+    CommandHelp {
+        name: "tutorial",
+        usage: ":tutorial [skip|stop]",
+        summary: "start a guided tour of the repl",
+        details: &[
+            "each step checks the input before showing the next one",
+            "use :tutorial skip to skip a step and :tutorial stop to leave the tour",
+            "the tour can also be started with irust --tour",
+        ],
+    },
+    CommandHelp {
+        name: "reset",
+        usage: ":reset",
+        summary: "reset repl",
+        details: &["the repl code is emptied, use :undo to get it back"],
+    },
+    CommandHelp {
+        name: "show",
+        usage: ":show",
+        summary: "show repl current code",
+        details: &["the code is formatted with rustfmt if it's installed"],
+    },
+    CommandHelp {
+        name: "add",
+        usage: ":add <dep_list>",
+        summary: "add dependencies (requires cargo-edit)",
+        details: &[
+            "use --env KEY=VALUE to set environment variables needed by build scripts",
+            "use #<number> to add a result of the last :search",
+            "use <dep>@<version> to pin a version",
+            "the resolved versions are shown after the build, with a warning for prereleases and yanked versions",
+            "example: :add rand regex",
+            "example: :add rand@0.7.3",
+            "example: :add openssl --env OPENSSL_DIR=/usr/local/ssl",
+            "example: :add #1",
+        ],
+    },
+    CommandHelp {
+        name: "update",
+        usage: ":update [dep]",
+        summary: "update the dependencies to their latest compatible versions",
+        details: &[
+            "only the given dependency is updated if there is one, what changed is shown and the repl is rebuilt",
+            "example: :update rand",
+        ],
+    },
+    CommandHelp {
+        name: "type",
+        usage: ":type <expression>",
+        summary: "show the type of an expression without evaluating it",
+        details: &["example: :type v.iter().map(f)"],
+    },
+    CommandHelp {
+        name: "sizeof",
+        usage: ":sizeof [--layout] <type|expression>",
+        summary: "show the size and alignment of a type or of an expression type",
+        details: &[
+            "expressions are not evaluated",
+            "use --layout to show the fields layout (requires a nightly toolchain)",
+            "example: :sizeof Option<u32>",
+            "example: :sizeof --layout v",
+        ],
+    },
+    CommandHelp {
+        name: "impls",
+        usage: ":impls <type|trait>",
+        summary: "list the traits a type implements or the types implementing a trait",
+        details: &[
+            "a type is checked against a fixed list of common std traits and the traits defined in the repl",
+            "a trait is checked against the types defined in the repl and a fixed list of common std types",
+            "the traits and types of the :add-ed crates are not checked",
+            "example: :impls Vec<u8>",
+            "example: :impls Display",
+        ],
+    },
+    CommandHelp {
+        name: "source",
+        usage: ":source <path::to::item>",
+        summary: "show the source of a std or dependency item (requires racer)",
+        details: &[
+            "long items are shown in $PAGER",
+            "example: :source std::mem::swap",
+        ],
+    },
+    CommandHelp {
+        name: "docs",
+        usage: ":docs <crate::item> [--offline-docs]",
+        summary: "open the docs of an item in the browser",
+        details: &[
+            "dependencies docs are opened on docs.rs, at the version used by the repl",
+            "use --offline-docs to build the docs with cargo doc and open the local page instead",
+            "example: :docs rand::Rng",
+        ],
+    },
+    CommandHelp {
+        name: "peek",
+        usage: ":peek <expression>",
+        summary: "print the expression value without adding anything to the repl",
+        details: &["example: :peek x"],
+    },
+    CommandHelp {
+        name: "plot",
+        usage: ":plot [--bars] [expression]",
+        summary: "chart a sequence of numbers in the terminal",
+        details: &[
+            "without an expression the last output is plotted",
+            "the chart is a sparkline, use --bars for a bar chart with one row per value",
+            "example: :plot (0..40).map(|x| (x as f64 / 4.0).sin()).collect::<Vec<_>>()",
+            "example: :plot --bars",
+        ],
+    },
+    CommandHelp {
+        name: "profile",
+        usage: ":profile [--open] <expression>",
+        summary: "profile an expression in release mode and write a flamegraph (requires cargo-flamegraph)",
+        details: &[
+            "the expression runs under perf (linux) or dtrace (macos), the svg path is printed",
+            "use --open to open the flamegraph in the browser",
+            "example: :profile --open (0..10_000_000).map(|x| x % 7).sum::<u64>()",
+        ],
+    },
+    CommandHelp {
+        name: "bench",
+        usage: ":bench [-n <iterations>] <expression>",
+        summary: "time an expression in release mode",
+        details: &[
+            "the expression runs a few times to warm up, then it's timed 100 times (or -n times)",
+            "the min, mean, max and standard deviation of the durations are shown",
+            "the expression runs several times, clone the values it consumes",
+            "example: :bench -n 1000 v.iter().sum::<u64>()",
+        ],
+    },
+    CommandHelp {
+        name: "miri",
+        usage: ":miri [on|off|expression]",
+        summary: "check an expression for undefined behavior with miri (requires nightly miri)",
+        details: &[
+            "with on every evaluation runs under miri until :miri off, without argument the state is shown",
+            "install miri with rustup +nightly component add miri",
+            "example: :miri unsafe { *(&0u32 as *const u32 as *const u64) }",
+            "example: :miri on",
+        ],
+    },
+    CommandHelp {
+        name: "explain",
+        usage: ":explain <error_code>",
+        summary: "show the explanation of a rustc error code",
+        details: &[
+            "long explanations are shown in $PAGER",
+            "example: :explain E0308",
+        ],
+    },
+    CommandHelp {
+        name: "search",
+        usage: ":search <query>",
+        summary: "search crates.io (requires curl)",
+        details: &[
+            "use :add #<number> to add one of the results",
+            "example: :search http client",
+        ],
+    },
+    CommandHelp {
+        name: "watch",
+        usage: ":watch <expression>",
+        summary: "re-evaluate and print the expression after every successful input",
+        details: &[
+            "the characters that changed since the previous value are highlighted",
+            "example: :watch v.len()",
+        ],
+    },
+    CommandHelp {
+        name: "unwatch",
+        usage: ":unwatch",
+        summary: "remove all the watched expressions",
+        details: &[],
+    },
+    CommandHelp {
+        name: "out",
+        usage: ":out [>>] <file> <expression>",
+        summary: "write the expression output to a file instead of the terminal",
+        details: &[
+            ">> appends to the file",
+            "example: :out data.txt v",
+            "example: :out >> data.txt v",
+        ],
+    },
+    CommandHelp {
+        name: "more",
+        usage: ":more",
+        summary: "show the rest of the last output in $PAGER",
+        details: &["outputs longer than the output_max_lines or output_max_bytes options are cut"],
+    },
+    CommandHelp {
+        name: "fix",
+        usage: ":fix",
+        summary: "apply the compiler suggestions to the repl code",
+        details: &[
+            "the machine-applicable suggestions of cargo fix are applied and the diff is shown",
+            "use :undo to go back to the code before the fix",
+        ],
+    },
+    CommandHelp {
+        name: "clean",
+        usage: ":clean [#<number>..|all]",
+        summary: "find and remove dead code in the repl code",
+        details: &[
+            "lists the duplicate and unused use statements, the shadowed let bindings never read",
+            "and the functions never used in the repl code, then :clean #<number> removes them",
+            "example: :clean #1 #3",
+            "example: :clean all",
+        ],
+    },
+    CommandHelp {
+        name: "checkpoint",
+        usage: ":checkpoint <name>",
+        summary: "save the current repl code under a name",
+        details: &["use :diff <name> to see what changed since"],
+    },
+    CommandHelp {
+        name: "log",
+        usage: ":log",
+        summary: "show the repl code history",
+        details: &["requires the git_versioning option"],
+    },
+    CommandHelp {
+        name: "revert",
+        usage: ":revert <hash>",
+        summary: "restore the repl code of a commit listed by :log",
+        details: &["example: :revert 3f2a1c0"],
+    },
+    CommandHelp {
+        name: "diff",
+        usage: ":diff [name]",
+        summary: "show what changed in the repl code since a checkpoint",
+        details: &["without a name the diff is since the session start"],
+    },
+    CommandHelp {
+        name: "target",
+        usage: ":target [<triple> [--runner <cmd>]|host]",
+        summary: "compile the inputs for another target",
+        details: &[
+            "without argument the current target is shown, host goes back to native evaluation",
+            "wasm32-wasi inputs are run with wasmtime or wasmer",
+            "other targets are only built, unless a runner is given with --runner",
+            "the target must be installed with rustup target add <triple>",
+            "example: :target wasm32-wasi",
+            "example: :target x86_64-unknown-linux-musl",
+            "example: :target aarch64-unknown-linux-gnu --runner qemu-aarch64 -L /usr/aarch64-linux-gnu",
+        ],
+    },
+    CommandHelp {
+        name: "cells",
+        usage: ":cells",
+        summary: "list the cells with their outputs (requires the cells option)",
+        details: &[
+            "with the cells option every input is a numbered cell, its number is printed before its output",
+            "the repl code is rebuilt from the cells when one of them is run again, edited or deleted",
+        ],
+    },
+    CommandHelp {
+        name: "rerun",
+        usage: ":rerun <cell> [--all]",
+        summary: "run a cell again",
+        details: &[
+            "the later cells defining items or statements are run again too, with --all every later cell is",
+            "example: :rerun 3 --all",
+        ],
+    },
+    CommandHelp {
+        name: "edit-cell",
+        usage: ":edit-cell <cell>",
+        summary: "edit a cell in the prompt",
+        details: &["the edited input replaces the cell when Enter is pressed, then it's run again like with :rerun"],
+    },
+    CommandHelp {
+        name: "del-cell",
+        usage: ":del-cell <cell>",
+        summary: "delete a cell",
+        details: &["the later cells defining items or statements are run again without it"],
+    },
+    CommandHelp {
+        name: "colors",
+        usage: ":colors",
+        summary: "preview the color options with their current value",
+        details: &["the colors are set in the config file with a name (DarkBlue) or a hex value (#1e90ff)"],
+    },
+    CommandHelp {
+        name: "grep",
+        usage: ":grep <regex>",
+        summary: "search the repl code and the inputs and outputs of the session",
+        details: &[
+            "the matching lines are listed with their repl line number (the one :del expects)",
+            "or their session line number",
+            "example: :grep fn \\w+_parser",
+        ],
+    },
+    CommandHelp {
+        name: "scrollback",
+        usage: ":scrollback [regex]",
+        summary: "browse the inputs and outputs of the session",
+        details: &[
+            "scroll with j/k, Space/b or the arrows, /pattern searches, n and N go to the next and previous match",
+            "the matches are highlighted, q quits",
+            "example: :scrollback error\\[E\\d+\\]",
+        ],
+    },
+    CommandHelp {
+        name: "load",
+        usage: ":load <file|dir>...",
+        summary: "load rust scripts into the repl",
+        details: &[
+            "`mod name;` declarations are replaced by inline modules with the modules files",
+            "a directory is loaded from its main.rs or lib.rs (src/ too), else from all its .rs files",
+            "example: :load script.rs",
+            "paths can start with ~ and use the * and ? wildcards",
+            "example: :load main.rs helpers.rs",
+            "example: :load src/*.rs",
+            "example: :load my_project/",
+        ],
+    },
+    CommandHelp {
+        name: "pop",
+        usage: ":pop",
+        summary: "remove last repl code line",
+        details: &[],
+    },
+    CommandHelp {
+        name: "undo",
+        usage: ":undo",
+        summary: "undo the last change of the repl code",
+        details: &["inputs, :pop, :del, :load, :edit, :reset.. can be undone, use :redo to redo them"],
+    },
+    CommandHelp {
+        name: "redo",
+        usage: ":redo",
+        summary: "redo the last undone change",
+        details: &[],
+    },
+    CommandHelp {
+        name: "del",
+        usage: ":del <line_num>",
+        summary: "remove a specific line from repl code",
+        details: &[
+            "line count starts at 1 from the first expression statement",
+            "example: :del 2",
+        ],
+    },
+    CommandHelp {
+        name: "args",
+        usage: ":args [args | clear]",
+        summary: "set the arguments the evaluations are run with",
+        details: &[
+            "the arguments are returned by std::env::args() in the following evaluations",
+            "without arguments the current ones are shown, clear removes them",
+            "example: :args --input data.csv -v",
+        ],
+    },
+    CommandHelp {
+        name: "stdin",
+        usage: ":stdin [<file> | <<EOF | clear]",
+        summary: "set the data piped to the evaluations stdin",
+        details: &[
+            "with <<EOF the following input lines until EOF are the data (use alt-enter for new lines)",
+            "without arguments the current data is shown, clear removes it",
+            "example: :stdin data.txt",
+            "example: :stdin <<EOF",
+        ],
+    },
+    CommandHelp {
+        name: "cargo",
+        usage: ":cargo [<toml> | reset]",
+        summary: "add sections to the repl Cargo.toml for the session",
+        details: &[
+            "without arguments the current additions are shown, reset removes them",
+            "example: :cargo [profile.dev] opt-level = 1",
+            "example: :cargo [patch.crates-io] serde = { path = \"../serde\" }",
+        ],
+    },
+    CommandHelp {
+        name: "sh",
+        usage: ":sh | :sh exit",
+        summary: "run the :: commands in a shell that keeps its state",
+        details: &[
+            "cd and export in a :: command apply to the next ones",
+            "the commands can't read from the terminal",
+            "example: :sh then ::cd src then ::ls",
+        ],
+    },
+    CommandHelp {
+        name: "results",
+        usage: ":results | :result <n>",
+        summary: "show the values of the last evaluations again",
+        details: &[
+            "the last 100 values are kept, the expression is not evaluated again",
+            "example: :result 4",
+        ],
+    },
+    CommandHelp {
+        name: "set",
+        usage: ":set [option on|off]",
+        summary: "switch an option for the current session",
+        details: &[
+            "options: autofmt, completion_session, completion_history, completion_snippets, input_status, persist_let_bindings, rerun_on_change, show_timings, show_types",
+            "allocator system|jemalloc|mimalloc sets the global allocator of the evaluations",
+            "panic abort|unwind sets the panic strategy of the evaluations",
+            "log <level|filter|off> sets RUST_LOG for the evaluations, with an env_logger set up for them",
+            "without arguments the current values are listed",
+            "example: :set autofmt on",
+            "example: :set allocator mimalloc",
+            "example: :set log debug",
+        ],
+    },
+    CommandHelp {
+        name: "format",
+        usage: ":format <code>",
+        summary: "put the code formatted with rustfmt in the prompt",
+        details: &[
+            "F3 formats the input in place instead",
+            "example: :format let v: Vec<_> = (0..10).filter(|x| x % 2 == 0).map(|x| x * x).collect();",
+        ],
+    },
+    CommandHelp {
+        name: "edit-line",
+        usage: ":edit-line <line_num>",
+        summary: "edit a line of the repl code in the prompt",
+        details: &[
+            "the next input replaces the line if the repl code still compiles, an empty input or Ctrl-c cancels",
+            "line count starts at 1 from the first expression statement, like :del",
+            "example: :edit-line 2",
+        ],
+    },
+    CommandHelp {
+        name: "edit",
+        usage: ":edit <editor> [args]",
+        summary: "edit internal buffer using an external editor",
+        details: &[
+            "everything should be defined in the main function (structs, enum, etc..)",
+            "the arguments are given to the editor before the file",
+            "example: :edit micro",
+            "example: :edit code --wait",
+        ],
+    },
+];
 
-:type <expression> => shows the expression type, example :type vec!(5)
+const TIPS: [&str; 6] = [
+    ":: => run a shell command, example ::ls, use ::! for the ones that need the terminal, example ::!htop",
+    "<expression> | ::<command> => pipe the expression output through shell commands, example v | ::grep 3 | ::wc -l",
+    "The output of the last successful evaluation is available as a &str in the next ones as __irust_last_out",
+    "You can use arrow keys to cycle through commands history",
+    "Use :help <command> for the details of a command and :help keys for the keybindings",
+    "Command arguments with spaces can be quoted, exp: :load \"my scripts/a.rs\"",
+];
 
-:load => load a rust script into the repl
+/// The names of the commands, for their completion
+pub fn command_names() -> impl Iterator<Item = &'static str> {
+    COMMANDS.iter().map(|cmd| cmd.name)
+}
 
-:pop => remove last repl code line
+/// The usage of the `name` command, for the incorrect arguments errors
+pub fn usage_error(name: &str) -> String {
+    match COMMANDS.iter().find(|cmd| cmd.name == name) {
+        Some(cmd) => format!("Usage: {}, see :help {}", cmd.usage, name),
+        None => format!("Incorrect arguments, see :help {}", name),
+    }
+}
 
-:del <line_num> => remove a specific line from repl code (line count starts at 1 from the first expression statement)
+impl IRust {
+    pub fn help(&mut self) -> Result<Printer, IRustError> {
+        // exp: :help, :help add, :help keys
+        let args = CmdArgs::parse("help", &self.buffer.to_string())?;
+        let outputs = match args.as_strs().as_slice() {
+            [] => Self::commands_help(),
+            ["keys"] => self.keys_help(),
+            [topic] => {
+                let topic = topic.trim_start_matches(':');
+                match COMMANDS.iter().find(|cmd| cmd.name == topic) {
+                    Some(cmd) => Self::command_help(cmd),
+                    None => {
+                        return Err(IRustError::Custom(format!(
+                            "No help for `{}`, use :help to list the commands",
+                            topic
+                        )))
+                    }
+                }
+            }
+            _ => return Err(args.usage_error()),
+        };
 
-:edit <editor> => edit internal buffer using an external editor, example: :edit micro
+        // long help is paged
+        if outputs.lines_count() >= self.cursor.bound.height && self.page(&outputs).is_ok() {
+            return Ok(Printer::default());
+        }
 
-:: => run a shell command, example ::ls
+        Ok(outputs)
+    }
 
-You can use arrow keys to cycle through commands history"
-                .to_output(Color::DarkCyan),
-        );
-        outputs.push(
-            "
-### Keybindings ###"
-                .to_output(Color::DarkYellow),
-        );
-        outputs.push(
-            "
+    fn commands_help() -> Printer {
+        let mut outputs = Printer::default();
+        outputs.push("### Commands ###".to_output(Color::DarkYellow));
+        outputs.add_new_line(2);
 
-ctrl-l clear screen
+        let width = COMMANDS
+            .iter()
+            .map(|cmd| cmd.usage.len())
+            .max()
+            .unwrap_or(0);
+        for cmd in COMMANDS.iter() {
+            outputs
+                .push(format!("{:width$}", cmd.usage, width = width).to_output(Color::DarkYellow));
+            outputs.push(format!("  {}", cmd.summary).to_output(Color::DarkCyan));
+            outputs.add_new_line(1);
+        }
 
-ctrl-c clear line, double click to exit
+        outputs.add_new_line(1);
+        outputs.push("### Tips & Tricks ###".to_output(Color::DarkYellow));
+        outputs.add_new_line(2);
+        for tip in TIPS.iter() {
+            outputs.push(tip.to_output(Color::DarkCyan));
+            outputs.add_new_line(1);
+        }
 
-ctrl-d exit if buffer is empty
+        outputs
+    }
 
-ctrl-z [unix only] send IRust to the background
+    fn command_help(cmd: &CommandHelp) -> Printer {
+        let mut outputs = Printer::new(cmd.usage.to_output(Color::DarkYellow));
+        outputs.add_new_line(2);
+        outputs.push(cmd.summary.to_output(Color::DarkCyan));
+        outputs.add_new_line(1);
 
-ctrl-left/right jump through words
+        for detail in cmd.details {
+            match detail.strip_prefix("example: ") {
+                Some(example) => {
+                    outputs.push("    example: ".to_output(Color::DarkCyan));
+                    outputs.push(example.to_output(Color::DarkGreen));
+                }
+                None => outputs.push(format!("    {}", detail).to_output(Color::DarkCyan)),
+            }
+            outputs.add_new_line(1);
+        }
 
-HOME/END go to line start / line end
+        outputs
+    }
 
-Tab/ShiftTab cycle through auto-completion suggestions (requires racer)
+    /// The keybindings enabled with the current options
+    fn keys_help(&self) -> Printer {
+        let mut keys = vec![
+            ("ctrl-l", "clear screen"),
+            (
+                "ctrl-c",
+                "clear line, double click to exit, interrupt a running evaluation/build",
+            ),
+            (
+                "ctrl-d",
+                "exit if buffer is empty, otherwise delete the character under the cursor",
+            ),
+        ];
+        if cfg!(unix) {
+            keys.push(("ctrl-z", "send IRust to the background"));
+        }
+        keys.push((
+            "ctrl-left/right",
+            "jump through words, ctrl-right at the end of the input accepts the next word of the suggestion",
+        ));
+        keys.push((
+            "ctrl-e",
+            "edit the history entry being shown, Up/Down continue from it",
+        ));
+        keys.push((
+            "ctrl-o",
+            "run the history entry being shown and show the next one",
+        ));
+        keys.push(("HOME/END", "go to line start / line end"));
+        keys.push((
+            "ctrl-HOME/END",
+            "go to input start / input end, PageUp/PageDown too",
+        ));
+        keys.push((
+            "Shift-arrows",
+            "select text, ctrl-x cuts, alt-w copies, ctrl-y pastes, backspace/delete delete it",
+        ));
+        if self.racer.is_ok() {
+            keys.push((
+                "Tab/ShiftTab",
+                "cycle forward/backward through auto-completion suggestions",
+            ));
+        }
+        keys.push(("Alt-Enter", "add line break"));
+        keys.push(("ctrl-t", "transpose the characters around the cursor"));
+        keys.push((
+            "Alt-u/l/c",
+            "upcase/downcase/capitalize the word after the cursor",
+        ));
+        if self.options.horizontal_scroll {
+            keys.push(("Alt-left/right", "scroll the last output horizontally"));
+        }
+        keys.push(("F2", "show/hide the repl code pane"));
+        keys.push(("F3", "format the input with rustfmt"));
+        keys.push((
+            "F4",
+            "add the imports suggested for the last failed evaluation and evaluate it again",
+        ));
 
-Alt-Enter add line break"
-                .to_output(Color::DarkCyan),
-        );
+        let mut outputs = Printer::new("### Keybindings ###".to_output(Color::DarkYellow));
+        outputs.add_new_line(2);
+        let width = keys.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+        for (key, action) in keys {
+            outputs.push(format!("{:width$}", key, width = width).to_output(Color::DarkYellow));
+            outputs.push(format!("  {}", action).to_output(Color::DarkCyan));
+            outputs.add_new_line(1);
+        }
 
-        Ok(outputs)
+        outputs
     }
 }
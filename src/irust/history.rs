@@ -1,4 +1,5 @@
 use super::IRustError;
+use regex::Regex;
 use std::fs;
 use std::path;
 
@@ -11,40 +12,71 @@ pub struct History {
     buffer_copy: String,
     cursor: usize,
     path: path::PathBuf,
+    // the history that is not loaded (global or project one), the session entries are saved in both
+    other_path: Option<path::PathBuf>,
+    // number of entries loaded from `path`, the next ones are the session ones
+    loaded: usize,
+    // the shown entry is edited in place, its position is kept (`Ctrl-e`)
+    editing: bool,
+    // entries starting with a space are not saved
+    ignore_space: bool,
+    // entries matching it are not saved, exp: the ones with a token
+    exclude: Option<Regex>,
 }
 
 impl History {
-    pub fn new(path: path::PathBuf) -> Result<Self, IRustError> {
-        let _ = fs::create_dir_all(&path);
-
-        let path = path.join("history");
-        if !path.exists() {
-            let _ = fs::File::create(&path);
-        }
-
-        let history: String = fs::read_to_string(&path)?;
-
-        let history: Vec<String> = if history.starts_with(NEW_HISTORY_MARK) {
-            history
-                .split("\n//\n")
-                .skip(1)
-                .map(ToOwned::to_owned)
-                .collect()
-        } else {
-            history.lines().map(ToOwned::to_owned).collect()
+    /// Load the global history, or the one of the current directory with `project`
+    pub fn new(dir: path::PathBuf, project: bool) -> Result<Self, IRustError> {
+        let global = dir.join("history");
+        let project_path = std::env::current_dir()
+            .ok()
+            .map(|cwd| dir.join("projects").join(project_file_name(&cwd)));
+        let (path, other_path) = match project_path {
+            Some(project_path) if project => (project_path, Some(global)),
+            project_path => (global, project_path),
         };
 
+        let history = read_history(&path)?;
         let cursor = history.len();
         let buffer_copy = String::new();
 
         Ok(Self {
+            loaded: history.len(),
             history,
             buffer_copy,
             cursor,
             path,
+            other_path,
+            editing: false,
+            ignore_space: false,
+            exclude: None,
         })
     }
+
+    /// The entries that are kept in the session but never saved
+    pub fn set_exclusions(&mut self, ignore_space: bool, exclude: Option<Regex>) {
+        self.ignore_space = ignore_space;
+        self.exclude = exclude;
+
+        // the entries saved before the exclusions were set are not loaded either
+        let loaded = std::mem::take(&mut self.history);
+        self.history = loaded
+            .into_iter()
+            .filter(|entry| !self.is_excluded(entry))
+            .collect();
+        self.loaded = self.history.len();
+        self.cursor = self.history.len();
+    }
+
+    fn is_excluded(&self, entry: &str) -> bool {
+        (self.ignore_space && entry.starts_with(' '))
+            || self
+                .exclude
+                .as_ref()
+                .is_some_and(|exclude| exclude.is_match(entry))
+    }
     pub fn down(&mut self) -> Option<String> {
+        self.editing = false;
         let filtered = self.filter();
         self.cursor += 1;
         if self.cursor >= filtered.len() {
@@ -56,6 +88,7 @@ impl History {
     }
 
     pub fn up(&mut self) -> Option<String> {
+        self.editing = false;
         let filtered = self.filter();
         self.cursor = std::cmp::min(self.cursor, filtered.len());
         if self.cursor == 0 || filtered.is_empty() {
@@ -66,6 +99,10 @@ impl History {
         }
     }
 
+    pub fn entries(&self) -> &[String] {
+        &self.history
+    }
+
     pub fn push(&mut self, buffer: String) {
         if !buffer.is_empty() && Some(&buffer) != self.history.last() {
             self.buffer_copy.clear();
@@ -75,37 +112,61 @@ impl History {
     }
 
     pub fn update_buffer_copy(&mut self, buffer: &str) {
+        if self.editing {
+            return;
+        }
         self.buffer_copy = buffer.to_string();
         self.cursor = self.history.len();
     }
 
     pub fn reset_buffer_copy(&mut self) {
+        self.editing = false;
         self.buffer_copy.clear();
         self.cursor = self.history.len();
     }
 
-    pub fn save(&self) {
-        let is_comment = |s: &str| -> bool { s.trim_start().starts_with("//") };
-        let mut history = self.history.clone();
+    /// Edit the shown entry without leaving its position, returns false if no entry is shown
+    pub fn edit_current(&mut self) -> bool {
+        self.editing = self.cursor < self.filter().len();
+        self.editing
+    }
 
-        if history[0] != NEW_HISTORY_MARK {
-            history.insert(0, NEW_HISTORY_MARK.to_string());
-        }
+    /// Index in the whole history of the entry after the shown one
+    pub fn next_index(&self) -> Option<usize> {
+        self.history
+            .iter()
+            .enumerate()
+            .filter(|(_, h)| h.contains(&self.buffer_copy))
+            .map(|(idx, _)| idx)
+            .nth(self.cursor + 1)
+    }
 
-        let history: Vec<String> = history
-            .into_iter()
-            .map(|e| {
-                let e: Vec<String> = e
-                    .lines()
-                    .filter(|l| !is_comment(l))
-                    .map(ToOwned::to_owned)
-                    .collect();
-                e.join("\n")
-            })
-            .collect();
-        let history = history.join("\n//\n");
+    /// Show the entry at `idx` of the whole history for editing, exp: after `Ctrl-o`
+    pub fn edit_entry(&mut self, idx: usize) -> Option<String> {
+        let entry = self.history.get(idx)?.clone();
+        self.buffer_copy.clear();
+        self.cursor = idx;
+        self.editing = true;
+        Some(entry)
+    }
+
+    pub fn save(&self) {
+        let saved = |entries: &[String]| -> Vec<String> {
+            entries
+                .iter()
+                .filter(|entry| !self.is_excluded(entry))
+                .cloned()
+                .collect()
+        };
+        write_history(&self.path, saved(&self.history));
 
-        let _ = fs::write(&self.path, history);
+        // the session entries are added to the other history too
+        if let Some(other_path) = &self.other_path {
+            if let Ok(mut other) = read_history(other_path) {
+                other.extend(saved(&self.history[self.loaded..]));
+                write_history(other_path, other);
+            }
+        }
     }
 
     fn filter(&self) -> Vec<String> {
@@ -122,3 +183,125 @@ impl History {
         }
     }
 }
+
+fn read_history(path: &path::Path) -> Result<Vec<String>, IRustError> {
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    if !path.exists() {
+        let _ = fs::File::create(path);
+    }
+
+    let history: String = fs::read_to_string(path)?;
+
+    let history: Vec<String> = if history.starts_with(NEW_HISTORY_MARK) {
+        history
+            .split("\n//\n")
+            .skip(1)
+            .map(ToOwned::to_owned)
+            .collect()
+    } else {
+        history.lines().map(ToOwned::to_owned).collect()
+    };
+    Ok(history)
+}
+
+fn write_history(path: &path::Path, mut history: Vec<String>) {
+    let is_comment = |s: &str| -> bool { s.trim_start().starts_with("//") };
+
+    if history.first().map(String::as_str) != Some(NEW_HISTORY_MARK) {
+        history.insert(0, NEW_HISTORY_MARK.to_string());
+    }
+
+    let history: Vec<String> = history
+        .into_iter()
+        .map(|e| {
+            let e: Vec<String> = e
+                .lines()
+                .filter(|l| !is_comment(l))
+                .map(ToOwned::to_owned)
+                .collect();
+            e.join("\n")
+        })
+        .collect();
+    let history = history.join("\n//\n");
+
+    let _ = fs::write(path, history);
+}
+
+/// The history file of a directory: its name and a hash of its canonical path, so directories
+/// with the same name or the same path once sanitized don't share it, exp: `app-6c3e226b4d4795d5`
+fn project_file_name(dir: &path::Path) -> String {
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_owned());
+    let name: String = dir
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!(
+        "{}-{:016x}",
+        name,
+        fnv1a(dir.as_os_str().as_encoded_bytes())
+    )
+}
+
+// a hash that doesn't change between Rust releases, unlike the std one
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn project_file_names_dont_collide() {
+        // they were all `tmp_a_b_c` once sanitized
+        let names: Vec<String> = ["/tmp/a_b/c", "/tmp/a/b/c", "/tmp/a b/c", "/tmp/a-b/c"]
+            .iter()
+            .map(|dir| project_file_name(path::Path::new(dir)))
+            .collect();
+        for (idx, name) in names.iter().enumerate() {
+            assert!(name.starts_with("c-"), "{}", name);
+            assert!(!names[idx + 1..].contains(name), "{}", name);
+        }
+        assert_eq!(project_file_name(path::Path::new("/")).len(), 17);
+    }
+
+    #[test]
+    fn project_file_name_of_the_canonical_path() {
+        let dir = std::env::temp_dir();
+        assert_eq!(project_file_name(&dir.join(".")), project_file_name(&dir));
+    }
+
+    #[test]
+    fn excluded_entries_are_not_loaded() {
+        let dir = std::env::temp_dir().join("irust_history_exclusions");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        write_history(
+            &dir.join("history"),
+            vec![
+                "let a = 1;".to_string(),
+                " let secret = 2;".to_string(),
+                "let token = \"abc\";".to_string(),
+            ],
+        );
+
+        let mut history = History::new(dir.clone(), false).unwrap();
+        history.set_exclusions(true, Some(Regex::new("token").unwrap()));
+        assert_eq!(history.entries(), ["let a = 1;"]);
+        assert_eq!(history.up().as_deref(), Some("let a = 1;"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,197 @@
+use super::cargo_cmds::cargo_run;
+use super::cmd_args::CmdArgs;
+use crate::irust::format::format_eval_output;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+use crate::utils::stdout_and_stderr;
+
+// (name, path) of the std traits that are looked for
+const STD_TRAITS: [(&str, &str); 22] = [
+    ("Clone", "std::clone::Clone"),
+    ("Copy", "std::marker::Copy"),
+    ("Debug", "std::fmt::Debug"),
+    ("Display", "std::fmt::Display"),
+    ("Default", "std::default::Default"),
+    ("PartialEq", "std::cmp::PartialEq"),
+    ("Eq", "std::cmp::Eq"),
+    ("PartialOrd", "std::cmp::PartialOrd"),
+    ("Ord", "std::cmp::Ord"),
+    ("Hash", "std::hash::Hash"),
+    ("Send", "std::marker::Send"),
+    ("Sync", "std::marker::Sync"),
+    ("Unpin", "std::marker::Unpin"),
+    ("Sized", "std::marker::Sized"),
+    ("Iterator", "std::iter::Iterator"),
+    ("DoubleEndedIterator", "std::iter::DoubleEndedIterator"),
+    ("ExactSizeIterator", "std::iter::ExactSizeIterator"),
+    ("IntoIterator", "std::iter::IntoIterator"),
+    ("ToString", "std::string::ToString"),
+    ("FromStr", "std::str::FromStr"),
+    ("Error", "std::error::Error"),
+    ("Future", "std::future::Future"),
+];
+
+// std types checked by `:impls <Trait>` in addition to the session types
+const STD_TYPES: [&str; 14] = [
+    "()",
+    "bool",
+    "char",
+    "i32",
+    "u8",
+    "usize",
+    "f64",
+    "str",
+    "&str",
+    "String",
+    "Vec<i32>",
+    "Option<i32>",
+    "std::collections::HashMap<String, i32>",
+    "Box<dyn std::any::Any>",
+];
+
+/// Traits and types defined in the repl, generic ones are left out
+fn session_items(code: &str) -> (Vec<String>, Vec<String>) {
+    let mut traits = vec![];
+    let mut types = vec![];
+    let file: syn::File = match syn::parse_str(code) {
+        Ok(file) => file,
+        Err(_) => return (traits, types),
+    };
+
+    // the repl items are defined inside main
+    let mut items: Vec<&syn::Item> = file.items.iter().collect();
+    for item in &file.items {
+        if let syn::Item::Fn(function) = item {
+            if function.sig.ident == "main" {
+                items.extend(function.block.stmts.iter().filter_map(|stmt| match stmt {
+                    syn::Stmt::Item(item) => Some(item),
+                    _ => None,
+                }));
+            }
+        }
+    }
+
+    for item in items {
+        match item {
+            syn::Item::Trait(item) if item.generics.params.is_empty() => {
+                traits.push(item.ident.to_string())
+            }
+            syn::Item::Struct(item) if item.generics.params.is_empty() => {
+                types.push(item.ident.to_string())
+            }
+            syn::Item::Enum(item) if item.generics.params.is_empty() => {
+                types.push(item.ident.to_string())
+            }
+            syn::Item::Union(item) if item.generics.params.is_empty() => {
+                types.push(item.ident.to_string())
+            }
+            _ => (),
+        }
+    }
+    (traits, types)
+}
+
+/// Prints `<type index> <trait index>` for every type that implements a trait,
+/// an inherent method bounded by the trait shadows the trait default method returning false
+fn impls_probe(types: &[String], traits: &[String]) -> String {
+    let mut probe = String::from("{\nstruct IRustProbe<T: ?Sized>(std::marker::PhantomData<T>);\n");
+    for (idx, path) in traits.iter().enumerate() {
+        probe += &format!(
+            "trait IRustNotImpl{idx} {{ fn irust_impl{idx}(&self) -> bool {{ false }} }}\n\
+             impl<T: ?Sized> IRustNotImpl{idx} for IRustProbe<T> {{}}\n\
+             impl<T: ?Sized + {path}> IRustProbe<T> {{ fn irust_impl{idx}(&self) -> bool {{ true }} }}\n",
+            idx = idx,
+            path = path
+        );
+    }
+    for (type_idx, ty) in types.iter().enumerate() {
+        for trait_idx in 0..traits.len() {
+            probe += &format!(
+                "if IRustProbe::<{ty}>(std::marker::PhantomData).irust_impl{trait_idx}() {{ println!(\"{type_idx} {trait_idx}\"); }}\n",
+                ty = ty,
+                type_idx = type_idx,
+                trait_idx = trait_idx
+            );
+        }
+    }
+    probe + "}"
+}
+
+impl IRust {
+    pub fn impls(&mut self) -> Result<Printer, IRustError> {
+        // exp: :impls Vec<u8>, :impls Display
+        let target = CmdArgs::parse_code("impls", &self.buffer.to_string())
+            .code()
+            .to_string();
+        if target.is_empty() {
+            return Err(IRustError::Custom("No type or trait specified".to_string()));
+        }
+
+        let (session_traits, session_types) = session_items(&self.repl.body.join("\n"));
+        let std_trait = STD_TRAITS
+            .iter()
+            .find(|(name, path)| *name == target || *path == target);
+        let is_trait = std_trait.is_some() || session_traits.contains(&target);
+
+        let (types, traits): (Vec<String>, Vec<(String, String)>) = if is_trait {
+            let path = std_trait.map_or(target.clone(), |(_, path)| path.to_string());
+            let types = session_types
+                .into_iter()
+                .chain(STD_TYPES.iter().map(ToString::to_string))
+                .collect();
+            (types, vec![(target.clone(), path)])
+        } else {
+            let traits = STD_TRAITS
+                .iter()
+                .map(|(name, path)| (name.to_string(), path.to_string()))
+                .chain(session_traits.into_iter().map(|name| (name.clone(), name)))
+                .collect();
+            (vec![target.clone()], traits)
+        };
+
+        let paths: Vec<String> = traits.iter().map(|(_, path)| path.clone()).collect();
+        let job = self
+            .repl
+            .eval_in_tmp_repl(impls_probe(&types, &paths), || Ok(cargo_run(true)?))?;
+        let output = self.wait_job(job, "Evaluating")?;
+        if !output.status.success() {
+            let mut outputs = format_eval_output(&stdout_and_stderr(output));
+            outputs.add_new_line(1);
+            return Ok(outputs);
+        }
+
+        let found: Vec<&str> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let (type_idx, trait_idx) = line.split_once(' ')?;
+                let (type_idx, trait_idx): (usize, usize) =
+                    (type_idx.parse().ok()?, trait_idx.parse().ok()?);
+                Some(if is_trait {
+                    types.get(type_idx)?.as_str()
+                } else {
+                    traits.get(trait_idx)?.0.as_str()
+                })
+            })
+            .collect();
+
+        let title = if is_trait {
+            format!(
+                "{} is implemented by (session and common std types):",
+                target
+            )
+        } else {
+            format!("{} implements (std and session traits):", target)
+        };
+        let mut outputs = Printer::new(PrinterItem::new(title, PrinterItemType::Eval));
+        outputs.add_new_line(1);
+        let list = if found.is_empty() {
+            "nothing".to_string()
+        } else {
+            found.join(", ")
+        };
+        outputs.push(PrinterItem::new(list, PrinterItemType::Ok));
+        outputs.add_new_line(1);
+
+        Ok(outputs)
+    }
+}
@@ -0,0 +1,186 @@
+use crate::irust::jobs::interrupt;
+use crate::irust::IRust;
+use crossterm::{InputEvent, KeyEvent};
+use std::sync::mpsc;
+use std::sync::{Arc, Condvar, Mutex};
+#[cfg(unix)]
+mod keys;
+
+#[derive(Debug, PartialEq)]
+pub enum Input {
+    Event(InputEvent),
+    // the keys crossterm doesn't know
+    Key(Key),
+    // the terminal size changed
+    Resize,
+}
+
+// only the unix reader parses them
+#[cfg_attr(not(unix), allow(dead_code))]
+#[derive(Debug, PartialEq)]
+pub enum Key {
+    CtrlHome,
+    CtrlEnd,
+    AltLeft,
+    AltRight,
+}
+
+#[derive(Default)]
+struct ReaderState {
+    paused: bool,
+    // the reader is waiting for `resume` and doesn't touch the terminal
+    idle: bool,
+    // the terminal is gone, the reader thread returned
+    stopped: bool,
+}
+
+/// Reads the terminal on its own thread, so input can still be handled while cargo is running,
+/// it's paused while a child process owns the terminal (editor, pager, `::!`)
+pub struct InputReader {
+    state: Arc<(Mutex<ReaderState>, Condvar)>,
+}
+
+impl InputReader {
+    pub fn start(send: mpsc::Sender<Input>) -> Self {
+        let state = Arc::new((Mutex::new(ReaderState::default()), Condvar::new()));
+        let thread_state = state.clone();
+        std::thread::spawn(move || {
+            read_loop(&send, &thread_state);
+            // a `pause` waiting for the reader must not wait forever
+            let (lock, cvar) = &*thread_state;
+            lock.lock().unwrap_or_else(|e| e.into_inner()).stopped = true;
+            cvar.notify_all();
+        });
+        Self { state }
+    }
+
+    /// Returns once the reader stopped reading
+    fn pause(&self) {
+        // the crossterm reader can't be interrupted
+        if cfg!(not(unix)) {
+            return;
+        }
+        let (lock, cvar) = &*self.state;
+        let mut state = lock.lock().unwrap_or_else(|e| e.into_inner());
+        state.paused = true;
+        cvar.notify_all();
+        while !state.idle && !state.stopped {
+            state = cvar.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    fn resume(&self) {
+        let (lock, cvar) = &*self.state;
+        lock.lock().unwrap_or_else(|e| e.into_inner()).paused = false;
+        cvar.notify_all();
+    }
+}
+
+// crossterm 0.11 blocks in a read of stdin that can't be stopped and doesn't report resizes or
+// modified keys like Ctrl-Home, so on unix the tty is polled and its bytes are parsed here
+#[cfg(unix)]
+fn read_loop(send: &mpsc::Sender<Input>, state: &(Mutex<ReaderState>, Condvar)) {
+    use nix::errno::Errno;
+    use nix::poll::{poll, PollFd, PollFlags};
+    use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+    use std::io::Read;
+    use std::os::unix::io::AsRawFd;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    // a lone ESC is the Esc key if nothing follows it in this time
+    const ESC_TIMEOUT: i32 = 50;
+    static RESIZED: AtomicBool = AtomicBool::new(false);
+    extern "C" fn on_resize(_: nix::libc::c_int) {
+        RESIZED.store(true, Ordering::Relaxed);
+    }
+    let resize = SigAction::new(
+        SigHandler::Handler(on_resize),
+        SaFlags::empty(),
+        SigSet::empty(),
+    );
+    let _ = unsafe { sigaction(Signal::SIGWINCH, &resize) };
+
+    let mut tty = match std::fs::File::open("/dev/tty") {
+        Ok(tty) => tty,
+        Err(_) => return,
+    };
+    let mut pending = vec![];
+    loop {
+        wait_while_paused(state);
+
+        if RESIZED.swap(false, Ordering::Relaxed) && send.send(Input::Resize).is_err() {
+            return;
+        }
+
+        let mut fds = [PollFd::new(tty.as_raw_fd(), PollFlags::POLLIN)];
+        let inputs = match poll(&mut fds, ESC_TIMEOUT) {
+            Ok(0) => keys::flush(&mut pending),
+            // interrupted by SIGWINCH
+            Err(nix::Error::Sys(Errno::EINTR)) => continue,
+            Err(_) => return,
+            Ok(_) => {
+                let mut bytes = [0; 1024];
+                match tty.read(&mut bytes) {
+                    // the terminal was closed
+                    Ok(0) => return,
+                    Ok(read) => pending.extend_from_slice(&bytes[..read]),
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                    Err(_) => return,
+                }
+                keys::parse(&mut pending)
+            }
+        };
+        for input in inputs {
+            if !forward(send, input) {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn read_loop(send: &mpsc::Sender<Input>, _state: &(Mutex<ReaderState>, Condvar)) {
+    for event in crossterm::TerminalInput::new().read_sync() {
+        if !forward(send, Input::Event(event)) {
+            return;
+        }
+    }
+}
+
+// raw mode gets Ctrl-c as a key instead of SIGINT, it interrupts the running job the same way
+fn forward(send: &mpsc::Sender<Input>, input: Input) -> bool {
+    if let Input::Event(InputEvent::Keyboard(KeyEvent::Ctrl('c'))) = input {
+        interrupt();
+    }
+    send.send(input).is_ok()
+}
+
+#[cfg(unix)]
+fn wait_while_paused(state: &(Mutex<ReaderState>, Condvar)) {
+    let (lock, cvar) = state;
+    let mut state = lock.lock().unwrap_or_else(|e| e.into_inner());
+    if !state.paused {
+        return;
+    }
+    state.idle = true;
+    cvar.notify_all();
+    while state.paused {
+        state = cvar.wait(state).unwrap_or_else(|e| e.into_inner());
+    }
+    state.idle = false;
+}
+
+impl IRust {
+    /// Stop reading the terminal before giving it to a child process, so its keys are not taken
+    pub fn pause_input(&self) {
+        if let Some(reader) = &self.input_reader {
+            reader.pause();
+        }
+    }
+
+    pub fn resume_input(&self) {
+        if let Some(reader) = &self.input_reader {
+            reader.resume();
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use super::{Input, Key};
+use crossterm::{InputEvent, KeyEvent};
+
+const ESC: u8 = 0x1B;
+
+/// Parse the complete events at the start of `pending`, an incomplete escape sequence or
+/// character is kept until the next read
+pub fn parse(pending: &mut Vec<u8>) -> Vec<Input> {
+    let mut inputs = vec![];
+    while let Some((input, len)) = parse_one(pending) {
+        pending.drain(..len);
+        inputs.push(input);
+    }
+    inputs
+}
+
+/// Nothing more arrived, what is left is not waiting for more bytes: a lone ESC is the Esc key
+pub fn flush(pending: &mut Vec<u8>) -> Vec<Input> {
+    let mut inputs = vec![];
+    while !pending.is_empty() {
+        inputs.extend(parse(pending));
+        if pending.first() == Some(&ESC) {
+            pending.remove(0);
+            inputs.push(key(KeyEvent::Esc));
+        } else {
+            pending.clear();
+        }
+    }
+    inputs
+}
+
+fn key(key: KeyEvent) -> Input {
+    Input::Event(InputEvent::Keyboard(key))
+}
+
+// (event, bytes it used), None if more bytes are needed
+fn parse_one(bytes: &[u8]) -> Option<(Input, usize)> {
+    let input = match *bytes.first()? {
+        ESC => {
+            return match *bytes.get(1)? {
+                b'[' => parse_csi(bytes, 2),
+                b'O' => {
+                    let input = match *bytes.get(2)? {
+                        c @ b'P'..=b'S' => key(KeyEvent::F(1 + c - b'P')),
+                        b'H' => key(KeyEvent::Home),
+                        b'F' => key(KeyEvent::End),
+                        _ => Input::Event(InputEvent::Unknown),
+                    };
+                    Some((input, 3))
+                }
+                // some terminals send Alt-arrows as ESC then the arrow
+                ESC if bytes.len() < 3 => None,
+                ESC if bytes[2] == b'[' => {
+                    match parse_csi(bytes, 3)? {
+                        (Input::Event(InputEvent::Keyboard(KeyEvent::Left)), len) => {
+                            Some((Input::Key(Key::AltLeft), len))
+                        }
+                        (Input::Event(InputEvent::Keyboard(KeyEvent::Right)), len) => {
+                            Some((Input::Key(Key::AltRight), len))
+                        }
+                        // Esc then another key
+                        _ => Some((key(KeyEvent::Esc), 1)),
+                    }
+                }
+                ESC => Some((key(KeyEvent::Esc), 1)),
+                _ => {
+                    let (c, len) = utf8_char(&bytes[1..])?;
+                    Some((
+                        c.map_or(Input::Event(InputEvent::Unknown), |c| key(KeyEvent::Alt(c))),
+                        1 + len,
+                    ))
+                }
+            };
+        }
+        b'\r' | b'\n' => key(KeyEvent::Enter),
+        b'\t' => key(KeyEvent::Tab),
+        0x7F => key(KeyEvent::Backspace),
+        c @ 0x01..=0x1A => key(KeyEvent::Ctrl((c - 0x01 + b'a') as char)),
+        c @ 0x1C..=0x1F => key(KeyEvent::Ctrl((c - 0x1C + b'4') as char)),
+        0 => key(KeyEvent::Null),
+        _ => {
+            let (c, len) = utf8_char(bytes)?;
+            return Some((
+                c.map_or(Input::Event(InputEvent::Unknown), |c| {
+                    key(KeyEvent::Char(c))
+                }),
+                len,
+            ));
+        }
+    };
+    Some((input, 1))
+}
+
+// ESC [ parameters final-byte, the parameters are `;` separated numbers
+fn parse_csi(bytes: &[u8], start: usize) -> Option<(Input, usize)> {
+    // the linux console F1-F5: ESC [ [ A-E
+    if *bytes.get(start)? == b'[' {
+        let input = match *bytes.get(start + 1)? {
+            c @ b'A'..=b'E' => key(KeyEvent::F(1 + c - b'A')),
+            _ => Input::Event(InputEvent::Unknown),
+        };
+        return Some((input, start + 2));
+    }
+
+    let end = start
+        + bytes[start..]
+            .iter()
+            .position(|b| (0x40..=0x7E).contains(b))?;
+    let params: Vec<u8> = std::str::from_utf8(&bytes[start..end])
+        .unwrap_or_default()
+        .split(';')
+        .map(|param| param.parse().unwrap_or(0))
+        .collect();
+    // xterm modifiers: 1 + (1 shift, 2 alt, 4 ctrl)
+    let modifier = params.get(1).copied().unwrap_or(1);
+
+    let input = match (bytes[end], modifier) {
+        (b'A', 1) => key(KeyEvent::Up),
+        (b'B', 1) => key(KeyEvent::Down),
+        (b'C', 1) => key(KeyEvent::Right),
+        (b'D', 1) => key(KeyEvent::Left),
+        (b'A', 2) => key(KeyEvent::ShiftUp),
+        (b'B', 2) => key(KeyEvent::ShiftDown),
+        (b'C', 2) => key(KeyEvent::ShiftRight),
+        (b'D', 2) => key(KeyEvent::ShiftLeft),
+        (b'C', 3) => Input::Key(Key::AltRight),
+        (b'D', 3) => Input::Key(Key::AltLeft),
+        (b'A', 5) => key(KeyEvent::CtrlUp),
+        (b'B', 5) => key(KeyEvent::CtrlDown),
+        (b'C', 5) => key(KeyEvent::CtrlRight),
+        (b'D', 5) => key(KeyEvent::CtrlLeft),
+        (b'H', 1) => key(KeyEvent::Home),
+        (b'F', 1) => key(KeyEvent::End),
+        (b'H', 5) => Input::Key(Key::CtrlHome),
+        (b'F', 5) => Input::Key(Key::CtrlEnd),
+        (b'Z', _) => key(KeyEvent::BackTab),
+        (b'~', 1) => match params[0] {
+            1 | 7 => key(KeyEvent::Home),
+            2 => key(KeyEvent::Insert),
+            3 => key(KeyEvent::Delete),
+            4 | 8 => key(KeyEvent::End),
+            5 => key(KeyEvent::PageUp),
+            6 => key(KeyEvent::PageDown),
+            v @ 11..=15 => key(KeyEvent::F(v - 10)),
+            v @ 17..=21 => key(KeyEvent::F(v - 11)),
+            v @ 23..=24 => key(KeyEvent::F(v - 12)),
+            _ => Input::Event(InputEvent::Unknown),
+        },
+        (b'~', 5) => match params[0] {
+            1 | 7 => Input::Key(Key::CtrlHome),
+            4 | 8 => Input::Key(Key::CtrlEnd),
+            _ => Input::Event(InputEvent::Unknown),
+        },
+        // rxvt
+        (b'^', _) => match params[0] {
+            7 => Input::Key(Key::CtrlHome),
+            8 => Input::Key(Key::CtrlEnd),
+            _ => Input::Event(InputEvent::Unknown),
+        },
+        _ => Input::Event(InputEvent::Unknown),
+    };
+    Some((input, end + 1))
+}
+
+// None if the character is not complete yet, an invalid one is returned as None
+fn utf8_char(bytes: &[u8]) -> Option<(Option<char>, usize)> {
+    let len = match bytes[0] {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => return Some((None, 1)),
+    };
+    let bytes = bytes.get(..len)?;
+    match std::str::from_utf8(bytes) {
+        Ok(s) => Some((s.chars().next(), len)),
+        // only the first byte is dropped, what follows may be a valid character
+        Err(_) => Some((None, 1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(bytes: &[u8]) -> (Vec<Input>, Vec<u8>) {
+        let mut pending = bytes.to_vec();
+        let inputs = parse(&mut pending);
+        (inputs, pending)
+    }
+
+    #[test]
+    fn parse_escape_sequences() {
+        let (inputs, pending) = keys(b"\x1b[A\x1b[1;5C\x1b[3~\x1bOP\x1b[15~\x1b[Z");
+        assert_eq!(
+            inputs,
+            vec![
+                key(KeyEvent::Up),
+                key(KeyEvent::CtrlRight),
+                key(KeyEvent::Delete),
+                key(KeyEvent::F(1)),
+                key(KeyEvent::F(5)),
+                key(KeyEvent::BackTab),
+            ]
+        );
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn parse_control_and_alt_keys() {
+        let (inputs, _) = keys(b"\x03\r\t\x7f\x1bw");
+        assert_eq!(
+            inputs,
+            vec![
+                key(KeyEvent::Ctrl('c')),
+                key(KeyEvent::Enter),
+                key(KeyEvent::Tab),
+                key(KeyEvent::Backspace),
+                key(KeyEvent::Alt('w')),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_ctrl_home_and_end() {
+        let (inputs, _) = keys(b"\x1b[1;5H\x1b[1;5F\x1b[1;5~\x1b[4;5~\x1b[7^\x1b[8^");
+        assert_eq!(
+            inputs,
+            vec![
+                Input::Key(Key::CtrlHome),
+                Input::Key(Key::CtrlEnd),
+                Input::Key(Key::CtrlHome),
+                Input::Key(Key::CtrlEnd),
+                Input::Key(Key::CtrlHome),
+                Input::Key(Key::CtrlEnd),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_alt_arrows() {
+        let (inputs, _) = keys(b"\x1b[1;3D\x1b[1;3C\x1b\x1b[D\x1b\x1b[C");
+        assert_eq!(
+            inputs,
+            vec![
+                Input::Key(Key::AltLeft),
+                Input::Key(Key::AltRight),
+                Input::Key(Key::AltLeft),
+                Input::Key(Key::AltRight),
+            ]
+        );
+    }
+
+    #[test]
+    fn incomplete_sequence_waits_for_more_bytes() {
+        let mut pending = b"a\x1b[1;".to_vec();
+        assert_eq!(parse(&mut pending), vec![key(KeyEvent::Char('a'))]);
+        assert_eq!(pending, b"\x1b[1;");
+
+        pending.extend_from_slice(b"2A");
+        assert_eq!(parse(&mut pending), vec![key(KeyEvent::ShiftUp)]);
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn split_utf8_char() {
+        let bytes = "é€".as_bytes();
+        let mut pending = bytes[..3].to_vec();
+        assert_eq!(parse(&mut pending), vec![key(KeyEvent::Char('é'))]);
+        assert_eq!(pending, &bytes[2..3]);
+
+        pending.extend_from_slice(&bytes[3..]);
+        assert_eq!(parse(&mut pending), vec![key(KeyEvent::Char('€'))]);
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn invalid_utf8_is_unknown() {
+        let (inputs, pending) = keys(b"\xff\xc3(");
+        assert_eq!(
+            inputs,
+            vec![
+                Input::Event(InputEvent::Unknown),
+                Input::Event(InputEvent::Unknown),
+                key(KeyEvent::Char('(')),
+            ]
+        );
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn lone_esc_is_the_esc_key() {
+        let mut pending = vec![ESC];
+        assert!(parse(&mut pending).is_empty());
+        assert_eq!(flush(&mut pending), vec![key(KeyEvent::Esc)]);
+        assert!(pending.is_empty());
+
+        let mut pending = b"\x1b\x1b[B".to_vec();
+        assert_eq!(
+            parse(&mut pending),
+            vec![key(KeyEvent::Esc), key(KeyEvent::Down)]
+        );
+    }
+
+    #[test]
+    fn flush_splits_an_incomplete_sequence() {
+        let mut pending = b"\x1b[1".to_vec();
+        assert_eq!(
+            flush(&mut pending),
+            vec![
+                key(KeyEvent::Esc),
+                key(KeyEvent::Char('[')),
+                key(KeyEvent::Char('1'))
+            ]
+        );
+        assert!(pending.is_empty());
+    }
+}
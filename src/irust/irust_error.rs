@@ -40,13 +40,13 @@ impl From<crossterm::ErrorKind> for IRustError {
     }
 }
 
-impl ToString for IRustError {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for IRustError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
-            IoError(e) => e.to_string(),
-            CrosstermError(e) => e.to_string(),
-            Custom(e) => e.to_string(),
-            RacerDisabled => "Racer is disabled".to_string(),
+            IoError(e) => write!(f, "{}", e),
+            CrosstermError(e) => write!(f, "{}", e),
+            Custom(e) => write!(f, "{}", e),
+            RacerDisabled => write!(f, "Racer is disabled"),
         }
     }
 }
@@ -0,0 +1,137 @@
+use crate::irust::input::Input;
+use crate::irust::{IRust, IRustError};
+use crossterm::{ClearType, Color, InputEvent, KeyEvent};
+use std::io::Read;
+use std::process::{Child, Output};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+const SPINNER: [char; 4] = ['\\', '|', '/', '-'];
+// don't show progress for jobs that finish quickly
+const SPINNER_DELAY: Duration = Duration::from_millis(300);
+const TICK: Duration = Duration::from_millis(80);
+// set on SIGINT or Ctrl-c, the running job is killed
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+}
+
+/// Outside of raw mode (accessible mode) Ctrl-c sends SIGINT, it interrupts the running job
+/// instead of ending the session
+pub fn catch_interrupts() {
+    #[cfg(unix)]
+    {
+        use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+        extern "C" fn on_interrupt(_: nix::libc::c_int) {
+            interrupt();
+        }
+        let interrupt = SigAction::new(
+            SigHandler::Handler(on_interrupt),
+            SaFlags::SA_RESTART,
+            SigSet::empty(),
+        );
+        let _ = unsafe { sigaction(Signal::SIGINT, &interrupt) };
+    }
+}
+
+impl IRust {
+    /// Wait for a cargo job while still handling input,
+    /// Ctrl-c (or SIGINT) kills the job, other key events are kept for later
+    pub fn wait_job(&mut self, mut job: Child, msg: &str) -> Result<Output, IRustError> {
+        let stdout = job.stdout.take().map(read_in_background);
+        let stderr = job.stderr.take().map(read_in_background);
+
+        let status = match self.wait_until(msg, || Ok(job.try_wait()?))? {
+            Some(status) => status,
+            None => {
+                let _ = job.kill();
+                let _ = job.wait();
+                return Err(IRustError::Custom("Interrupted".to_string()));
+            }
+        };
+
+        let join = |output: Option<std::thread::JoinHandle<Vec<u8>>>| {
+            output
+                .and_then(|output| output.join().ok())
+                .unwrap_or_default()
+        };
+        Ok(Output {
+            status,
+            stdout: join(stdout),
+            stderr: join(stderr),
+        })
+    }
+
+    /// Poll `done` until it returns a value, showing the progress and keeping the key events
+    /// for later, None if Ctrl-c (or SIGINT) interrupted the wait
+    pub fn wait_until<T>(
+        &mut self,
+        msg: &str,
+        mut done: impl FnMut() -> Result<Option<T>, IRustError>,
+    ) -> Result<Option<T>, IRustError> {
+        let start = Instant::now();
+        let pos = self.cursor.pos.current_pos;
+        let mut tick = 0;
+        // an interrupt from before the job doesn't concern it
+        INTERRUPTED.store(false, Ordering::Relaxed);
+
+        let value = loop {
+            if let Some(value) = done()? {
+                break Some(value);
+            }
+            if INTERRUPTED.swap(false, Ordering::Relaxed) {
+                break None;
+            }
+
+            while let Ok(event) = self.input.try_recv() {
+                match event {
+                    // it already interrupted the job
+                    Input::Event(InputEvent::Keyboard(KeyEvent::Ctrl('c'))) => (),
+                    // resizes are handled after the job too
+                    event => self.pending_input.push_back(event),
+                }
+            }
+
+            if start.elapsed() >= SPINNER_DELAY && !self.options.accessible {
+                self.raw_terminal.set_fg(Color::Cyan)?;
+                self.write_str_at(
+                    &format!(
+                        " {} [{}] {}s",
+                        msg,
+                        SPINNER[tick % SPINNER.len()],
+                        start.elapsed().as_secs()
+                    ),
+                    0,
+                    pos.1,
+                )?;
+                self.raw_terminal.reset_color()?;
+                tick += 1;
+            }
+
+            std::thread::sleep(TICK);
+        };
+
+        self.clear_job_progress(pos)?;
+        Ok(value)
+    }
+
+    fn clear_job_progress(&mut self, pos: (usize, usize)) -> Result<(), IRustError> {
+        if self.options.accessible {
+            return Ok(());
+        }
+        self.cursor.goto(0, pos.1);
+        self.raw_terminal.clear(ClearType::CurrentLine)?;
+        self.cursor.goto(pos.0, pos.1);
+        Ok(())
+    }
+}
+
+// a full pipe would block the job forever, so drain it concurrently
+fn read_in_background<R: Read + Send + 'static>(mut out: R) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buffer = vec![];
+        let _ = out.read_to_end(&mut buffer);
+        buffer
+    })
+}
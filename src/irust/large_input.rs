@@ -0,0 +1,88 @@
+use crate::irust::input::Input;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+use crossterm::{ClearType, InputEvent, KeyEvent};
+
+// characters of a paste inserted before the input is drawn again
+pub const PASTE_CHUNK: usize = 4096;
+
+impl IRust {
+    /// The next character event if it's already waiting, other events are kept for the main loop
+    pub fn next_pending_char(&mut self) -> Option<char> {
+        if let Some(Input::Event(InputEvent::Keyboard(KeyEvent::Char(c)))) =
+            self.pending_input.front()
+        {
+            let c = *c;
+            self.pending_input.pop_front();
+            return Some(c);
+        }
+        // keep the events order
+        if !self.pending_input.is_empty() {
+            return None;
+        }
+        match self.input.try_recv() {
+            Ok(Input::Event(InputEvent::Keyboard(KeyEvent::Char(c)))) => Some(c),
+            Ok(event) => {
+                self.pending_input.push_back(event);
+                None
+            }
+            Err(_) => None,
+        }
+    }
+
+    /// The input has `input_max_lines` lines, the next lines are dropped with a warning
+    pub fn input_is_full(&mut self) -> bool {
+        if self.input_lines() < self.options.input_max_lines {
+            self.input_full_warned = false;
+            return false;
+        }
+        if !self.input_full_warned {
+            self.input_full_warned = true;
+            let _ = self.warn_input_full();
+        }
+        true
+    }
+
+    /// Above `highlight_max_lines` lines the input is drawn in one color, highlighting it
+    /// on every keystroke would make typing lag
+    pub fn plain_input(&self) -> Option<Printer> {
+        if self.input_lines() <= self.options.highlight_max_lines {
+            return None;
+        }
+        let mut printer = Printer::default();
+        for line in self.buffer.to_string().split('\n') {
+            printer.push(PrinterItem::new(
+                line.to_owned(),
+                PrinterItemType::Custom(self.options.input_color),
+            ));
+            printer.add_new_line(1);
+        }
+        printer.pop();
+        Some(printer)
+    }
+
+    fn input_lines(&self) -> usize {
+        self.buffer.iter().filter(|c| **c == '\n').count() + 1
+    }
+
+    fn warn_input_full(&mut self) -> Result<(), IRustError> {
+        let mut outputs = Printer::new(PrinterItem::new(
+            format!(
+                "IRust: the input is limited to {} lines (input_max_lines), the next ones are dropped, use :load for bigger scripts",
+                self.options.input_max_lines
+            ),
+            PrinterItemType::Warn,
+        ));
+        outputs.add_new_line(1);
+
+        // the notice is written where the input starts, then the input is written again under it
+        self.cursor.hide();
+        self.cursor.goto_start();
+        self.raw_terminal.clear(ClearType::FromCursorDown)?;
+        self.print_output(outputs)?;
+        self.print_input()?;
+        self.goto_buffer_pos();
+        self.cursor.show();
+        Ok(())
+    }
+}
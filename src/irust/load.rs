@@ -0,0 +1,283 @@
+use super::cargo_cmds::cargo_fmt;
+use super::cmd_args::CmdArgs;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+use crate::utils::{blank_comments_and_strings, remove_main};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+// the files a directory is loaded from, in this order, otherwise all of its `.rs` files are loaded
+const ENTRY_POINTS: [&str; 4] = ["main.rs", "lib.rs", "src/main.rs", "src/lib.rs"];
+
+impl IRust {
+    /// Load rust scripts and directories into the repl, exp: `:load script.rs utils/`, `:load ~/scripts/*.rs`
+    pub fn load_script(&mut self) -> Result<Printer, IRustError> {
+        let buffer = self.buffer.to_string();
+        let args = CmdArgs::parse("load", &buffer)?.into_vec();
+        if args.is_empty() {
+            return Err(IRustError::Custom("No script specified".to_string()));
+        }
+
+        let mut scripts = vec![];
+        let mut failed = vec![];
+        for arg in &args {
+            match expand_path(arg) {
+                Ok(paths) => scripts.extend(paths),
+                Err(e) => failed.push(format!("  {}: {}", arg, e)),
+            }
+        }
+        if !failed.is_empty() {
+            return Err(IRustError::Custom(format!(
+                "Nothing loaded, these paths failed:\n{}",
+                failed.join("\n")
+            )));
+        }
+        self.load_files(&scripts, &buffer)
+    }
+
+    /// The output of loading the `--preload` script, `None` without one
+    pub fn load_preload(&mut self) -> Option<Printer> {
+        let script = self.preload.take()?;
+        let outputs = self
+            .load_files(std::slice::from_ref(&script), "--preload")
+            .unwrap_or_else(|e| {
+                let mut outputs = Printer::new(PrinterItem::new(
+                    format!("Couldn't preload {}: {}", script.display(), e),
+                    PrinterItemType::Err,
+                ));
+                outputs.add_new_line(1);
+                outputs
+            });
+        Some(outputs)
+    }
+
+    // nothing is loaded if one of the scripts can't be read
+    fn load_files(&mut self, scripts: &[PathBuf], message: &str) -> Result<Printer, IRustError> {
+        let mut files = vec![];
+        for script in scripts {
+            if script.is_dir() {
+                files.extend(dir_scripts(script)?);
+            } else {
+                files.push(script.clone());
+            }
+        }
+
+        let mut code = vec![];
+        let mut kept_main = false;
+        for file in files {
+            let s = inline_modules(&file)?;
+            // Format script to make `remove_main` function work correctly
+            let s = cargo_fmt(&s)?;
+            let (s, kept) = remove_main(&s);
+            kept_main |= kept;
+            code.push(s);
+        }
+        for s in code {
+            self.repl.insert(s);
+        }
+        self.repl_changed(message)?;
+
+        let mut outputs = Printer::new(PrinterItem::new("Ok!".to_string(), PrinterItemType::Ok));
+        outputs.add_new_line(1);
+        if kept_main {
+            outputs.push(PrinterItem::new(
+                "IRust: main can't run as top-level statements, it's kept as `script_main()`"
+                    .to_string(),
+                PrinterItemType::Warn,
+            ));
+            outputs.add_new_line(1);
+        }
+
+        Ok(outputs)
+    }
+}
+
+/// The existing paths matching `arg`, after expanding a leading `~` and the `*` and `?` wildcards
+fn expand_path(arg: &str) -> Result<Vec<PathBuf>, String> {
+    let path = match arg.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => dirs::home_dir()
+            .ok_or_else(|| "home directory not found".to_string())?
+            .join(rest.trim_start_matches('/')),
+        _ => PathBuf::from(arg),
+    };
+
+    if !arg.contains(['*', '?']) {
+        return if path.exists() {
+            Ok(vec![path])
+        } else {
+            Err("not found".to_string())
+        };
+    }
+
+    let mut matches = vec![PathBuf::new()];
+    for component in path.components() {
+        let pattern = match component {
+            Component::Normal(pattern) => pattern.to_string_lossy(),
+            component => {
+                for path in matches.iter_mut() {
+                    path.push(component);
+                }
+                continue;
+            }
+        };
+        if !pattern.contains(['*', '?']) {
+            for path in matches.iter_mut() {
+                path.push(&*pattern);
+            }
+            continue;
+        }
+
+        let mut next = vec![];
+        for dir in &matches {
+            let entries = match fs::read_dir(if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir
+            }) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for entry in entries.filter_map(Result::ok) {
+                let name = entry.file_name().to_string_lossy().to_string();
+                // like shells, hidden files are only matched explicitly
+                if name.starts_with('.') && !pattern.starts_with('.') {
+                    continue;
+                }
+                if wildcard_match(&pattern, &name) {
+                    next.push(dir.join(name));
+                }
+            }
+        }
+        next.sort();
+        matches = next;
+    }
+
+    matches.retain(|path| path.exists());
+    if matches.is_empty() {
+        Err("no matching files".to_string())
+    } else {
+        Ok(matches)
+    }
+}
+
+// `*` matches any characters, `?` one character
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // the last `*` position in the pattern and the name position it was tried at
+    let mut star: Option<(usize, usize)> = None;
+    let (mut p, mut n) = (0, 0);
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            // the `*` takes one more character
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// The entry point of `dir`, or all of its `.rs` files sorted by name
+fn dir_scripts(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    if let Some(entry) = ENTRY_POINTS
+        .iter()
+        .map(|entry| dir.join(entry))
+        .find(|entry| entry.is_file())
+    {
+        return Ok(vec![entry]);
+    }
+
+    let mut scripts: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "rs"))
+        .collect();
+    if scripts.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No rust files in {}", dir.display()),
+        ));
+    }
+    scripts.sort();
+    Ok(scripts)
+}
+
+/// The content of `file` with its `mod name;` declarations replaced by inline modules
+/// holding the modules files, recursively
+fn inline_modules(file: &Path) -> io::Result<String> {
+    let script = fs::read_to_string(file)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", file.display(), e)))?;
+    let code = blank_comments_and_strings(&script);
+
+    let mut inlined = String::new();
+    for (line, code_line) in script.lines().zip(code.lines()) {
+        match mod_declaration(code_line) {
+            Some((visibility, name)) => {
+                let module = module_file(file, name)?;
+                inlined += &format!(
+                    "{}mod {} {{\n{}\n}}\n",
+                    visibility,
+                    name,
+                    inline_modules(&module)?
+                );
+            }
+            None => {
+                inlined += line;
+                inlined.push('\n');
+            }
+        }
+    }
+    Ok(inlined)
+}
+
+// `pub mod name;` => Some(("pub ", "name"))
+fn mod_declaration(code_line: &str) -> Option<(&str, &str)> {
+    let line = code_line.trim();
+    let declaration = line.strip_suffix(';')?;
+    let idx = declaration.find("mod ")?;
+    let (visibility, name) = (&declaration[..idx], declaration[idx + 4..].trim());
+    let valid_visibility = visibility.is_empty() || visibility.trim_end().starts_with("pub");
+    let valid_name = !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if valid_visibility && valid_name {
+        Some((visibility, name))
+    } else {
+        None
+    }
+}
+
+/// `name.rs` or `name/mod.rs` next to `file`, or in the `file` directory
+/// when `file` isn't a `main.rs`, `lib.rs` or `mod.rs`
+fn module_file(file: &Path, name: &str) -> io::Result<PathBuf> {
+    let parent = file.parent().unwrap_or_else(|| Path::new(""));
+    let is_root = file
+        .file_name()
+        .is_some_and(|f| f == "main.rs" || f == "lib.rs" || f == "mod.rs");
+    let dir = match file.file_stem() {
+        Some(stem) if !is_root => parent.join(stem),
+        _ => parent.to_owned(),
+    };
+
+    [
+        dir.join(format!("{}.rs", name)),
+        dir.join(name).join("mod.rs"),
+    ]
+    .iter()
+    .find(|path| path.is_file())
+    .cloned()
+    .ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Module `{}` of {} not found", name, file.display()),
+        )
+    })
+}
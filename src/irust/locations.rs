@@ -0,0 +1,113 @@
+use once_cell::sync::OnceCell;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// the config file given with `--config`
+static CONFIG_FILE: OnceCell<PathBuf> = OnceCell::new();
+
+pub fn set_config_file(path: PathBuf) {
+    let _ = CONFIG_FILE.set(path);
+}
+
+/// The config file the options are read from and written to
+pub fn config_file() -> Option<PathBuf> {
+    match CONFIG_FILE.get() {
+        Some(path) => Some(path.clone()),
+        None => config_dir().map(|dir| dir.join("config")),
+    }
+}
+
+/// A config installed by the system (in `$XDG_CONFIG_DIRS`, /etc/xdg by default), used
+/// as long as the user has none
+pub fn system_config_file() -> Option<PathBuf> {
+    if CONFIG_FILE.get().is_some() || cfg!(any(windows, target_os = "macos")) {
+        return None;
+    }
+    let dirs = env::var_os("XDG_CONFIG_DIRS")
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/etc/xdg".into());
+    env::split_paths(&dirs)
+        .filter(|dir| dir.is_absolute())
+        .map(|dir| dir.join("irust/config"))
+        .find(|config| config.is_file())
+}
+
+// $XDG_CONFIG_HOME/irust, ~/Library/Application Support/irust on macOS and
+// AppData\Roaming\irust on Windows
+fn config_dir() -> Option<PathBuf> {
+    let dir = match xdg_dir("XDG_CONFIG_HOME") {
+        Some(dir) => dir,
+        None if cfg!(target_os = "macos") => dirs::data_dir()?,
+        None => dirs::config_dir()?,
+    };
+    Some(dir.join("irust"))
+}
+
+/// Compiled dependencies, they can be deleted at any time
+pub fn cache_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CACHE_HOME")
+        .or_else(dirs::cache_dir)
+        .map(|dir| dir.join("irust"))
+}
+
+/// The history, it's kept across sessions but is not configuration
+pub fn state_dir() -> Option<PathBuf> {
+    let dir = match xdg_dir("XDG_STATE_HOME") {
+        Some(dir) => dir,
+        None if cfg!(any(windows, target_os = "macos")) => dirs::data_local_dir()?,
+        None => dirs::home_dir()?.join(".local/state"),
+    };
+    Some(dir.join("irust"))
+}
+
+// the spec says relative paths are invalid and should be ignored
+fn xdg_dir(var: &str) -> Option<PathBuf> {
+    if cfg!(windows) {
+        return None;
+    }
+    env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+}
+
+/// Move the files left in the old locations: the config from ~/Library/Preferences on macOS
+/// and the history from the cache dir
+pub fn migrate() {
+    if CONFIG_FILE.get().is_none() {
+        if let (Some(old), Some(new)) = (dirs::config_dir(), config_file()) {
+            move_entry(&old.join("irust/config"), &new);
+        }
+    }
+    if let (Some(old), Some(new)) = (dirs::cache_dir(), state_dir()) {
+        for name in ["history", "projects"].iter() {
+            move_entry(&old.join("irust").join(name), &new.join(name));
+        }
+    }
+}
+
+// nothing is overwritten, a file that can't be moved is copied and the old one kept
+fn move_entry(old: &Path, new: &Path) {
+    if old == new || !old.exists() || new.exists() {
+        return;
+    }
+    if let Some(dir) = new.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    if fs::rename(old, new).is_err() {
+        let _ = copy_entry(old, new);
+    }
+}
+
+fn copy_entry(old: &Path, new: &Path) -> io::Result<()> {
+    if !old.is_dir() {
+        return fs::copy(old, new).map(|_| ());
+    }
+    fs::create_dir_all(new)?;
+    for entry in fs::read_dir(old)? {
+        let entry = entry?;
+        copy_entry(&entry.path(), &new.join(entry.file_name()))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use super::cargo_cmds::{cargo_manifest_error, write_cargo_fragments, CARGO_FRAGMENTS};
+use super::cmd_args::CmdArgs;
+use super::highlight::highlight;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+
+impl IRust {
+    /// Add sections to the repl Cargo.toml for the session, exp: `:cargo [profile.dev] opt-level = 1`
+    pub fn cargo(&mut self) -> Result<Printer, IRustError> {
+        let args = CmdArgs::parse_code("cargo", &self.buffer.to_string());
+        let fragment = args.code();
+        let mut fragments = CARGO_FRAGMENTS.lock().unwrap_or_else(|e| e.into_inner());
+
+        match fragment {
+            "" if fragments.is_empty() => {
+                return Ok(Printer::new(PrinterItem::new(
+                    "No Cargo.toml additions".to_string(),
+                    PrinterItemType::Warn,
+                )))
+            }
+            "" => {
+                let mut outputs = highlight(&fragments.join("\n"));
+                outputs.add_new_line(1);
+                return Ok(outputs);
+            }
+            "reset" => fragments.clear(),
+            fragment => {
+                fragments.push(
+                    fragment
+                        .lines()
+                        .map(split_table_header)
+                        .collect::<Vec<String>>()
+                        .join("\n"),
+                );
+            }
+        }
+        // released before writing the manifest
+        drop(fragments);
+        write_cargo_fragments()?;
+
+        if let Some(error) = cargo_manifest_error()? {
+            CARGO_FRAGMENTS
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .pop();
+            write_cargo_fragments()?;
+            return Err(IRustError::Custom(format!(
+                "Invalid Cargo.toml addition, it's not kept:\n{}",
+                error
+            )));
+        }
+
+        let mut outputs = Printer::new(PrinterItem::new("Ok!".to_string(), PrinterItemType::Ok));
+        outputs.add_new_line(1);
+        Ok(outputs)
+    }
+}
+
+// `[profile.dev] opt-level = 1` => the table header on its own line, as TOML requires
+fn split_table_header(line: &str) -> String {
+    let line = line.trim();
+    let end = if line.starts_with("[[") {
+        line.find("]]").map(|idx| idx + 2)
+    } else if line.starts_with('[') {
+        line.find(']').map(|idx| idx + 1)
+    } else {
+        None
+    };
+    match end {
+        Some(end) if !line[end..].trim().is_empty() => {
+            format!("{}\n{}", &line[..end], line[end..].trim())
+        }
+        _ => line.to_owned(),
+    }
+}
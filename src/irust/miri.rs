@@ -0,0 +1,102 @@
+use super::cargo_cmds::{cargo_miri_run, MIRI};
+use super::cmd_args::CmdArgs;
+use crate::irust::format::format_eval_output;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+use std::process::Output;
+use std::sync::atomic::Ordering;
+
+impl IRust {
+    pub fn miri(&mut self) -> Result<Printer, IRustError> {
+        // exp: :miri unsafe { *std::ptr::null::<u8>() }, :miri on, :miri off, :miri
+        let args = CmdArgs::parse_code("miri", &self.buffer.to_string());
+        let expression = args.expression();
+
+        match expression {
+            "" => {
+                let state = if MIRI.load(Ordering::Relaxed) {
+                    "on"
+                } else {
+                    "off"
+                };
+                return Ok(message(format!("Miri: {}", state)));
+            }
+            "on" | "off" => {
+                MIRI.store(expression == "on", Ordering::Relaxed);
+                return Ok(message(format!("Miri: {}", expression)));
+            }
+            _ => (),
+        }
+
+        let statement = self.repl.eval_statement(expression);
+        let job = self
+            .repl
+            .eval_in_tmp_repl(statement, || Ok(cargo_miri_run(true)?))?;
+        let output = self.wait_job(job, "Running miri")?;
+        let mut outputs = format_miri_output(output)?;
+        outputs.add_new_line(1);
+
+        Ok(outputs)
+    }
+}
+
+/// The evaluation output, or the miri error (undefined behavior) if there is one,
+/// the program output is on stdout and the miri diagnostics on stderr
+pub fn format_miri_output(output: Output) -> Result<Printer, IRustError> {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if output.status.success() {
+        return Ok(format_eval_output(&stdout));
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.contains("no such command") || stderr.contains("is not installed") {
+        return Err(IRustError::Custom(
+            "Miri is not installed, use rustup +nightly component add miri".to_string(),
+        ));
+    }
+    // cargo status lines come before the first diagnostic,
+    // the diagnostics are colored for the compile errors
+    let error = strip_colors(&stderr)
+        .lines()
+        .skip_while(|line| !line.starts_with("error"))
+        .collect::<Vec<&str>>()
+        .join("\n");
+    // compile errors are formatted like the normal evaluations ones
+    if !error.starts_with("error: Undefined Behavior") && !error.starts_with("error: unsupported") {
+        return Ok(format_eval_output(&stderr));
+    }
+
+    let mut outputs = if stdout.trim().is_empty() {
+        Printer::default()
+    } else {
+        let mut outputs = format_eval_output(&stdout);
+        outputs.add_new_line(1);
+        outputs
+    };
+    outputs.push(PrinterItem::new(error, PrinterItemType::Err));
+    Ok(outputs)
+}
+
+// exp: \x1b[1m\x1b[38;5;9merror\x1b[0m
+fn strip_colors(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+fn message(msg: String) -> Printer {
+    let mut outputs = Printer::new(PrinterItem::new(msg, PrinterItemType::Ok));
+    outputs.add_new_line(1);
+    outputs
+}
@@ -1,14 +1,58 @@
-use crate::irust::IRust;
+use crate::irust::locations;
+use crate::irust::{IRust, INPUT_START_COL};
 use crate::utils::VecTools;
 use crossterm::Color;
 use std::io::Write;
 mod parser;
 use parser::RacerEnabled;
 
+// `keystroke`: suggestions are shown while typing, `tab`: only when Tab is pressed
+pub const RACER_TRIGGERS: [&str; 2] = ["keystroke", "tab"];
+// the options `:set` can switch
+pub const FLAGS: [&str; 9] = [
+    "autofmt",
+    "completion_session",
+    "completion_history",
+    "completion_snippets",
+    "input_status",
+    "persist_let_bindings",
+    "rerun_on_change",
+    "show_timings",
+    "show_types",
+];
+
+// the options `color_mut` knows, in the order `:colors` shows them
+pub const COLOR_OPTIONS: [&str; 21] = [
+    "ok_color",
+    "eval_color",
+    "irust_color",
+    "irust_warn_color",
+    "out_color",
+    "shell_color",
+    "err_color",
+    "input_color",
+    "insert_color",
+    "watch_color",
+    "watch_changed_color",
+    "line_numbers_color",
+    "semantic_type_color",
+    "semantic_function_color",
+    "semantic_macro_color",
+    "semantic_mutable_color",
+    "welcome_color",
+    "racer_inline_suggestion_color",
+    "racer_suggestions_table_color",
+    "racer_selected_suggestion_color",
+    "racer_suggestion_docs_color",
+];
+
 #[derive(Clone)]
 pub struct Options {
     add_irust_cmd_to_history: bool,
     add_shell_cmd_to_history: bool,
+    pub project_history: bool,
+    pub history_ignore_space: bool,
+    pub history_exclude: String,
     pub ok_color: Color,
     pub eval_color: Color,
     pub irust_color: Color,
@@ -18,6 +62,13 @@ pub struct Options {
     pub err_color: Color,
     pub input_color: Color,
     pub insert_color: Color,
+    pub watch_color: Color,
+    pub watch_changed_color: Color,
+    pub line_numbers_color: Color,
+    pub semantic_type_color: Color,
+    pub semantic_function_color: Color,
+    pub semantic_macro_color: Color,
+    pub semantic_mutable_color: Color,
     pub welcome_msg: String,
     pub welcome_color: Color,
     pub enable_racer: bool,
@@ -25,6 +76,44 @@ pub struct Options {
     pub racer_suggestions_table_color: Color,
     pub racer_selected_suggestion_color: Color,
     pub racer_max_suggestions: usize,
+    pub racer_suggestion_docs_color: Color,
+    pub racer_trigger: String,
+    pub racer_min_chars: usize,
+    pub racer_delay: u64,
+    pub completion_session: bool,
+    pub completion_history: bool,
+    pub completion_snippets: bool,
+    pub persist_let_bindings: bool,
+    pub live_check: bool,
+    pub rerun_on_change: bool,
+    pub git_versioning: bool,
+    pub tab_width: usize,
+    pub word_wrap: bool,
+    pub horizontal_scroll: bool,
+    pub line_numbers: bool,
+    pub rust_analyzer: bool,
+    pub accessible: bool,
+    pub output_max_lines: usize,
+    pub output_max_bytes: usize,
+    pub prefer_display: bool,
+    pub sandbox: String,
+    pub sandbox_image: String,
+    pub memory_limit: usize,
+    pub cpu_time_limit: usize,
+    pub cells: bool,
+    pub body_pane: bool,
+    pub body_pane_width: usize,
+    pub pinned_input: bool,
+    pub show_timings: bool,
+    pub show_types: bool,
+    pub validate_insertions: bool,
+    pub autofmt: bool,
+    pub input_max_lines: usize,
+    pub eval_template: String,
+    pub highlight_max_lines: usize,
+    pub input_status: bool,
+    // invalid values found in the config file, shown at startup
+    pub config_errors: Vec<String>,
 }
 
 impl Default for Options {
@@ -33,6 +122,9 @@ impl Default for Options {
             // [Histroy]
             add_irust_cmd_to_history: false,
             add_shell_cmd_to_history: false,
+            project_history: false,
+            history_ignore_space: false,
+            history_exclude: String::new(),
 
             // [Colors]
             ok_color: Color::Blue,
@@ -44,6 +136,13 @@ impl Default for Options {
             err_color: Color::DarkRed,
             input_color: Color::Yellow,
             insert_color: Color::White,
+            watch_color: Color::DarkMagenta,
+            watch_changed_color: Color::Yellow,
+            line_numbers_color: Color::DarkGrey,
+            semantic_type_color: Color::DarkCyan,
+            semantic_function_color: Color::Blue,
+            semantic_macro_color: Color::Magenta,
+            semantic_mutable_color: Color::DarkYellow,
 
             // [Welcome]
             welcome_msg: String::new(),
@@ -56,6 +155,45 @@ impl Default for Options {
             racer_suggestions_table_color: Color::Green,
             racer_selected_suggestion_color: Color::DarkRed,
             racer_max_suggestions: 5,
+            racer_suggestion_docs_color: Color::DarkGrey,
+            racer_trigger: "keystroke".to_string(),
+            racer_min_chars: 1,
+            racer_delay: 50,
+            completion_session: true,
+            completion_history: true,
+            completion_snippets: true,
+
+            // [Repl]
+            persist_let_bindings: false,
+            live_check: false,
+            rerun_on_change: false,
+            git_versioning: false,
+            tab_width: 4,
+            word_wrap: false,
+            horizontal_scroll: false,
+            line_numbers: false,
+            rust_analyzer: false,
+            accessible: false,
+            output_max_lines: 500,
+            output_max_bytes: 50_000,
+            prefer_display: false,
+            sandbox: "none".to_string(),
+            sandbox_image: "debian:stable-slim".to_string(),
+            memory_limit: 0,
+            cpu_time_limit: 0,
+            cells: false,
+            body_pane: false,
+            body_pane_width: 40,
+            pinned_input: false,
+            show_timings: false,
+            show_types: false,
+            validate_insertions: false,
+            autofmt: false,
+            input_max_lines: 10_000,
+            eval_template: String::new(),
+            highlight_max_lines: 1000,
+            input_status: false,
+            config_errors: Vec::new(),
         }
     }
 }
@@ -65,25 +203,30 @@ impl Options {
         if let Some(config_path) = Options::config_path() {
             match std::fs::File::open(&config_path) {
                 Ok(config_file) => Options::parse(config_file),
-                Err(_) => Options::create_config(config_path, RacerEnabled::True),
+                Err(_) => match locations::system_config_file() {
+                    Some(system_config) => Options::parse(std::fs::File::open(system_config)?),
+                    None => Options::create_config(config_path, RacerEnabled::True),
+                },
             }
         } else {
             Ok(Options::default())
         }
     }
 
+    /// Use `config_path` instead of the default config file, exp: `--config`
+    pub fn set_config_path(config_path: std::path::PathBuf) {
+        locations::set_config_file(config_path);
+    }
+
     pub fn reset_config(config_path: std::path::PathBuf) {
         let _ = Options::create_config(config_path, RacerEnabled::True);
     }
 
     pub fn config_path() -> Option<std::path::PathBuf> {
-        let config_dir = match dirs::config_dir() {
-            Some(dir) => dir.join("irust"),
-            None => return None,
-        };
-
-        let _ = std::fs::create_dir(&config_dir);
-        let config_path = config_dir.join("config");
+        let config_path = locations::config_file()?;
+        if let Some(config_dir) = config_path.parent() {
+            let _ = std::fs::create_dir_all(config_dir);
+        }
 
         Some(config_path)
     }
@@ -101,6 +244,31 @@ impl Options {
         Ok(Options::default())
     }
 
+    pub fn flag_mut(&mut self, option: &str) -> Option<&mut bool> {
+        match option {
+            "autofmt" => Some(&mut self.autofmt),
+            "completion_session" => Some(&mut self.completion_session),
+            "completion_history" => Some(&mut self.completion_history),
+            "completion_snippets" => Some(&mut self.completion_snippets),
+            "input_status" => Some(&mut self.input_status),
+            "persist_let_bindings" => Some(&mut self.persist_let_bindings),
+            "rerun_on_change" => Some(&mut self.rerun_on_change),
+            "show_timings" => Some(&mut self.show_timings),
+            "show_types" => Some(&mut self.show_types),
+            _ => None,
+        }
+    }
+
+    /// Characters of an input row before it wraps, the rows are scrolled instead with
+    /// `horizontal_scroll`
+    pub fn input_row_chars(&self, width: usize) -> usize {
+        if self.horizontal_scroll {
+            usize::MAX
+        } else {
+            width - INPUT_START_COL
+        }
+    }
+
     pub fn disable_racer() -> std::io::Result<()> {
         if let Some(config_path) = Options::config_path() {
             Options::create_config(config_path, RacerEnabled::False)?;
@@ -121,7 +289,18 @@ impl Options {
         }
     }
 
-    fn str_to_color(value: &str) -> Result<Color, &str> {
+    /// A color name (case insensitive) or a hex `#rrggbb` value
+    fn str_to_color(value: &str) -> Result<Color, String> {
+        if let Some(hex) = value.strip_prefix('#') {
+            let channel = |idx: usize| u8::from_str_radix(hex.get(idx..idx + 2)?, 16).ok();
+            return match (hex.len(), channel(0), channel(2), channel(4)) {
+                (6, Some(r), Some(g), Some(b)) => Ok(Color::Rgb { r, g, b }),
+                _ => Err(format!(
+                    "invalid hex color `{}`, expected #rrggbb, exp: #1e90ff",
+                    value
+                )),
+            };
+        }
         match value.to_lowercase().as_ref() {
             "black" => Ok(Color::Black),
             "red" => Ok(Color::Red),
@@ -137,37 +316,80 @@ impl Options {
             "cyan" => Ok(Color::Cyan),
             "darkcyan" => Ok(Color::DarkCyan),
             "grey" => Ok(Color::Grey),
+            "darkgrey" => Ok(Color::DarkGrey),
             "white" => Ok(Color::White),
-            value => {
-                eprintln!("Unknown option value: {}", value);
-                Err("Unknown option value")
-            }
+            _ => Err(format!(
+                "unknown color `{}`, expected a color name (Red, DarkBlue, Grey..) or #rrggbb",
+                value
+            )),
         }
     }
 
+    /// Set a color option, an invalid value or option is kept in `config_errors`
+    fn set_color(&mut self, option: &str, value: &str) {
+        let error = match (Options::str_to_color(value), self.color_mut(option)) {
+            (Ok(value), Some(color)) => {
+                *color = value;
+                return;
+            }
+            (Err(e), Some(_)) => format!("{}: {}", option, e),
+            (_, None) => format!("unknown config option `{}`", option),
+        };
+        self.config_errors.push(error);
+    }
+
+    pub fn color_mut(&mut self, option: &str) -> Option<&mut Color> {
+        let color = match option {
+            "ok_color" => &mut self.ok_color,
+            "eval_color" => &mut self.eval_color,
+            "irust_color" => &mut self.irust_color,
+            "irust_warn_color" => &mut self.irust_warn_color,
+            "out_color" => &mut self.out_color,
+            "shell_color" => &mut self.shell_color,
+            "err_color" => &mut self.err_color,
+            "input_color" => &mut self.input_color,
+            "insert_color" => &mut self.insert_color,
+            "watch_color" => &mut self.watch_color,
+            "watch_changed_color" => &mut self.watch_changed_color,
+            "line_numbers_color" => &mut self.line_numbers_color,
+            "semantic_type_color" => &mut self.semantic_type_color,
+            "semantic_function_color" => &mut self.semantic_function_color,
+            "semantic_macro_color" => &mut self.semantic_macro_color,
+            "semantic_mutable_color" => &mut self.semantic_mutable_color,
+            "welcome_color" => &mut self.welcome_color,
+            "racer_inline_suggestion_color" => &mut self.racer_inline_suggestion_color,
+            "racer_suggestions_table_color" => &mut self.racer_suggestions_table_color,
+            "racer_selected_suggestion_color" => &mut self.racer_selected_suggestion_color,
+            "racer_suggestion_docs_color" => &mut self.racer_suggestion_docs_color,
+            _ => return None,
+        };
+        Some(color)
+    }
+
     fn get_section(lines: &[String], section_name: String) -> Vec<(String, String)> {
-        let sec_start = match VecTools::index(lines, &section_name).get(0) {
+        // a missing section keeps its defaults, exp: a config written by an older version
+        let sec_start = match VecTools::index(lines, &section_name).first() {
             Some(idx) => *idx,
-            None => {
-                eprintln!("Section {} not found", section_name);
-                return Vec::new();
-            }
+            None => return Vec::new(),
         };
 
         let sec_end = VecTools::index(lines, "[")
             .into_iter()
             .find(|elem| *elem > sec_start)
-            .unwrap_or_else(|| lines.len());
+            .unwrap_or(lines.len());
 
         lines[sec_start + 1..sec_end]
             .iter()
             .filter_map(|line| {
-                let lines_part = line.split('=').map(str::trim).collect::<Vec<&str>>();
-                if lines_part.len() == 2 {
-                    Some((lines_part[0].to_string(), lines_part[1].to_string()))
-                } else {
-                    eprintln!("Unknown line: {}", line);
-                    None
+                // values can contain `=`, exp: history_exclude = token=\w+
+                match line.split_once('=') {
+                    Some((option, value)) => {
+                        Some((option.trim().to_string(), value.trim().to_string()))
+                    }
+                    None => {
+                        eprintln!("Unknown line: {}", line);
+                        None
+                    }
                 }
             })
             .collect()
@@ -1,4 +1,6 @@
-use super::Options;
+use super::{Options, RACER_TRIGGERS};
+use crate::irust::cargo_cmds::SANDBOXES;
+use crate::irust::repl::EvalTemplate;
 use std::io::Read;
 
 impl Options {
@@ -25,100 +27,201 @@ impl Options {
                 ("add_shell_cmd_to_history", value) => {
                     options.add_shell_cmd_to_history = Options::str_to_bool(&value);
                 }
+                ("project_history", value) => {
+                    options.project_history = Options::str_to_bool(&value);
+                }
+                ("history_ignore_space", value) => {
+                    options.history_ignore_space = Options::str_to_bool(&value);
+                }
+                ("history_exclude", value) => match regex::Regex::new(&value) {
+                    Ok(_) => options.history_exclude = value,
+                    Err(e) => options
+                        .config_errors
+                        .push(format!("history_exclude: invalid regex: {}", e)),
+                },
                 _ => eprintln!("Unknown config option: {} {}", option, value),
             }
         }
 
         for (option, value) in Options::get_section(&lines, "[Colors]".to_string()).into_iter() {
-            match (option.to_lowercase().as_ref(), value.clone()) {
-                ("ok_color", value) => {
-                    if let Ok(value) = Options::str_to_color(&value) {
-                        options.ok_color = value;
+            options.set_color(&option.to_lowercase(), &value);
+        }
+
+        for (option, value) in Options::get_section(&lines, "[Welcome]".to_string()).into_iter() {
+            match (option.to_lowercase().as_str(), value.clone()) {
+                ("welcome_msg", value) => {
+                    if !value.is_empty() {
+                        options.welcome_msg = value;
                     }
                 }
-                ("eval_color", value) => {
-                    if let Ok(value) = Options::str_to_color(&value) {
-                        options.eval_color = value;
-                    }
+                ("welcome_color", value) => options.set_color("welcome_color", &value),
+                _ => eprintln!("Unknown config option: {} {}", option, value),
+            }
+        }
+
+        // the other completion sources follow `enable_racer` unless they are set
+        let mut completion_session = None;
+        let mut completion_history = None;
+        let mut completion_snippets = None;
+        for (option, value) in Options::get_section(&lines, "[Racer]".to_string()).into_iter() {
+            match (option.to_lowercase().as_str(), value.clone()) {
+                ("enable_racer", value) => {
+                    options.enable_racer = Options::str_to_bool(&value);
                 }
-                ("irust_color", value) => {
-                    if let Ok(value) = Options::str_to_color(&value) {
-                        options.irust_color = value;
+                ("racer_max_suggestions", value) => {
+                    if let Ok(value) = value.parse() {
+                        options.racer_max_suggestions = value;
                     }
                 }
-                ("irust_warn_color", value) => {
-                    if let Ok(value) = Options::str_to_color(&value) {
-                        options.irust_warn_color = value;
+                ("racer_trigger", value) => {
+                    if RACER_TRIGGERS.contains(&value.as_str()) {
+                        options.racer_trigger = value;
+                    } else {
+                        options.config_errors.push(format!(
+                            "racer_trigger: unknown trigger `{}`, expected one of: {}",
+                            value,
+                            RACER_TRIGGERS.join(", ")
+                        ));
                     }
                 }
-                ("shell_color", value) => {
-                    if let Ok(value) = Options::str_to_color(&value) {
-                        options.shell_color = value;
+                ("racer_min_chars", value) => {
+                    if let Ok(value) = value.parse() {
+                        options.racer_min_chars = value;
                     }
                 }
-                ("err_color", value) => {
-                    if let Ok(value) = Options::str_to_color(&value) {
-                        options.err_color = value;
+                ("racer_delay", value) => {
+                    if let Ok(value) = value.parse() {
+                        options.racer_delay = value;
                     }
                 }
-                ("out_color", value) => {
-                    if let Ok(value) = Options::str_to_color(&value) {
-                        options.out_color = value;
-                    }
+                ("completion_session", value) => {
+                    completion_session = Some(Options::str_to_bool(&value));
                 }
-                ("input_color", value) => {
-                    if let Ok(value) = Options::str_to_color(&value) {
-                        options.input_color = value;
-                    }
+                ("completion_history", value) => {
+                    completion_history = Some(Options::str_to_bool(&value));
                 }
-                ("insert_color", value) => {
-                    if let Ok(value) = Options::str_to_color(&value) {
-                        options.insert_color = value;
-                    }
+                ("completion_snippets", value) => {
+                    completion_snippets = Some(Options::str_to_bool(&value));
+                }
+                (option, value) if option.starts_with("racer_") && option.ends_with("_color") => {
+                    options.set_color(option, &value)
                 }
                 _ => eprintln!("Unknown config option: {} {}", option, value),
             }
         }
 
-        for (option, value) in Options::get_section(&lines, "[Welcome]".to_string()).into_iter() {
+        options.completion_session = completion_session.unwrap_or(options.enable_racer);
+        options.completion_history = completion_history.unwrap_or(options.enable_racer);
+        options.completion_snippets = completion_snippets.unwrap_or(options.enable_racer);
+
+        for (option, value) in Options::get_section(&lines, "[Repl]".to_string()).into_iter() {
             match (option.to_lowercase().as_str(), value.clone()) {
-                ("welcome_msg", value) => {
-                    if !value.is_empty() {
-                        options.welcome_msg = value;
+                ("persist_let_bindings", value) => {
+                    options.persist_let_bindings = Options::str_to_bool(&value);
+                }
+                ("live_check", value) => {
+                    options.live_check = Options::str_to_bool(&value);
+                }
+                ("rerun_on_change", value) => {
+                    options.rerun_on_change = Options::str_to_bool(&value);
+                }
+                ("git_versioning", value) => {
+                    options.git_versioning = Options::str_to_bool(&value);
+                }
+                ("tab_width", value) => {
+                    if let Ok(value) = value.parse() {
+                        options.tab_width = value;
                     }
                 }
-                ("welcome_color", value) => {
-                    if let Ok(value) = Options::str_to_color(&value) {
-                        options.welcome_color = value;
+                ("word_wrap", value) => {
+                    options.word_wrap = Options::str_to_bool(&value);
+                }
+                ("horizontal_scroll", value) => {
+                    options.horizontal_scroll = Options::str_to_bool(&value);
+                }
+                ("line_numbers", value) => {
+                    options.line_numbers = Options::str_to_bool(&value);
+                }
+                ("rust_analyzer", value) => {
+                    options.rust_analyzer = Options::str_to_bool(&value);
+                }
+                ("accessible", value) => {
+                    options.accessible = Options::str_to_bool(&value);
+                }
+                ("prefer_display", value) => {
+                    options.prefer_display = Options::str_to_bool(&value);
+                }
+                ("sandbox", value) => {
+                    if SANDBOXES.contains(&value.as_str()) {
+                        options.sandbox = value;
+                    } else {
+                        eprintln!("Unknown sandbox: {}", value);
                     }
                 }
-                _ => eprintln!("Unknown config option: {} {}", option, value),
-            }
-        }
-
-        for (option, value) in Options::get_section(&lines, "[Racer]".to_string()).into_iter() {
-            match (option.to_lowercase().as_str(), value.clone()) {
-                ("enable_racer", value) => {
-                    options.enable_racer = Options::str_to_bool(&value);
+                ("sandbox_image", value) => {
+                    options.sandbox_image = value;
                 }
-                ("racer_inline_suggestion_color", value) => {
-                    if let Ok(value) = Options::str_to_color(&value) {
-                        options.racer_inline_suggestion_color = value;
+                ("memory_limit", value) => {
+                    if let Ok(value) = value.parse() {
+                        options.memory_limit = value;
                     }
                 }
-                ("racer_suggestions_table_color", value) => {
-                    if let Ok(value) = Options::str_to_color(&value) {
-                        options.racer_suggestions_table_color = value;
+                ("cpu_time_limit", value) => {
+                    if let Ok(value) = value.parse() {
+                        options.cpu_time_limit = value;
                     }
                 }
-                ("racer_selected_suggestion_color", value) => {
-                    if let Ok(value) = Options::str_to_color(&value) {
-                        options.racer_selected_suggestion_color = value;
+                ("cells", value) => {
+                    options.cells = Options::str_to_bool(&value);
+                }
+                ("body_pane", value) => {
+                    options.body_pane = Options::str_to_bool(&value);
+                }
+                ("body_pane_width", value) => {
+                    if let Ok(value) = value.parse() {
+                        options.body_pane_width = value;
                     }
                 }
-                ("racer_max_suggestions", value) => {
+                ("pinned_input", value) => {
+                    options.pinned_input = Options::str_to_bool(&value);
+                }
+                ("show_timings", value) => {
+                    options.show_timings = Options::str_to_bool(&value);
+                }
+                ("show_types", value) => {
+                    options.show_types = Options::str_to_bool(&value);
+                }
+                ("validate_insertions", value) => {
+                    options.validate_insertions = Options::str_to_bool(&value);
+                }
+                ("autofmt", value) => {
+                    options.autofmt = Options::str_to_bool(&value);
+                }
+                ("input_max_lines", value) => {
                     if let Ok(value) = value.parse() {
-                        options.racer_max_suggestions = value;
+                        options.input_max_lines = value;
+                    }
+                }
+                ("eval_template", value) => match EvalTemplate::parse(&value) {
+                    Ok(_) => options.eval_template = value,
+                    Err(e) => options.config_errors.push(format!("eval_template: {}", e)),
+                },
+                ("highlight_max_lines", value) => {
+                    if let Ok(value) = value.parse() {
+                        options.highlight_max_lines = value;
+                    }
+                }
+                ("input_status", value) => {
+                    options.input_status = Options::str_to_bool(&value);
+                }
+                ("output_max_lines", value) => {
+                    if let Ok(value) = value.parse() {
+                        options.output_max_lines = value;
+                    }
+                }
+                ("output_max_bytes", value) => {
+                    if let Ok(value) = value.parse() {
+                        options.output_max_bytes = value;
                     }
                 }
                 _ => eprintln!("Unknown config option: {} {}", option, value),
@@ -132,7 +235,10 @@ impl Options {
         let history = "\
 [History]
 add_irust_cmd_to_history = false
-add_shell_cmd_to_history = false";
+add_shell_cmd_to_history = false
+project_history = false
+history_ignore_space = false
+history_exclude =";
 
         let racer = if racer_enabled == RacerEnabled::True {
             "true"
@@ -142,12 +248,19 @@ add_shell_cmd_to_history = false";
         let racer = format!(
             "\
 [Racer]
-enable_racer = {}
+enable_racer = {racer}
 racer_inline_suggestion_color = Cyan
 racer_suggestions_table_color = Green
 racer_selected_suggestion_color = DarkRed
-racer_max_suggestions = 5",
-            racer
+racer_max_suggestions = 5
+racer_suggestion_docs_color = DarkGrey
+racer_trigger = keystroke
+racer_min_chars = 1
+racer_delay = 50
+completion_session = {racer}
+completion_history = {racer}
+completion_snippets = {racer}",
+            racer = racer
         );
 
         let colors = "\
@@ -160,14 +273,56 @@ eval_color = White
 irust_color = DarkBlue
 irust_warn_color = Cyan
 shell_color = DarkYellow
-err_color = DarkRed";
+err_color = DarkRed
+watch_color = DarkMagenta
+watch_changed_color = Yellow
+line_numbers_color = DarkGrey
+semantic_type_color = DarkCyan
+semantic_function_color = Blue
+semantic_macro_color = Magenta
+semantic_mutable_color = DarkYellow";
 
         let welcome = "\
 [Welcome]
 welcome_msg = Welcome to IRust
 welcome_color = DarkBlue";
 
-        format!("{}\n\n{}\n\n{}\n\n{}", history, racer, colors, welcome)
+        let repl = "\
+[Repl]
+persist_let_bindings = false
+live_check = false
+rerun_on_change = false
+git_versioning = false
+tab_width = 4
+word_wrap = false
+horizontal_scroll = false
+line_numbers = false
+rust_analyzer = false
+accessible = false
+output_max_lines = 500
+output_max_bytes = 50000
+prefer_display = false
+sandbox = none
+sandbox_image = debian:stable-slim
+memory_limit = 0
+cpu_time_limit = 0
+cells = false
+body_pane = false
+body_pane_width = 40
+pinned_input = false
+show_timings = false
+show_types = false
+validate_insertions = false
+autofmt = false
+input_max_lines = 10000
+highlight_max_lines = 1000
+eval_template =
+input_status = false";
+
+        format!(
+            "{}\n\n{}\n\n{}\n\n{}\n\n{}",
+            history, racer, colors, welcome, repl
+        )
     }
 }
 
@@ -0,0 +1,134 @@
+use super::help::ColoredPrinterItem;
+use super::highlight::highlight;
+use crate::irust::printer::Printer;
+use crate::irust::{IRust, IRustError, INPUT_START_COL};
+use crossterm::{ClearType, Color};
+
+// the pane is not shown if the main window would be narrower
+const MIN_MAIN_WIDTH: usize = INPUT_START_COL + 30;
+
+/// A region of the terminal, from `col` to the right side
+#[derive(Clone, Copy)]
+pub struct Window {
+    pub col: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// The pane showing the repl code beside the prompt, toggled with F2
+pub struct Pane {
+    pub visible: bool,
+    width: usize,
+}
+
+impl Pane {
+    pub fn new(visible: bool, width: usize) -> Self {
+        Self { visible, width }
+    }
+
+    /// The main window, where the inputs and outputs are written, and the pane window if it's visible
+    pub fn windows(&self, width: usize, height: usize) -> (Window, Option<Window>) {
+        if self.visible && width >= self.width + MIN_MAIN_WIDTH {
+            let main_width = width - self.width;
+            (
+                Window {
+                    col: 0,
+                    width: main_width,
+                    height,
+                },
+                Some(Window {
+                    col: main_width,
+                    width: self.width,
+                    height,
+                }),
+            )
+        } else {
+            (
+                Window {
+                    col: 0,
+                    width,
+                    height,
+                },
+                None,
+            )
+        }
+    }
+}
+
+impl IRust {
+    pub fn toggle_pane(&mut self) -> Result<(), IRustError> {
+        self.pane.visible = !self.pane.visible;
+        let (width, height) = self.raw_terminal.size()?;
+        let (main, pane) = self.pane.windows(width as usize, height as usize);
+        if self.pane.visible && pane.is_none() {
+            self.pane.visible = false;
+            return Ok(());
+        }
+
+        // the input is written again in the resized main window
+        let input = self.buffer.to_string();
+        self.cursor.set_width(main.width);
+        self.buffer
+            .set_max_line_char(self.options.input_row_chars(main.width));
+        self.clear()?;
+        self.replace_input(&input)
+    }
+
+    /// The windows are computed again for the new terminal size and the input is redrawn
+    pub fn handle_resize(&mut self) -> Result<(), IRustError> {
+        let (width, height) = self.raw_terminal.size()?;
+        let (main, _) = self.pane.windows(width as usize, height as usize);
+        // too small to draw the prompt, wait for the next resize
+        if main.width <= INPUT_START_COL || main.height == 0 {
+            return Ok(());
+        }
+
+        let input = self.buffer.to_string();
+        self.cursor.set_size(main.width, main.height);
+        self.buffer
+            .set_max_line_char(self.options.input_row_chars(main.width));
+        self.clear()?;
+        self.replace_input(&input)
+    }
+
+    /// Draw the pane rows from `from_row`, the main window is left as is
+    pub fn draw_pane(&mut self, from_row: usize) -> Result<(), IRustError> {
+        if !self.pane.visible || self.options.accessible {
+            return Ok(());
+        }
+        let (width, height) = self.raw_terminal.size()?;
+        let window = match self.pane.windows(width as usize, height as usize).1 {
+            Some(window) => window,
+            None => return Ok(()),
+        };
+
+        // the repl code without `fn main() {` and its closing brace, numbered like `:del` expects
+        let body = self.repl.body[1..self.repl.body.len() - 1].to_vec();
+        // the last lines are kept when the code is taller than the pane
+        let skip = body.len().saturating_sub(window.height - 1);
+        let number_width = self.repl.body.len().to_string().len();
+
+        let pos = self.cursor.pos.clone();
+        for row in from_row..window.height {
+            let mut printer = Printer::new("│ ".to_output(Color::DarkGrey));
+            if row == 0 {
+                printer.push("repl code".to_output(Color::DarkYellow));
+            } else if let Some(line) = body.get(skip + row - 1) {
+                printer.push(
+                    format!("{:>width$} ", skip + row, width = number_width)
+                        .to_output(self.options.line_numbers_color),
+                );
+                printer.append(&mut highlight(line));
+            }
+
+            self.cursor.goto(window.col, row);
+            self.raw_terminal.clear(ClearType::UntilNewLine)?;
+            // the last column is left empty so the terminal doesn't wrap
+            self.print_clipped(printer, window.width - 1)?;
+        }
+        self.cursor.pos = pos;
+        self.cursor.goto_internal_pos()?;
+
+        Ok(())
+    }
+}
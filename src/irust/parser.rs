@@ -1,32 +1,114 @@
-use super::cargo_cmds::{cargo_fmt, cargo_fmt_file, cargo_run, MAIN_FILE};
+use super::cargo_cmds::{
+    cargo_env, cargo_fmt_file, cargo_print_type_sizes, cargo_run, locked_versions, MAIN_FILE,
+};
+use super::classify::{classify, is_type, let_binding, split_trailing_expression, InputKind};
+use super::cmd_args::{split_args, CmdArgs};
+use super::diff::changed_chars;
+use super::help::usage_error;
 use super::highlight::highlight;
-use crate::irust::format::format_eval_output;
+use super::search::crates_io_name;
+use crate::irust::format::{
+    format_eval_output, format_explanation, format_timings, import_suggestions, missing_crate,
+    parse_numbers, InputLines,
+};
 use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::repl::Repl;
 use crate::irust::{IRust, IRustError};
-use crate::utils::{remove_main, stdout_and_stderr};
+use crate::utils::stdout_and_stderr;
+use std::io::Write;
+use std::time::Instant;
 
 const SUCCESS: &str = "Ok!";
+// the cargo-add flags followed by their value, the value is not a crate name
+const CARGO_ADD_VALUE_FLAGS: [&str; 13] = [
+    "--features",
+    "-F",
+    "--rename",
+    "--registry",
+    "--path",
+    "--git",
+    "--branch",
+    "--tag",
+    "--rev",
+    "--package",
+    "-p",
+    "--target",
+    "--manifest-path",
+];
 
 impl IRust {
     pub fn parse(&mut self) -> Result<Printer, IRustError> {
+        // a command ends the `:edit-line` edit too
+        let edited_line = self.line_edit.take_line();
+        // the rest of an older output is gone once something else is evaluated
+        if self.buffer.to_string() != ":more" {
+            self.more_output = None;
+        }
         match self.buffer.to_string().as_str() {
-            ":help" => self.help(),
             ":reset" => self.reset(),
             ":show" => self.show(),
             ":pop" => self.pop(),
             ":irust" => self.irust(),
+            cmd if cmd.starts_with("::!") => self.run_interactive_cmd(),
             cmd if cmd.starts_with("::") => self.run_cmd(),
+            cmd if cmd.starts_with(":edit-cell") => self.edit_cell(),
+            cmd if cmd.starts_with(":edit-line") => self.edit_line(),
             cmd if cmd.starts_with(":edit") => self.extern_edit(),
             cmd if cmd.starts_with(":add") => self.add_dep(),
+            cmd if cmd.starts_with(":args") => self.run_args(),
+            cmd if cmd.starts_with(":stdin") => self.run_stdin(),
+            cmd if cmd.starts_with(":cargo") => self.cargo(),
             cmd if cmd.starts_with(":load") => self.load_script(),
             cmd if cmd.starts_with(":type") => self.show_type(),
+            cmd if cmd.starts_with(":peek") => self.peek(),
+            cmd if cmd.starts_with(":del-cell") => self.del_cell(),
             cmd if cmd.starts_with(":del") => self.del(),
-            _ => self.parse_second_order(),
+            cmd if cmd.starts_with(":explain") => self.explain(),
+            cmd if cmd.starts_with(":search") => self.search(),
+            cmd if cmd.starts_with(":update") => self.update_deps(),
+            cmd if cmd.starts_with(":watch") => self.watch(),
+            ":unwatch" => self.unwatch(),
+            ":more" => self.more(),
+            cmd if cmd.starts_with(":plot") => self.plot(),
+            cmd if cmd.starts_with(":profile") => self.profile(),
+            cmd if cmd.starts_with(":miri") => self.miri(),
+            ":fix" => self.fix(),
+            cmd if cmd.starts_with(":format") => self.format(),
+            cmd if cmd.starts_with(":bench") => self.bench(),
+            cmd if cmd.starts_with(":clean") => self.clean(),
+            ":colors" => self.colors(),
+            cmd if cmd.starts_with(":grep") => self.grep(),
+            cmd if cmd.starts_with(":scrollback") => self.scrollback(),
+            cmd if cmd.starts_with(":target") => self.target(),
+            cmd if cmd.starts_with(":out") => self.out(),
+            cmd if cmd.starts_with(":help") => self.help(),
+            cmd if cmd.starts_with(":sizeof") => self.size_of(),
+            cmd if cmd.starts_with(":impls") => self.impls(),
+            cmd if cmd.starts_with(":source") => self.source(),
+            cmd if cmd.starts_with(":docs") => self.docs(),
+            cmd if cmd.starts_with(":tutorial") => self.tutorial(),
+            cmd if cmd.starts_with(":checkpoint") => self.checkpoint(),
+            cmd if cmd.starts_with(":diff") => self.diff(),
+            ":log" => self.log(),
+            cmd if cmd.starts_with(":revert") => self.revert(),
+            ":undo" => self.undo(),
+            ":redo" => self.redo(),
+            ":cells" => self.cells(),
+            cmd if cmd.starts_with(":rerun") => self.rerun(),
+            cmd if cmd.starts_with(":result") => self.results(),
+            cmd if cmd.starts_with(":set") => self.set(),
+            cmd if cmd.starts_with(":sh") => self.sh(),
+            _ => match edited_line {
+                Some(line) => self.replace_edited_line(line),
+                None if self.options.cells => self.eval_cell(),
+                None => self.parse_second_order(),
+            },
         }
     }
 
     fn reset(&mut self) -> Result<Printer, IRustError> {
         self.repl.reset();
+        self.repl_changed(":reset")?;
         let mut outputs = Printer::new(PrinterItem::new(SUCCESS.to_string(), PrinterItemType::Ok));
         outputs.add_new_line(1);
 
@@ -35,6 +117,7 @@ impl IRust {
 
     fn pop(&mut self) -> Result<Printer, IRustError> {
         self.repl.pop();
+        self.repl_changed(":pop")?;
         let mut outputs = Printer::new(PrinterItem::new(SUCCESS.to_string(), PrinterItemType::Ok));
         outputs.add_new_line(1);
 
@@ -42,9 +125,12 @@ impl IRust {
     }
 
     fn del(&mut self) -> Result<Printer, IRustError> {
-        if let Some(line_num) = self.buffer.to_string().split_whitespace().last() {
-            self.repl.del(line_num)?;
+        let args = CmdArgs::parse("del", &self.buffer.to_string())?;
+        match args.into_vec().as_slice() {
+            [line_num] => self.repl.del(line_num)?,
+            _ => return Err(IRustError::Custom(usage_error("del"))),
         }
+        self.repl_changed(&self.buffer.to_string())?;
 
         let mut outputs = Printer::new(PrinterItem::new(SUCCESS.to_string(), PrinterItemType::Ok));
         outputs.add_new_line(1);
@@ -59,93 +145,435 @@ impl IRust {
     }
 
     fn add_dep(&mut self) -> Result<Printer, IRustError> {
-        let dep: Vec<String> = self
-            .buffer
-            .to_string()
-            .split_whitespace()
-            .skip(1)
-            .map(ToOwned::to_owned)
-            .collect();
+        // exp: :add openssl --env OPENSSL_DIR=/usr/local/ssl
+        // exp: :add rand@0.7.3
+        let mut dep: Vec<String> = vec![];
+        let mut names: Vec<String> = vec![];
+        let mut env: Vec<(String, String)> = vec![];
+
+        let mut args = CmdArgs::parse("add", &self.buffer.to_string())?;
+        for var in args.option_values("--env")? {
+            match var.split_once('=') {
+                Some((key, value)) => env.push((key.to_owned(), value.to_owned())),
+                None => {
+                    return Err(IRustError::Custom(
+                        "Incorrect --env argument, expected --env KEY=VALUE".to_string(),
+                    ))
+                }
+            }
+        }
+        if args.is_empty() && env.is_empty() {
+            return Err(args.usage_error());
+        }
+        let mut args = args.into_vec().into_iter();
+        while let Some(arg) = args.next() {
+            let arg = arg.as_str();
+            if arg.starts_with('#') {
+                // result of the last `:search`
+                let name = self.search_result(arg)?;
+                dep.push(name.clone());
+                names.push(name);
+            } else if arg.starts_with('-') {
+                // cargo-add flags, exp: --features derive,rc
+                dep.push(arg.to_owned());
+                if CARGO_ADD_VALUE_FLAGS.contains(&arg) {
+                    match args.next() {
+                        Some(value) => dep.push(value),
+                        None => {
+                            return Err(IRustError::Custom(format!("Missing the value of {}", arg)))
+                        }
+                    }
+                }
+            } else {
+                let (name, version) = parse_dep(arg)?;
+                match version {
+                    // pin the exact version
+                    Some(version) => dep.push(format!("{}@={}", name, version)),
+                    None => dep.push(name.clone()),
+                }
+                names.push(name);
+            }
+        }
+
+        if !env.is_empty() {
+            cargo_env(&env)?;
+        }
 
         self.cursor.save_position()?;
-        self.wait_add(self.repl.add_dep(&dep)?, "Add")?;
-        self.wait_add(self.repl.build()?, "Build")?;
+        let mut cargo_stderr = String::new();
+        if !dep.is_empty() {
+            let output = self.wait_add(self.repl.add_dep(&dep)?, "Adding dep")?;
+            cargo_stderr.push_str(&String::from_utf8_lossy(&output.stderr));
+        }
+        let output = self.wait_add(self.repl.build()?, "Building dep")?;
+        cargo_stderr.push_str(&String::from_utf8_lossy(&output.stderr));
+
+        // new dependencies mean new completions
+        if let Ok(racer) = self.racer.as_mut() {
+            racer.invalidate_cache();
+        }
         self.write_newline()?;
 
-        let mut outputs = Printer::new(PrinterItem::new(SUCCESS.to_string(), PrinterItemType::Ok));
+        let mut outputs = Printer::default();
+        // show what cargo resolved
+        for name in names {
+            for version in locked_versions(&name) {
+                outputs.push(PrinterItem::new(
+                    format!("{} = \"{}\"", name, version),
+                    PrinterItemType::Ok,
+                ));
+                if version.contains('-') {
+                    outputs.push(PrinterItem::new(
+                        " (prerelease)".to_string(),
+                        PrinterItemType::Warn,
+                    ));
+                }
+                outputs.add_new_line(1);
+            }
+        }
+        for line in cargo_stderr.lines().filter(|l| l.contains("yanked")) {
+            outputs.push(PrinterItem::new(
+                line.trim().to_string(),
+                PrinterItemType::Warn,
+            ));
+            outputs.add_new_line(1);
+        }
+        outputs.push(PrinterItem::new(SUCCESS.to_string(), PrinterItemType::Ok));
         outputs.add_new_line(1);
 
         Ok(outputs)
     }
 
-    fn load_script(&mut self) -> Result<Printer, IRustError> {
-        let buffer = self.buffer.to_string();
-        let script = buffer.split_whitespace().last().unwrap();
+    fn update_deps(&mut self) -> Result<Printer, IRustError> {
+        // exp: :update, :update rand
+        let args = CmdArgs::parse("update", &self.buffer.to_string())?.into_vec();
+        let krate = match args.as_slice() {
+            [] => None,
+            [krate] => Some(krate.as_str()),
+            _ => return Err(IRustError::Custom(usage_error("update"))),
+        };
 
-        let script_code = std::fs::read(script)?;
-        if let Ok(s) = String::from_utf8(script_code) {
-            // Format script to make `remove_main` function work correctly
-            let s = cargo_fmt(&s)?;
-            let s = remove_main(&s);
+        self.cursor.save_position()?;
+        let output = self.wait_add(self.repl.update(krate)?, "Updating deps")?;
+        let changes: Vec<String> = String::from_utf8_lossy(&output.stderr)
+            .lines()
+            .map(str::trim)
+            .filter(|l| {
+                l.starts_with("Updating") || l.starts_with("Adding") || l.starts_with("Removing")
+            })
+            // `Updating crates.io index`
+            .filter(|l| !l.ends_with("index"))
+            .map(ToOwned::to_owned)
+            .collect();
 
-            self.repl.insert(s);
+        if !changes.is_empty() {
+            self.cursor.save_position()?;
+            self.wait_add(self.repl.build()?, "Building dep")?;
+            if let Ok(racer) = self.racer.as_mut() {
+                racer.invalidate_cache();
+            }
         }
+        self.write_newline()?;
 
-        let mut outputs = Printer::new(PrinterItem::new(SUCCESS.to_string(), PrinterItemType::Ok));
+        let mut outputs = Printer::default();
+        if changes.is_empty() {
+            outputs.push(PrinterItem::new(
+                "Dependencies are up to date".to_string(),
+                PrinterItemType::Ok,
+            ));
+        } else {
+            outputs.push(PrinterItem::new(changes.join("\n"), PrinterItemType::Eval));
+            outputs.add_new_line(1);
+            outputs.push(PrinterItem::new(SUCCESS.to_string(), PrinterItemType::Ok));
+        }
         outputs.add_new_line(1);
 
         Ok(outputs)
     }
 
     fn show_type(&mut self) -> Result<Printer, IRustError> {
-        const TYPE_FOUND_MSG: &str = "found type `";
-        const EMPTY_TYPE_MSG: &str = "dev [unoptimized + debuginfo]";
+        // exp: :type my_vec.iter().map(f)
+        let expression = CmdArgs::parse_code("type", &self.buffer.to_string())
+            .expression()
+            .to_string();
+        if expression.is_empty() {
+            return Err(IRustError::Custom("No expression specified".to_string()));
+        }
+
+        let job = self.repl.type_of(&expression)?;
+        let output = self.wait_job(job, "Evaluating")?;
+        if !output.status.success() {
+            let mut outputs = format_eval_output(&stdout_and_stderr(output));
+            outputs.add_new_line(1);
+            return Ok(outputs);
+        }
+
+        Ok(Printer::new(PrinterItem::new(
+            String::from_utf8_lossy(&output.stdout).trim().to_owned(),
+            PrinterItemType::Ok,
+        )))
+    }
 
-        let variable = self
-            .buffer
-            .to_string()
-            .trim_start_matches(":type")
+    fn size_of(&mut self) -> Result<Printer, IRustError> {
+        // exp: :sizeof Option<u32>, :sizeof v, :sizeof --layout Option<u32>
+        let mut args = CmdArgs::parse_code("sizeof", &self.buffer.to_string());
+        let layout = args.take_flag("--layout");
+        let target = args.expression();
+        if target.is_empty() {
+            return Err(IRustError::Custom(
+                "No type or expression specified".to_string(),
+            ));
+        }
+
+        let probe = Repl::layout_probe(target, is_type(target));
+        let job = self
+            .repl
+            .eval_in_tmp_repl(probe.clone(), || Ok(cargo_run(true)?))?;
+        let output = self.wait_job(job, "Evaluating")?;
+        if !output.status.success() {
+            let mut outputs = format_eval_output(&stdout_and_stderr(output));
+            outputs.add_new_line(1);
+            return Ok(outputs);
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut lines = stdout.lines();
+        let (name, size, align) = match (lines.next(), lines.next(), lines.next()) {
+            (Some(name), Some(size), Some(align)) => (name, size, align),
+            _ => return Err(IRustError::Custom("Unexpected probe output".to_string())),
+        };
+
+        let mut outputs = Printer::default();
+        outputs.push(PrinterItem::new(name.to_owned(), PrinterItemType::Eval));
+        outputs.add_new_line(1);
+        outputs.push(PrinterItem::new(
+            format!("size: {} bytes, align: {} bytes", size, align),
+            PrinterItemType::Ok,
+        ));
+        outputs.add_new_line(1);
+
+        if layout {
+            let job = self
+                .repl
+                .eval_in_tmp_repl(probe, || Ok(cargo_print_type_sizes()?))?;
+            let output = self.wait_job(job, "Computing layout")?;
+            if !output.status.success() {
+                return Err(IRustError::Custom(
+                    "`--layout` requires a nightly toolchain".to_string(),
+                ));
+            }
+            let sizes = String::from_utf8_lossy(&output.stdout);
+            match type_layout(&sizes, name) {
+                Some(layout) => {
+                    outputs.push(PrinterItem::new(layout, PrinterItemType::Shell));
+                    outputs.add_new_line(1);
+                }
+                None => {
+                    outputs.push(PrinterItem::new(
+                        "IRust: rustc printed no layout for this type".to_string(),
+                        PrinterItemType::Warn,
+                    ));
+                    outputs.add_new_line(1);
+                }
+            }
+        }
+
+        Ok(outputs)
+    }
+
+    fn peek(&mut self) -> Result<Printer, IRustError> {
+        // a trailing `;` would make the expression evaluate to `()`
+        let expression = CmdArgs::parse_code("peek", &self.buffer.to_string())
+            .expression()
             .to_string();
-        let mut raw_out = String::new();
+        if expression.is_empty() {
+            return Err(IRustError::Custom("No expression specified".to_string()));
+        }
 
-        self.repl
-            .eval_in_tmp_repl(variable, || -> Result<(), IRustError> {
-                raw_out = cargo_run(false).unwrap();
-                Ok(())
-            })?;
+        // eval always uses a temporary repl, so the body is never modified
+        let job = self.repl.eval(expression)?;
+        let output = self.wait_job(job, "Evaluating")?;
+        let mut outputs = self.format_run_output(output);
+        outputs.add_new_line(1);
 
-        let var_type = if raw_out.find(TYPE_FOUND_MSG).is_some() {
-            raw_out
-                .lines()
-                .find(|l| l.contains(TYPE_FOUND_MSG))
-                .unwrap()
-                .split('`')
-                .nth(1)
-                .unwrap()
-                .to_string()
-        } else if raw_out.find(EMPTY_TYPE_MSG).is_some() {
-            "()".into()
-        } else {
-            "Uknown".into()
+        Ok(outputs)
+    }
+
+    fn out(&mut self) -> Result<Printer, IRustError> {
+        // exp: :out result.txt v, :out >> result.txt v
+        let mut args = CmdArgs::parse_code("out", &self.buffer.to_string());
+        let append = args.take_flag(">>");
+        let file = match args.take_arg()? {
+            Some(file) => file,
+            None => return Err(IRustError::Custom("No file specified".to_string())),
         };
+        let expression = args.expression();
+        if expression.is_empty() {
+            return Err(IRustError::Custom("No expression specified".to_string()));
+        }
+
+        let job = self.repl.eval(expression.to_owned())?;
+        let output = self.wait_job(job, "Evaluating")?;
+        // errors are still shown in the terminal
+        if !output.status.success() {
+            let mut outputs = format_eval_output(&stdout_and_stderr(output));
+            outputs.add_new_line(1);
+            return Ok(outputs);
+        }
+
+        std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(&file)?
+            .write_all(&output.stdout)?;
 
         Ok(Printer::new(PrinterItem::new(
-            var_type,
+            format!(
+                "{} ({} bytes {} {})",
+                SUCCESS,
+                output.stdout.len(),
+                if append { "appended to" } else { "written to" },
+                file
+            ),
             PrinterItemType::Ok,
         )))
     }
 
+    fn explain(&mut self) -> Result<Printer, IRustError> {
+        // exp: :explain E0308, :explain 308
+        let args = CmdArgs::parse("explain", &self.buffer.to_string())?.into_vec();
+        let code = match args.as_slice() {
+            [code] => code.trim_start_matches(['E', 'e']).to_owned(),
+            [] => return Err(IRustError::Custom("No error code specified".to_string())),
+            _ => return Err(IRustError::Custom(usage_error("explain"))),
+        };
+        let code = match code.parse::<usize>() {
+            Ok(code) => format!("E{:04}", code),
+            Err(_) => return Err(IRustError::Custom("Incorrect error code".to_string())),
+        };
+
+        let output = std::process::Command::new("rustc")
+            .args(["--explain", &code])
+            .output()?;
+        if !output.status.success() {
+            return Err(IRustError::Custom(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+
+        let explanation = String::from_utf8_lossy(&output.stdout);
+        let outputs = format_explanation(&explanation);
+
+        // long explanations are paged
+        if explanation.lines().count() >= self.cursor.bound.height && self.page(&outputs).is_ok() {
+            return Ok(Printer::default());
+        }
+
+        Ok(outputs)
+    }
+
+    fn watch(&mut self) -> Result<Printer, IRustError> {
+        let expression = CmdArgs::parse_code("watch", &self.buffer.to_string())
+            .expression()
+            .to_string();
+        if expression.is_empty() {
+            return Err(IRustError::Custom("No expression specified".to_string()));
+        }
+        self.watches.push(expression);
+
+        // show its current value right away
+        let mut outputs = self.eval_watches()?;
+        outputs.add_new_line(1);
+        Ok(outputs)
+    }
+
+    fn unwatch(&mut self) -> Result<Printer, IRustError> {
+        self.watches.clear();
+        self.watch_values.clear();
+        let mut outputs = Printer::new(PrinterItem::new(SUCCESS.to_string(), PrinterItemType::Ok));
+        outputs.add_new_line(1);
+
+        Ok(outputs)
+    }
+
+    fn more(&mut self) -> Result<Printer, IRustError> {
+        let rest = match self.more_output.take() {
+            Some(rest) => rest,
+            None => return Err(IRustError::Custom("No more output".to_string())),
+        };
+
+        if self.page(&rest).is_ok() {
+            return Ok(Printer::default());
+        }
+        // without a pager the rest is printed, cut again if it's still too long
+        Ok(rest)
+    }
+
+    fn eval_watches(&mut self) -> Result<Printer, IRustError> {
+        let watches = self.watches.clone();
+        let job = self.repl.eval_watches(&watches)?;
+        let output = stdout_and_stderr(self.wait_job(job, "Evaluating watches")?);
+
+        let mut outputs = Printer::default();
+        match Repl::watches_output(&output, &watches) {
+            Some(values) => {
+                for (watch, value) in watches.iter().zip(values) {
+                    outputs.push(PrinterItem::new(
+                        format!("Watch {}: ", watch),
+                        PrinterItemType::Custom(self.options.watch_color),
+                    ));
+                    // the characters that changed since the previous evaluation are highlighted
+                    let changed = match self.watch_values.get(watch) {
+                        Some(previous) => changed_chars(previous, value),
+                        None => vec![false; value.chars().count()],
+                    };
+                    let mut chars = value.chars().zip(changed).peekable();
+                    while let Some((c, is_changed)) = chars.next() {
+                        let mut run = c.to_string();
+                        while let Some((c, _)) = chars.next_if(|(_, next)| *next == is_changed) {
+                            run.push(c);
+                        }
+                        let color = if is_changed {
+                            self.options.watch_changed_color
+                        } else {
+                            self.options.watch_color
+                        };
+                        outputs.push(PrinterItem::new(run, PrinterItemType::Custom(color)));
+                    }
+                    self.watch_values.insert(watch.clone(), value.to_owned());
+                    outputs.add_new_line(1);
+                }
+                outputs.pop();
+            }
+            None => {
+                outputs.push(PrinterItem::new(
+                    "Watch: evaluation failed".to_string(),
+                    PrinterItemType::Warn,
+                ));
+                outputs.add_new_line(1);
+                outputs.append(&mut format_eval_output(&output));
+            }
+        }
+
+        Ok(outputs)
+    }
+
     fn run_cmd(&mut self) -> Result<Printer, IRustError> {
         // remove ::
         let buffer = &self.buffer.to_string()[2..];
+        if let Some(result) = self.run_in_shell(buffer) {
+            return result;
+        }
 
-        let mut cmd = buffer.split_whitespace();
+        let cmd = split_args(buffer)
+            .ok_or_else(|| IRustError::Custom("Unterminated quote in the command".to_string()))?;
+        let (program, args) = match cmd.split_first() {
+            Some(cmd) => cmd,
+            None => return Err(IRustError::Custom("No command specified".to_string())),
+        };
 
-        let output = stdout_and_stderr(
-            std::process::Command::new(cmd.next().unwrap_or_default())
-                .args(&cmd.collect::<Vec<&str>>())
-                .output()?,
-        );
+        let output = stdout_and_stderr(std::process::Command::new(program).args(args).output()?);
 
         Ok(Printer::new(PrinterItem::new(
             output,
@@ -153,30 +581,201 @@ impl IRust {
         )))
     }
 
-    fn parse_second_order(&mut self) -> Result<Printer, IRustError> {
-        if self.buffer.to_string().trim().is_empty() {
+    fn pipe(&mut self, expression: &str, cmds: &[&str]) -> Result<Printer, IRustError> {
+        let job = self.repl.eval(expression.trim().to_owned())?;
+        let output = self.wait_job(job, "Evaluating")?;
+        if !output.status.success() {
+            let mut outputs = format_eval_output(&stdout_and_stderr(output));
+            outputs.add_new_line(1);
+            return Ok(outputs);
+        }
+
+        // each command reads the previous one output
+        let mut piped = output;
+        for cmd in cmds {
+            let cmd = split_args(cmd).ok_or_else(|| {
+                IRustError::Custom("Unterminated quote in the command".to_string())
+            })?;
+            let (program, args) = match cmd.split_first() {
+                Some(cmd) => cmd,
+                None => return Err(IRustError::Custom("No command specified".to_string())),
+            };
+            let mut child = std::process::Command::new(program)
+                .args(args)
+                .stdin(std::process::Stdio::piped())
+                .stdout(std::process::Stdio::piped())
+                .stderr(std::process::Stdio::piped())
+                .spawn()
+                .map_err(|e| IRustError::Custom(format!("{}: {}", program, e)))?;
+
+            // write from another thread so a command that outputs before reading all its input doesn't block
+            let mut stdin = child.stdin.take().unwrap();
+            let input = std::mem::take(&mut piped.stdout);
+            std::thread::spawn(move || stdin.write_all(&input));
+
+            piped = child.wait_with_output()?;
+        }
+
+        let mut outputs = Printer::new(PrinterItem::new(
+            stdout_and_stderr(piped).trim_end_matches('\n').to_owned(),
+            PrinterItemType::Shell,
+        ));
+        outputs.add_new_line(1);
+
+        Ok(outputs)
+    }
+
+    pub fn parse_second_order(&mut self) -> Result<Printer, IRustError> {
+        let buffer = self.buffer.to_string();
+        if let Some((expression, cmds)) = split_pipe(&buffer) {
+            return self.pipe(expression, &cmds);
+        }
+        let insert_in_body = match classify(&buffer) {
+            InputKind::Item | InputKind::Statement | InputKind::Comment => true,
+            InputKind::Expression => false,
+            // fallback to the old heuristic
+            InputKind::Unknown => buffer.trim().ends_with(';'),
+        };
+
+        if buffer.trim().is_empty() {
             Ok(Printer::default())
-        } else if self.buffer.to_string().trim().ends_with(';') {
-            self.repl.insert(self.buffer.to_string());
+        } else if insert_in_body {
+            let mut printer = Printer::default();
+            let input = self.autofmt(buffer.clone(), &mut printer);
+            match let_binding(&buffer) {
+                Some(ref binding) if self.options.persist_let_bindings && !binding.literal => {
+                    let job = self.repl.persist_probe(&buffer, binding)?;
+                    let probe_out = stdout_and_stderr(self.wait_job(job, "Evaluating")?);
+                    self.repl.insert_persisted_let(input, binding, &probe_out);
+                }
+                _ => {
+                    self.repl.insert(input.clone());
+                    self.validate_insertion(&input);
+                }
+            }
+            self.repl_changed(&buffer)?;
 
-            let printer = Printer::default();
+            if !self.watches.is_empty() {
+                printer.append(&mut self.eval_watches()?);
+                printer.add_new_line(1);
+            }
 
             Ok(printer)
         } else {
             let mut outputs = Printer::default();
-            let mut eval_output = format_eval_output(&self.repl.eval(self.buffer.to_string())?);
+            // `let a = 1; a + 1`: the statements are kept only if the whole input evaluates
+            let (statements, expression) = match split_trailing_expression(&buffer) {
+                Some((statements, expression)) => {
+                    (Some(statements.to_owned()), expression.trim().to_owned())
+                }
+                None => (None, buffer.clone()),
+            };
+            self.last_eval = Some(expression);
+            // the input starts on the second line of the evaluated statement
+            let (code, statement_start) = self.repl.code_with(&self.repl.eval_statement(&buffer));
+            let input_lines = InputLines {
+                start: statement_start + 1,
+                len: buffer.lines().count(),
+                inserted: code.lines().count() - self.repl.body.len(),
+            };
+            let start = Instant::now();
+            let job = self.repl.eval(buffer.clone())?;
+            let mut output = self.wait_job(job, "Evaluating")?;
+            let timings = if self.options.show_timings {
+                format_timings(&output.stderr, start.elapsed())
+            } else {
+                None
+            };
+            let success = output.status.success();
+            let value_type = Repl::take_value_type(&mut output.stderr);
+            self.import_fix = None;
+            self.dependency_fix = None;
+            if !success && !self.options.accessible {
+                let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+                let uses = import_suggestions(&stderr);
+                if !uses.is_empty() {
+                    self.import_fix = Some((uses, buffer.clone()));
+                }
+                if let Some(krate) = missing_crate(&stderr).and_then(|name| crates_io_name(&name)) {
+                    self.dependency_fix = Some((krate, buffer));
+                }
+            }
+            if !success {
+                output.stderr = input_lines
+                    .map_error(&String::from_utf8_lossy(&output.stderr))
+                    .into_bytes();
+            }
+            if success {
+                self.repl.last_out = String::from_utf8_lossy(&output.stdout)
+                    .trim_end_matches('\n')
+                    .to_string();
+                if let Some(expression) = self.last_eval.clone() {
+                    self.record_result(expression, self.repl.last_out.clone());
+                }
+                if let Some(statements) = statements {
+                    let statements = self.autofmt(statements, &mut outputs);
+                    self.repl.insert(statements.clone());
+                    self.repl_changed(&statements)?;
+                }
+            }
+            let mut eval_output = self.format_run_output(output);
 
             outputs.append(&mut eval_output);
+            if let Some((uses, _)) = self.import_fix.as_ref() {
+                outputs.add_new_line(1);
+                outputs.push(PrinterItem::new(
+                    format!(
+                        "IRust: press F4 to add `{}` and evaluate again",
+                        uses.join(" ")
+                    ),
+                    PrinterItemType::Warn,
+                ));
+            }
+            if let Some((krate, _)) = self.dependency_fix.as_ref() {
+                outputs.add_new_line(1);
+                outputs.push(PrinterItem::new(
+                    format!("IRust: add dependency `{}`? [y/N]", krate),
+                    PrinterItemType::Warn,
+                ));
+            }
+            if let (true, Some(value_type)) = (success, value_type) {
+                outputs.push(PrinterItem::new(
+                    format!(" : {}", without_paths(&value_type)),
+                    PrinterItemType::Custom(crossterm::Color::DarkGrey),
+                ));
+            }
             outputs.add_new_line(1);
 
+            if let Some(timings) = timings {
+                outputs.push(PrinterItem::new(
+                    timings,
+                    PrinterItemType::Custom(crossterm::Color::DarkGrey),
+                ));
+                outputs.add_new_line(1);
+            }
+
+            if success && parse_numbers(&self.repl.last_out).is_some() {
+                outputs.push(PrinterItem::new(
+                    "IRust: use `:plot` to chart these numbers".to_string(),
+                    PrinterItemType::Warn,
+                ));
+                outputs.add_new_line(1);
+            }
+
+            if success && !self.watches.is_empty() {
+                outputs.append(&mut self.eval_watches()?);
+                outputs.add_new_line(1);
+            }
+
             Ok(outputs)
         }
     }
 
     fn extern_edit(&mut self) -> Result<Printer, IRustError> {
-        // exp: :edit vi
-        let editor: String = match self.buffer.to_string().split_whitespace().nth(1) {
-            Some(ed) => ed.to_string(),
+        // exp: :edit vi, :edit code --wait
+        let args = CmdArgs::parse("edit", &self.buffer.to_string())?.into_vec();
+        let (editor, editor_args) = match args.split_first() {
+            Some(editor) => editor,
             None => return Err(IRustError::Custom("No editor specified".to_string())),
         };
 
@@ -190,19 +789,25 @@ impl IRust {
         self.repl.write()?;
         // beautify code
         if self.repl.body.len() > 2 {
-            let _ = cargo_fmt_file(&*MAIN_FILE);
+            let _ = cargo_fmt_file(&MAIN_FILE);
         }
 
-        std::process::Command::new(editor)
+        self.pause_input();
+        let status = std::process::Command::new(editor)
+            .args(editor_args)
             .arg(&*MAIN_FILE)
-            .spawn()?
-            .wait()?;
+            .status();
+        self.resume_input();
+        status?;
 
         match self.repl.update_from_main_file() {
-            Ok(_) => Ok(Printer::new(PrinterItem::new(
-                SUCCESS.to_string(),
-                PrinterItemType::Ok,
-            ))),
+            Ok(_) => {
+                self.repl_changed(":edit")?;
+                Ok(Printer::new(PrinterItem::new(
+                    SUCCESS.to_string(),
+                    PrinterItemType::Ok,
+                )))
+            }
             Err(e) => {
                 self.repl.reset();
                 Err(e)
@@ -229,3 +834,113 @@ impl IRust {
         )))
     }
 }
+
+/// `name` or `name@version`, the version is validated before reaching cargo
+fn parse_dep(dep: &str) -> Result<(String, Option<String>), IRustError> {
+    let (name, version) = match dep.split_once('@') {
+        Some((name, version)) => (name, Some(version)),
+        None => (dep, None),
+    };
+
+    let valid_name = name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid_name {
+        return Err(IRustError::Custom(format!("Invalid crate name `{}`", name)));
+    }
+
+    // exp: 0.7.3, 1.0.0-beta.2, 1.2
+    if let Some(version) = version {
+        let (numbers, pre) = match version.split_once('-') {
+            Some((numbers, pre)) => (numbers, Some(pre)),
+            None => (version, None),
+        };
+        let numbers: Vec<&str> = numbers.split('.').collect();
+        let valid_version = numbers.len() <= 3
+            && numbers
+                .iter()
+                .all(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+            && pre.is_none_or(|pre| {
+                !pre.is_empty()
+                    && pre
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+            });
+        if !valid_version {
+            return Err(IRustError::Custom(format!(
+                "Invalid version `{}`, expected something like {}@0.7.3",
+                version, name
+            )));
+        }
+    }
+
+    Ok((name.to_owned(), version.map(ToOwned::to_owned)))
+}
+
+// exp: v | ::grep 3 | ::wc -l => ("v", ["grep 3", "wc -l"])
+// a `|` followed by a path (`x | ::std::u8::MAX`) is still rust code
+pub fn split_pipe(input: &str) -> Option<(&str, Vec<&str>)> {
+    input.match_indices('|').find_map(|(idx, _)| {
+        let expression = &input[..idx];
+        let cmds = input[idx + 1..]
+            .split('|')
+            .map(|part| {
+                let cmd = part.trim_start().strip_prefix("::")?.trim();
+                if cmd.is_empty() || cmd.contains("::") {
+                    None
+                } else {
+                    Some(cmd)
+                }
+            })
+            .collect::<Option<Vec<&str>>>()?;
+        if expression.trim().is_empty() {
+            None
+        } else {
+            Some((expression, cmds))
+        }
+    })
+}
+
+// the `-Zprint-type-sizes` lines of a type, exp:
+// print-type-size type: `std::option::Option<u32>`: 8 bytes, alignment: 4 bytes
+// print-type-size     discriminant: 4 bytes
+fn type_layout(sizes: &str, type_name: &str) -> Option<String> {
+    let type_name = without_paths(type_name);
+    let mut lines = sizes.lines().skip_while(|line| {
+        line.strip_prefix("print-type-size type: `")
+            .and_then(|line| line.rsplit_once("`:"))
+            .is_none_or(|(name, _)| without_paths(name) != type_name)
+    });
+    let header = lines.next()?.trim_start_matches("print-type-size ");
+    let details = lines
+        .take_while(|line| line.starts_with("print-type-size  "))
+        .map(|line| line.trim_start_matches("print-type-size "));
+
+    Some(
+        std::iter::once(header)
+            .chain(details)
+            .collect::<Vec<&str>>()
+            .join("\n"),
+    )
+}
+
+// `alloc::vec::Vec<core::option::Option<u8>>` => `Vec<Option<u8>>`
+fn without_paths(type_name: &str) -> String {
+    let mut out = String::new();
+    let mut segment = String::new();
+    let mut chars = type_name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            segment.clear();
+        } else if c.is_alphanumeric() || c == '_' {
+            segment.push(c);
+        } else {
+            out += &segment;
+            segment.clear();
+            out.push(c);
+        }
+    }
+    out + &segment
+}
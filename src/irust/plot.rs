@@ -0,0 +1,119 @@
+use super::cmd_args::CmdArgs;
+use crate::irust::format::{format_eval_output, parse_numbers};
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+use crate::utils::stdout_and_stderr;
+
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const BAR: char = '█';
+
+impl IRust {
+    pub fn plot(&mut self) -> Result<Printer, IRustError> {
+        // exp: :plot, :plot --bars, :plot (0..20).map(|x| x * x).collect::<Vec<_>>()
+        let mut args = CmdArgs::parse_code("plot", &self.buffer.to_string());
+        let bars = args.take_flag("--bars");
+        let expression = args.expression();
+
+        // the last output is plotted if there is no expression
+        let output = if expression.is_empty() {
+            self.repl.last_out.clone()
+        } else {
+            let job = self.repl.eval(expression.to_string())?;
+            let output = self.wait_job(job, "Evaluating")?;
+            if !output.status.success() {
+                let mut outputs = format_eval_output(&stdout_and_stderr(output));
+                outputs.add_new_line(1);
+                return Ok(outputs);
+            }
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        };
+        let numbers = parse_numbers(&output).ok_or_else(|| {
+            IRustError::Custom(
+                "Nothing to plot, the output is not a sequence of numbers".to_string(),
+            )
+        })?;
+
+        let mut outputs = if bars {
+            bar_chart(&numbers, self.cursor.bound.width)
+        } else {
+            Printer::new(PrinterItem::new(
+                sparkline(&numbers, self.cursor.bound.width),
+                PrinterItemType::Eval,
+            ))
+        };
+        outputs.add_new_line(1);
+        let (min, max) = bounds(&numbers);
+        outputs.push(PrinterItem::new(
+            format!("{} values, min {} max {}", numbers.len(), min, max),
+            PrinterItemType::Warn,
+        ));
+        outputs.add_new_line(1);
+
+        Ok(outputs)
+    }
+}
+
+fn bounds(numbers: &[f64]) -> (f64, f64) {
+    numbers
+        .iter()
+        .filter(|n| n.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &n| {
+            (min.min(n), max.max(n))
+        })
+}
+
+/// One char per value, values are averaged when there are more of them than columns
+fn sparkline(numbers: &[f64], width: usize) -> String {
+    let width = std::cmp::max(width.saturating_sub(1), 1);
+    let chunk = numbers.len().div_ceil(width);
+    let values: Vec<f64> = numbers
+        .chunks(chunk)
+        .map(|chunk| chunk.iter().sum::<f64>() / chunk.len() as f64)
+        .collect();
+
+    let (min, max) = bounds(&values);
+    values
+        .iter()
+        .map(|&value| {
+            if !value.is_finite() {
+                return ' ';
+            }
+            if max == min {
+                return SPARKS[SPARKS.len() / 2];
+            }
+            let level = (value - min) / (max - min) * (SPARKS.len() - 1) as f64;
+            SPARKS[level.round() as usize]
+        })
+        .collect()
+}
+
+/// One row per value: `label │████`, the bars start at 0 (or at the minimum if it's negative)
+fn bar_chart(numbers: &[f64], width: usize) -> Printer {
+    let labels: Vec<String> = numbers.iter().map(ToString::to_string).collect();
+    let label_width = labels.iter().map(String::len).max().unwrap_or(0);
+    let bar_width = width.saturating_sub(label_width + 3).max(1);
+
+    let (min, max) = bounds(numbers);
+    let (start, end) = (min.min(0.0), max.max(0.0));
+
+    let mut printer = Printer::default();
+    for (label, &value) in labels.iter().zip(numbers) {
+        let len = if value.is_finite() && end > start {
+            ((value - start) / (end - start) * bar_width as f64).round() as usize
+        } else {
+            0
+        };
+        printer.push(PrinterItem::new(
+            format!("{:>width$} │", label, width = label_width),
+            PrinterItemType::Warn,
+        ));
+        printer.push(PrinterItem::new(
+            BAR.to_string().repeat(len),
+            PrinterItemType::Eval,
+        ));
+        printer.add_new_line(1);
+    }
+    // the caller adds the line break after the chart
+    printer.pop();
+    printer
+}
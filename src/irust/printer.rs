@@ -1,8 +1,14 @@
 use super::highlight::highlight;
 use crate::irust::{IRust, IRustError};
 use crate::utils::StringTools;
-use crossterm::{ClearType, Color};
+use crossterm::{style, Attribute, ClearType, Color};
+use std::io::Write;
 use std::iter::FromIterator;
+use std::ops::Range;
+mod layout;
+mod pinned;
+pub use layout::strip_escapes;
+use layout::{last_line_width, layout, truncate};
 
 #[derive(Debug, Default, Clone)]
 pub struct Printer {
@@ -29,6 +35,21 @@ impl Printer {
         self.items.pop()
     }
 
+    /// Remove the line breaks at the end, new line items and the end of the last text
+    pub fn trim_new_lines(&mut self) {
+        while self
+            .items
+            .last()
+            .is_some_and(|item| item.string_type == PrinterItemType::NewLine)
+        {
+            self.items.pop();
+        }
+        if let Some(item) = self.items.last_mut() {
+            item.string
+                .truncate(item.string.trim_end_matches('\n').len());
+        }
+    }
+
     pub fn append(&mut self, other: &mut Self) {
         self.items.append(&mut other.items);
     }
@@ -37,9 +58,101 @@ impl Printer {
         self.items.is_empty()
     }
 
+    /// Keep the first `max_lines` lines and `max_bytes` bytes and return the rest, 0 means no limit
+    pub fn truncate(&mut self, max_lines: usize, max_bytes: usize) -> Option<Printer> {
+        let max_lines = if max_lines == 0 {
+            usize::MAX
+        } else {
+            max_lines
+        };
+        let max_bytes = if max_bytes == 0 {
+            usize::MAX
+        } else {
+            max_bytes
+        };
+        let (mut lines, mut bytes) = (0, 0);
+
+        // (item index, byte index in the item) where the rest starts
+        let mut split = None;
+        'items: for (idx, item) in self.items.iter().enumerate() {
+            if item.string_type == PrinterItemType::NewLine {
+                lines += 1;
+                if lines >= max_lines {
+                    split = Some((idx + 1, 0));
+                    break;
+                }
+                continue;
+            }
+            for (pos, c) in item.string.char_indices() {
+                if bytes >= max_bytes {
+                    split = Some((idx, pos));
+                    break 'items;
+                }
+                bytes += c.len_utf8();
+                if c == '\n' {
+                    lines += 1;
+                    if lines >= max_lines {
+                        split = Some((idx, pos + 1));
+                        break 'items;
+                    }
+                }
+            }
+        }
+
+        let (idx, pos) = split?;
+        let mut rest = self.items.split_off(idx);
+        if let Some(item) = rest.first_mut() {
+            if pos > 0 {
+                let end = item.string.split_off(pos);
+                self.items.push(PrinterItem::new(
+                    std::mem::replace(&mut item.string, end),
+                    item.string_type.clone(),
+                ));
+            }
+        }
+
+        // trailing line breaks are not worth a `:more`
+        let rest = Printer { items: rest };
+        if rest.iter().all(|item| {
+            item.string_type == PrinterItemType::NewLine || item.string.trim().is_empty()
+        }) {
+            self.items.extend(rest.items);
+            return None;
+        }
+        Some(rest)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.items
+            .iter()
+            .any(|item| item.string_type == PrinterItemType::Err)
+    }
+
+    /// The text of the items without colors nor escape sequences
+    pub fn to_plain_text(&self) -> String {
+        self.items
+            .iter()
+            .map(|item| match item.string_type {
+                PrinterItemType::NewLine => "\n".to_string(),
+                _ => strip_escapes(&item.string),
+            })
+            .collect()
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &PrinterItem> {
         self.items.iter()
     }
+
+    /// Number of lines, without accounting for wrapping
+    pub fn lines_count(&self) -> usize {
+        self.items
+            .iter()
+            .map(|item| match item.string_type {
+                PrinterItemType::NewLine => 1,
+                _ => item.string.matches('\n').count(),
+            })
+            .sum()
+    }
 }
 
 impl Iterator for Printer {
@@ -64,6 +177,14 @@ impl FromIterator<PrinterItem> for Printer {
     }
 }
 
+/// The last output, kept to redraw it scrolled horizontally
+pub struct ScrolledOutput {
+    printer: Printer,
+    // terminal rows of the output
+    pub rows: Range<usize>,
+    offset: usize,
+}
+
 #[derive(Debug, Clone)]
 pub struct PrinterItem {
     string: String,
@@ -88,7 +209,7 @@ impl PrinterItem {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum PrinterItemType {
     Eval,
     Ok,
@@ -97,51 +218,156 @@ pub enum PrinterItemType {
     Out,
     Shell,
     Err,
+    #[default]
     NewLine,
     Custom(Color),
 }
 
-impl Default for PrinterItemType {
-    fn default() -> Self {
-        PrinterItemType::NewLine
-    }
-}
-
 impl IRust {
     pub fn print_input(&mut self) -> Result<(), IRustError> {
+        // the accessible mode never redraws the input
+        if self.options.accessible {
+            return Ok(());
+        }
         self.cursor.hide();
         // scroll if needed before writing the input
-        self.scroll_if_needed_for_input();
+        if self.pinned_input() {
+            self.pin_input()?;
+        } else {
+            self.scroll_if_needed_for_input();
+        }
         self.cursor.save_position()?;
         self.cursor.goto_start();
         self.raw_terminal.clear(ClearType::FromCursorDown)?;
 
-        self.write_from_terminal_start(super::IN, Color::Yellow)?;
-        self.print_inner(highlight(&self.buffer.to_string()))?;
+        self.write_input_prompt()?;
+        self.numbered_line = self.cursor_line();
+        let (underline, message) = match self.input_diagnostic() {
+            Some(diagnostic) => (diagnostic.span.clone(), Some(diagnostic.message.clone())),
+            None => (0..0, None),
+        };
+        match self.plain_input() {
+            Some(plain) => self.print_inner(plain, underline, &[])?,
+            None => {
+                let semantic = self.semantic_colors();
+                self.print_inner(highlight(&self.buffer.to_string()), underline, &semantic)?;
+            }
+        }
+        if let Some(mut rest) = self.inline_suggestion_rest() {
+            if self.options.horizontal_scroll {
+                let room =
+                    (self.cursor.bound.width - 1).saturating_sub(self.cursor.pos.current_pos.0);
+                rest = rest.chars().take_while(|c| *c != '\n').take(room).collect();
+            }
+            self.write(&rest, self.options.racer_inline_suggestion_color)?;
+        }
+
+        // dimmed error message under the input
+        if let Some(mut message) = message {
+            let max_width = self.cursor.bound.width - 1;
+            if message.chars().count() > max_width {
+                message = message.chars().take(max_width - 3).collect::<String>() + "...";
+            }
+            self.cursor.goto_next_row_terminal_start();
+            self.raw_terminal
+                .write_with_color(message, Color::DarkGrey)?;
+        }
 
         self.cursor.restore_position()?;
+        // the input was cleared until the end of the screen
+        self.draw_pane(self.cursor.pos.starting_pos.1)?;
         self.cursor.show();
 
         Ok(())
     }
 
-    fn print_inner(&mut self, printer: Printer) -> Result<(), IRustError> {
+    // with `input_status` the last cell of the prompt tells if Enter evaluates the input (`↵`)
+    // or adds a line to it (`…`)
+    fn write_input_prompt(&mut self) -> Result<(), IRustError> {
+        let buffer = self.buffer.to_string();
+        if !self.options.input_status || buffer.trim().is_empty() {
+            return self.write_from_terminal_start(super::IN, Color::Yellow);
+        }
+        let status = if self.incomplete_input(&buffer) {
+            "…"
+        } else {
+            "↵"
+        };
+        self.write_from_terminal_start(super::IN.trim_end(), Color::Yellow)?;
+        self.write(status, Color::DarkGrey)
+    }
+
+    fn print_inner(
+        &mut self,
+        printer: Printer,
+        underline: Range<usize>,
+        semantic: &[(Range<usize>, Color)],
+    ) -> Result<(), IRustError> {
+        // index of the input char being written
+        let mut idx = 0;
+        let mut line = 1;
+        // column of the char in its line
+        let mut col = 0;
+        let line_lens: Vec<usize> = self
+            .buffer
+            .to_string()
+            .split('\n')
+            .map(|line| line.chars().count())
+            .collect();
+        let selection = self.buffer.selection().unwrap_or(0..0);
         for elem in printer {
             match elem.string_type {
                 PrinterItemType::Custom(color) => {
                     let _ = self.raw_terminal.set_fg(color);
 
                     for c in elem.string.chars() {
-                        self.write(&c.to_string(), color)?;
+                        let c = if self.options.horizontal_scroll {
+                            match self.scrolled_input_char(c, col, line_lens[line - 1]) {
+                                Some(c) => c,
+                                None => {
+                                    idx += 1;
+                                    col += 1;
+                                    continue;
+                                }
+                            }
+                        } else {
+                            c
+                        };
+                        if selection.contains(&idx) {
+                            self.raw_terminal
+                                .write(style(c).with(color).attr(Attribute::Reverse))?;
+                            self.cursor.move_right_unbounded();
+                        } else if underline.contains(&idx) {
+                            self.raw_terminal.write(
+                                style(c)
+                                    .with(self.options.err_color)
+                                    .attr(Attribute::Underlined),
+                            )?;
+                            self.cursor.move_right_unbounded();
+                        } else {
+                            // rust-analyzer colors take precedence over the lexical ones
+                            let color = semantic
+                                .iter()
+                                .find(|(range, _)| range.contains(&idx))
+                                .map_or(color, |(_, color)| *color);
+                            self.write(&c.to_string(), color)?;
+                        }
+                        idx += 1;
+                        col += 1;
                         if self.cursor.is_at_col(super::INPUT_START_COL) {
-                            self.write_from_terminal_start("..: ", Color::Yellow)?;
+                            let (prompt, color) = self.continuation_prompt(None);
+                            self.write_from_terminal_start(&prompt, color)?;
                         }
                     }
                 }
                 PrinterItemType::NewLine => {
+                    idx += 1;
+                    line += 1;
+                    col = 0;
                     self.cursor.bound_current_row_at_current_col();
                     self.cursor.goto_next_row_terminal_start();
-                    self.write("..: ", Color::Yellow)?;
+                    let (prompt, color) = self.continuation_prompt(Some(line));
+                    self.write(&prompt, color)?;
                 }
                 _ => {}
             }
@@ -150,47 +376,308 @@ impl IRust {
         Ok(())
     }
 
-    pub fn print_output(&mut self, printer: Printer) -> Result<(), IRustError> {
+    // the char shown at `col` of an input line with `horizontal_scroll`, None if it's hidden,
+    // `<` and `>` show that the line continues to the left or to the right
+    fn scrolled_input_char(&self, c: char, col: usize, line_len: usize) -> Option<char> {
+        let offset = self.input_offset;
+        let last_col = offset + self.input_view_width() - 1;
+        if col < offset || col > last_col {
+            None
+        } else if col == offset && offset > 0 {
+            Some('<')
+        } else if col == last_col && line_len > last_col + 1 {
+            Some('>')
+        } else {
+            Some(c)
+        }
+    }
+
+    // the last column is left empty so the cursor never wraps to the next row
+    fn input_view_width(&self) -> usize {
+        self.cursor
+            .bound
+            .width
+            .saturating_sub(super::INPUT_START_COL + 1)
+            .max(2)
+    }
+
+    /// Redraw the parts of the input depending on the cursor position after it moved,
+    /// the relative line numbers and the columns shown with `horizontal_scroll`
+    pub fn follow_cursor(&mut self) -> Result<(), IRustError> {
+        if self.options.line_numbers && self.cursor_line() != self.numbered_line {
+            self.print_input()?;
+        }
+        if self.options.horizontal_scroll {
+            self.scroll_input()?;
+        }
+        Ok(())
+    }
+
+    // 1-based
+    fn cursor_line(&self) -> usize {
+        self.buffer.buffer[..self.buffer.buffer_pos]
+            .iter()
+            .filter(|c| **c == '\n')
+            .count()
+            + 1
+    }
+
+    /// With `horizontal_scroll` the input lines are scrolled to keep the cursor in view
+    pub fn scroll_input(&mut self) -> Result<(), IRustError> {
+        let x = self
+            .buffer
+            .buffer_pos_to_relative_cursor_pos(self.buffer.buffer_pos)
+            .0;
+        let width = self.input_view_width();
+        // the marker columns hide the cursor too
+        let hidden =
+            (x <= self.input_offset && self.input_offset > 0) || x >= self.input_offset + width - 1;
+        if hidden {
+            self.input_offset = x.saturating_sub(width / 2);
+            self.print_input()?;
+        }
+        self.goto_buffer_pos();
+        Ok(())
+    }
+
+    // `line` is None for the rows of a wrapped line
+    fn continuation_prompt(&self, line: Option<usize>) -> (String, Color) {
+        if !self.options.line_numbers {
+            return ("..: ".to_string(), Color::Yellow);
+        }
+        let prompt = match line {
+            // the line of the cursor shows its number, the others their distance to it
+            Some(line) => {
+                let number = if line == self.numbered_line {
+                    line
+                } else {
+                    line.max(self.numbered_line) - line.min(self.numbered_line)
+                };
+                // clamped to the prompt width
+                let max = 10usize.pow(super::INPUT_START_COL as u32 - 1) - 1;
+                format!(
+                    "{:>width$} ",
+                    number.min(max),
+                    width = super::INPUT_START_COL - 1
+                )
+            }
+            None => " ".repeat(super::INPUT_START_COL),
+        };
+        (prompt, self.options.line_numbers_color)
+    }
+
+    pub fn print_output(&mut self, mut printer: Printer) -> Result<(), IRustError> {
+        // huge outputs are cut, the rest is shown by `:more`
+        if let Some(rest) =
+            printer.truncate(self.options.output_max_lines, self.options.output_max_bytes)
+        {
+            printer.add_new_line(1);
+            printer.push(PrinterItem::new(
+                format!("… {} more lines, :more to view", rest.lines_count().max(1)),
+                PrinterItemType::Warn,
+            ));
+            printer.add_new_line(1);
+            self.more_output = Some(rest);
+        }
+
+        if self.options.accessible {
+            return Self::print_plain(printer);
+        }
+        if self.pinned_input() {
+            return self.print_pinned(printer);
+        }
         self.scroll_if_needed_for_printer(&printer);
 
+        if self.options.horizontal_scroll {
+            let rows = self.print_output_inner(printer.clone(), 0)?;
+            let end = self.cursor.pos.current_pos.1;
+            self.scrolled_output = Some(ScrolledOutput {
+                printer,
+                rows: end.saturating_sub(rows)..end,
+                offset: 0,
+            });
+            Ok(())
+        } else {
+            self.print_output_inner(printer, 0).map(|_| ())
+        }
+    }
+
+    /// Linear output without colors for the accessible mode
+    fn print_plain(printer: Printer) -> Result<(), IRustError> {
+        let text = printer.to_plain_text();
+        // one line break after the output, whatever the item kinds
+        let text = text.trim_end_matches('\n').to_owned() + "\n";
+        let mut stdout = std::io::stdout();
+        stdout.write_all(text.as_bytes())?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Redraw the last output scrolled to the left or to the right (`horizontal_scroll` option)
+    pub fn scroll_output(&mut self, right: bool) -> Result<(), IRustError> {
+        let mut scrolled = match self.scrolled_output.take() {
+            Some(scrolled) => scrolled,
+            None => return Ok(()),
+        };
+        let step = self.cursor.bound.width / 2;
+        scrolled.offset = if right {
+            scrolled.offset + step
+        } else {
+            scrolled.offset.saturating_sub(step)
+        };
+
+        let pos = self.cursor.pos.clone();
+        self.cursor.hide();
+        for row in scrolled.rows.clone() {
+            self.cursor.goto(0, row);
+            self.raw_terminal.clear(ClearType::CurrentLine)?;
+        }
+        self.cursor.goto(0, scrolled.rows.start);
+        self.print_output_inner(scrolled.printer.clone(), scrolled.offset)?;
+        self.raw_terminal.reset_color()?;
+        self.cursor.pos = pos;
+        self.cursor.goto_internal_pos()?;
+        self.cursor.show();
+
+        self.scrolled_output = Some(scrolled);
+        Ok(())
+    }
+
+    // returns the number of rows the cursor moved down
+    fn print_output_inner(&mut self, printer: Printer, offset: usize) -> Result<usize, IRustError> {
+        let mut rows = 0;
         for output in printer {
-            let color = match output.string_type {
-                PrinterItemType::Eval => self.options.eval_color,
-                PrinterItemType::Ok => self.options.ok_color,
-                PrinterItemType::_IRust => self.options.irust_color,
-                PrinterItemType::Warn => self.options.irust_warn_color,
-                PrinterItemType::Out => self.options.out_color,
-                PrinterItemType::Shell => self.options.shell_color,
-                PrinterItemType::Err => self.options.err_color,
-                PrinterItemType::Custom(color) => color,
-                PrinterItemType::NewLine => {
-                    self.cursor.goto_next_row_terminal_start();
+            let color = match self.item_color(&output.string_type) {
+                Some(color) => color,
+                None => {
+                    self.goto_next_output_row();
                     self.cursor.use_current_row_as_starting_row();
+                    rows += 1;
                     continue;
                 }
             };
 
             self.raw_terminal.set_fg(color)?;
             if StringTools::is_multiline(&output.string) {
-                self.cursor.goto_next_row_terminal_start();
-                output.string.split('\n').for_each(|line| {
-                    let _ = self.raw_terminal.write(line);
-                    let _ = self.raw_terminal.write("\r\n");
-                    self.cursor.pos.current_pos.1 += 1;
-                });
+                self.goto_next_output_row();
+                let string = self.layout(&output.string, 0, offset);
+                rows += 1 + StringTools::new_lines_count(&string) + 1;
+                for line in string.split('\n') {
+                    self.raw_terminal.write(line)?;
+                    self.goto_next_output_row();
+                }
             } else {
-                self.raw_terminal.write(&output.string)?;
+                // a long line is wrapped by us with the columns each char takes,
+                // so the cursor knows where the output ends
+                let start = self.cursor.pos.current_pos.0;
+                let string = self.layout(&output.string, start, offset);
+                rows += StringTools::new_lines_count(&string);
+                for (idx, line) in string.split('\n').enumerate() {
+                    if idx != 0 {
+                        self.goto_next_output_row();
+                    }
+                    self.raw_terminal.write(line)?;
+                }
+                self.cursor.pos.current_pos.0 = if string.contains('\n') {
+                    last_line_width(&string)
+                } else {
+                    start + last_line_width(&string)
+                };
             }
-            self.scroll_if_needed_for_output(&output.string)?;
         }
 
+        Ok(rows)
+    }
+
+    /// Show the output in the user pager (`less -R` by default)
+    pub fn page(&mut self, printer: &Printer) -> Result<(), IRustError> {
+        // the output is printed as is instead
+        if self.options.accessible {
+            return Err(IRustError::Custom(
+                "No pager in accessible mode".to_string(),
+            ));
+        }
+        let text: String = printer
+            .iter()
+            .map(|item| match self.item_color(&item.string_type) {
+                Some(color) => style(&item.string).with(color).to_string(),
+                None => "\n".to_string(),
+            })
+            .collect();
+
+        // the pager reads the keys from the terminal
+        self.pause_input();
+        let paged = run_pager(&text);
+        self.resume_input();
+        paged?;
+
         Ok(())
     }
 
+    fn layout(&self, text: &str, col: usize, offset: usize) -> String {
+        if self.options.horizontal_scroll {
+            return truncate(
+                text,
+                col,
+                self.cursor.bound.width,
+                self.options.tab_width,
+                offset,
+            );
+        }
+        layout(
+            text,
+            col,
+            self.cursor.bound.width,
+            self.options.tab_width,
+            self.options.word_wrap,
+        )
+    }
+
+    // None for new lines
+    /// Write the printer on the current row, cut after `width` chars, it stops at the first new line
+    pub fn print_clipped(&mut self, printer: Printer, width: usize) -> Result<(), IRustError> {
+        let mut remaining = width;
+        for item in printer {
+            let color = match self.item_color(&item.string_type) {
+                Some(color) => color,
+                None => break,
+            };
+            let text: String = item
+                .string
+                .chars()
+                .filter(|c| *c != '\n')
+                .map(|c| if c == '\t' { ' ' } else { c })
+                .take(remaining)
+                .collect();
+            remaining -= text.chars().count();
+            self.raw_terminal.write_with_color(text, color)?;
+            if remaining == 0 {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn item_color(&self, item_type: &PrinterItemType) -> Option<Color> {
+        match item_type {
+            PrinterItemType::Eval => Some(self.options.eval_color),
+            PrinterItemType::Ok => Some(self.options.ok_color),
+            PrinterItemType::_IRust => Some(self.options.irust_color),
+            PrinterItemType::Warn => Some(self.options.irust_warn_color),
+            PrinterItemType::Out => Some(self.options.out_color),
+            PrinterItemType::Shell => Some(self.options.shell_color),
+            PrinterItemType::Err => Some(self.options.err_color),
+            PrinterItemType::Custom(color) => Some(*color),
+            PrinterItemType::NewLine => None,
+        }
+    }
+
     // scrolling fns
 
     fn scroll_if_needed_for_input(&mut self) {
-        let input_last_row = self.cursor.input_last_pos(&self.buffer).1;
+        // the diagnostic message takes one more row
+        let input_last_row =
+            self.cursor.input_last_pos(&self.buffer).1 + self.input_diagnostic().is_some() as usize;
         let height_overflow = input_last_row.saturating_sub(self.cursor.bound.height - 1);
         if height_overflow > 0 {
             self.scroll_up(height_overflow);
@@ -209,16 +696,31 @@ impl IRust {
             self.scroll_up(height_overflow);
         }
     }
-    fn scroll_if_needed_for_output(&mut self, output: &str) -> Result<(), IRustError> {
-        // check if we did scroll automatically
-        // if we did scroll the terminal by another row
-        // and update current_pos.1 to the height of the terminal (-1)
-        let new_lines = StringTools::new_lines_count(output);
-        let height_overflow = self.cursor.screen_height_overflow_by_new_lines(new_lines);
-        if height_overflow > 0 {
-            self.raw_terminal.scroll_up(1)?;
-            self.cursor.pos.current_pos.1 = self.cursor.bound.height - 1;
+
+    // an output taller than the rows left scrolls the screen while it's written,
+    // so the cursor row always matches the terminal one
+    fn goto_next_output_row(&mut self) {
+        if self.cursor.pos.current_pos.1 + 1 >= self.cursor.bound.height {
+            self.scroll_up(1);
         }
-        Ok(())
+        self.cursor.goto_next_row_terminal_start();
     }
 }
+
+fn run_pager(text: &str) -> std::io::Result<()> {
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| "less -R".to_string());
+    let mut pager = pager.split_whitespace();
+    let mut pager = std::process::Command::new(pager.next().unwrap_or("less"))
+        .args(pager)
+        .env(
+            "LESS",
+            std::env::var("LESS").unwrap_or_else(|_| "R".to_string()),
+        )
+        .stdin(std::process::Stdio::piped())
+        .spawn()?;
+
+    // the pager may quit before reading everything
+    let _ = pager.stdin.take().unwrap().write_all(text.as_bytes());
+    pager.wait()?;
+    Ok(())
+}
@@ -0,0 +1,252 @@
+#[derive(Clone)]
+enum Token {
+    Char(char),
+    // terminal escape sequences (colors, links..) take no column
+    Escape(String),
+}
+
+/// Expand the tabs of `text` and wrap its lines at `width` columns,
+/// `col` is the column where the text starts
+pub fn layout(text: &str, col: usize, width: usize, tab_width: usize, word_wrap: bool) -> String {
+    let mut out = String::new();
+    for (idx, line) in text.split('\n').enumerate() {
+        let col = if idx == 0 {
+            col
+        } else {
+            out.push('\n');
+            0
+        };
+        wrap_line(
+            &tokenize(line, col, tab_width),
+            col,
+            width,
+            word_wrap,
+            &mut out,
+        );
+    }
+    out
+}
+
+/// Expand the tabs of `text` and cut its lines at `width` columns after skipping `offset` columns,
+/// `<` and `>` show that a line continues to the left or to the right
+pub fn truncate(text: &str, col: usize, width: usize, tab_width: usize, offset: usize) -> String {
+    let mut out = String::new();
+    for (idx, line) in text.split('\n').enumerate() {
+        let col = if idx == 0 {
+            col
+        } else {
+            out.push('\n');
+            0
+        };
+        let tokens = tokenize(line, col, tab_width);
+        let visible = columns(&tokens);
+        let available = width.saturating_sub(col);
+
+        let mut shown = 0;
+        let mut skipped = 0;
+        for token in &tokens {
+            match token {
+                // keep the colors of the hidden part
+                Token::Escape(seq) => out.push_str(seq),
+                Token::Char(c) if skipped < offset => skipped += char_width(*c),
+                // a wide char that doesn't fit is not shown
+                Token::Char(c) if shown + char_width(*c) <= available => {
+                    let c = if shown == 0 && offset > 0 {
+                        '<'
+                    } else if shown + char_width(*c) == available && offset + available < visible {
+                        '>'
+                    } else {
+                        *c
+                    };
+                    out.push(c);
+                    shown += char_width(c);
+                }
+                Token::Char(_) => (),
+            }
+        }
+    }
+    out
+}
+
+/// Columns taken by the last line of a text returned by `layout`
+pub fn last_line_width(text: &str) -> usize {
+    columns(&tokenize(
+        text.rsplit('\n').next().unwrap_or_default(),
+        0,
+        1,
+    ))
+}
+
+/// Columns taken by a character in the terminal, 0 for the combining and zero width ones
+/// and 2 for the wide east asian ones and the emojis
+pub fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x0300..=0x036F | 0x200B..=0x200F | 0x20D0..=0x20FF | 0xFE00..=0xFE0F | 0xFE20..=0xFE2F => {
+            0
+        }
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x2FFFD
+        | 0x30000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+fn columns(tokens: &[Token]) -> usize {
+    tokens
+        .iter()
+        .map(|token| match token {
+            Token::Char(c) => char_width(*c),
+            Token::Escape(_) => 0,
+        })
+        .sum()
+}
+
+/// The text without its terminal escape sequences
+pub fn strip_escapes(text: &str) -> String {
+    tokenize(text, 0, 1)
+        .into_iter()
+        .filter_map(|token| match token {
+            Token::Char(c) => Some(c),
+            Token::Escape(_) => None,
+        })
+        .collect()
+}
+
+fn tokenize(line: &str, mut col: usize, tab_width: usize) -> Vec<Token> {
+    let tab_width = std::cmp::max(tab_width, 1);
+    let mut tokens = vec![];
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => {
+                let mut seq = c.to_string();
+                match chars.peek() {
+                    // exp: \x1b[31m
+                    Some('[') => {
+                        for c in chars.by_ref() {
+                            seq.push(c);
+                            if c != '[' && ('@'..='~').contains(&c) {
+                                break;
+                            }
+                        }
+                    }
+                    // exp: \x1b]8;;url\x1b\\
+                    Some(']') => {
+                        for c in chars.by_ref() {
+                            seq.push(c);
+                            if c == '\x07' || seq.ends_with("\x1b\\") {
+                                break;
+                            }
+                        }
+                    }
+                    _ => (),
+                }
+                tokens.push(Token::Escape(seq));
+            }
+            '\t' => {
+                let spaces = tab_width - col % tab_width;
+                tokens.extend((0..spaces).map(|_| Token::Char(' ')));
+                col += spaces;
+            }
+            c => {
+                tokens.push(Token::Char(c));
+                col += char_width(c);
+            }
+        }
+    }
+    tokens
+}
+
+fn wrap_line(tokens: &[Token], mut col: usize, width: usize, word_wrap: bool, out: &mut String) {
+    let is_space = |token: &Token| matches!(token, Token::Char(c) if c.is_whitespace());
+
+    let mut previous_space = true;
+    for (idx, token) in tokens.iter().enumerate() {
+        let c = match token {
+            Token::Escape(seq) => {
+                out.push_str(seq);
+                continue;
+            }
+            Token::Char(c) => *c,
+        };
+
+        // move the whole word to the next line if it doesn't fit and it fits a line
+        if word_wrap && previous_space && !c.is_whitespace() {
+            let word_len = columns(
+                &tokens[idx..]
+                    .iter()
+                    .take_while(|token| !is_space(token))
+                    .cloned()
+                    .collect::<Vec<Token>>(),
+            );
+            if col != 0 && col + word_len > width && word_len <= width {
+                out.push('\n');
+                col = 0;
+            }
+        }
+        previous_space = c.is_whitespace();
+
+        // a wide char is moved to the next line instead of being split by the terminal
+        if col >= width || (col != 0 && col + char_width(c) > width) {
+            out.push('\n');
+            col = 0;
+            // the space where a line is broken is not shown
+            if word_wrap && c.is_whitespace() {
+                continue;
+            }
+        }
+        out.push(c);
+        col += char_width(c);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wrap(text: &str, col: usize, width: usize, word_wrap: bool) -> String {
+        let mut out = String::new();
+        wrap_line(&tokenize(text, col, 4), col, width, word_wrap, &mut out);
+        out
+    }
+
+    #[test]
+    fn wrap_line_at_width() {
+        assert_eq!(wrap("abcdefgh", 0, 3, false), "abc\ndef\ngh");
+        // the first row starts after the prompt
+        assert_eq!(wrap("abcdefgh", 2, 4, false), "ab\ncdef\ngh");
+    }
+
+    #[test]
+    fn wrap_line_at_words() {
+        // the space where the line is broken is dropped
+        assert_eq!(wrap("one two three", 0, 7, true), "one two\nthree");
+        // a word longer than the width is cut
+        assert_eq!(wrap("abcdefghij", 0, 4, true), "abcd\nefgh\nij");
+    }
+
+    #[test]
+    fn wrap_line_moves_wide_chars() {
+        assert_eq!(wrap("ab漢字", 0, 3, false), "ab\n漢\n字");
+    }
+
+    #[test]
+    fn wrap_line_keeps_escapes() {
+        assert_eq!(
+            wrap("\x1b[31mabcd\x1b[0m", 0, 2, false),
+            "\x1b[31mab\ncd\x1b[0m"
+        );
+    }
+}
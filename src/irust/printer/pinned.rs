@@ -0,0 +1,116 @@
+use super::{last_line_width, Printer, PrinterItem, PrinterItemType};
+use crate::irust::highlight::highlight;
+use crate::irust::{IRust, IRustError, IN};
+use crossterm::{style, ClearType, Color};
+
+// With the `pinned_input` option the input stays on the last rows of the screen,
+// the outputs are written above it in a scroll region (DECSTBM) so they never move the input
+
+impl IRust {
+    pub fn pinned_input(&self) -> bool {
+        self.options.pinned_input && !self.options.accessible
+    }
+
+    /// Move the input down to the last rows of the screen, a growing input scrolls the outputs up
+    pub fn pin_input(&mut self) -> Result<(), IRustError> {
+        // the diagnostic message takes one more row
+        let input_rows = self.cursor.input_last_pos(&self.buffer).1 + 1
+            - self.cursor.pos.starting_pos.1
+            + self.input_diagnostic().is_some() as usize;
+        let top = self.cursor.bound.height.saturating_sub(input_rows);
+        let start = self.cursor.pos.starting_pos.1;
+        if top <= start {
+            self.scroll_if_needed_for_input();
+            return Ok(());
+        }
+
+        // the rows the input leaves are emptied
+        self.cursor.goto(0, start);
+        self.raw_terminal.clear(ClearType::FromCursorDown)?;
+        let delta = top - start;
+        self.cursor.pos.starting_pos.1 += delta;
+        self.cursor.pos.current_pos.1 += delta;
+        self.cursor.goto_internal_pos()?;
+        Ok(())
+    }
+
+    /// Copy the input above the prompt, then empty the prompt for the evaluation progress
+    pub fn echo_input(&mut self) -> Result<(), IRustError> {
+        let mut printer = Printer::new(PrinterItem::new(
+            IN.to_string(),
+            PrinterItemType::Custom(Color::Yellow),
+        ));
+        for item in highlight(&self.buffer.to_string()) {
+            let new_line = item.string_type == PrinterItemType::NewLine;
+            printer.push(item);
+            if new_line {
+                printer.push(PrinterItem::new(
+                    "..: ".to_string(),
+                    PrinterItemType::Custom(Color::Yellow),
+                ));
+            }
+        }
+        self.print_pinned(printer)?;
+
+        self.cursor.goto_start();
+        self.raw_terminal.clear(ClearType::FromCursorDown)?;
+        self.cursor.goto(0, self.cursor.pos.starting_pos.1);
+        Ok(())
+    }
+
+    /// Write the printer after the last output, the rows above the input scroll when they are full
+    pub(super) fn print_pinned(&mut self, printer: Printer) -> Result<(), IRustError> {
+        let bottom = self.cursor.pos.starting_pos.1;
+        if bottom == 0 {
+            return Ok(());
+        }
+
+        let mut lines = vec![String::new()];
+        let mut col = 0;
+        for item in printer {
+            let color = match self.item_color(&item.string_type) {
+                Some(color) => color,
+                None => {
+                    lines.push(String::new());
+                    col = 0;
+                    continue;
+                }
+            };
+            // long lines are wrapped here, so every line takes one row
+            let text = self.layout(&item.string, col, 0);
+            for (idx, part) in text.split('\n').enumerate() {
+                if idx != 0 {
+                    lines.push(String::new());
+                    col = 0;
+                }
+                col += last_line_width(part);
+                if let Some(line) = lines.last_mut() {
+                    line.push_str(&style(part).with(color).to_string());
+                }
+            }
+        }
+        while lines.len() > 1 && lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+
+        let pos = self.cursor.pos.clone();
+        self.raw_terminal.set_scroll_region(0, bottom - 1)?;
+        for line in lines {
+            if self.output_row >= bottom {
+                // a line feed on the last row of the region scrolls the region only
+                self.cursor.goto(0, bottom - 1);
+                self.raw_terminal.write('\n')?;
+                self.output_row = bottom - 1;
+            }
+            self.cursor.goto(0, self.output_row);
+            self.raw_terminal.clear(ClearType::CurrentLine)?;
+            self.raw_terminal.write(line)?;
+            self.output_row += 1;
+        }
+        self.raw_terminal.reset_scroll_region()?;
+        self.cursor.pos = pos;
+        self.cursor.goto_internal_pos()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+use super::cargo_cmds::{cargo_flamegraph, TMP_DIR};
+use super::cmd_args::CmdArgs;
+use super::docs::open;
+use crate::irust::format::format_eval_output;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+use crate::utils::stdout_and_stderr;
+
+impl IRust {
+    pub fn profile(&mut self) -> Result<Printer, IRustError> {
+        // exp: :profile fib(35), :profile --open v.sort()
+        let mut args = CmdArgs::parse_code("profile", &self.buffer.to_string());
+        let open_svg = args.take_flag("--open");
+        let expression = args.expression();
+        if expression.is_empty() {
+            return Err(IRustError::Custom("No expression specified".to_string()));
+        }
+
+        let svg = TMP_DIR.join("irust_flamegraph.svg");
+        let _ = std::fs::remove_file(&svg);
+        let statement = self.repl.eval_statement(expression);
+        let job = self
+            .repl
+            .eval_in_tmp_repl(statement, || Ok(cargo_flamegraph(&svg)?))?;
+        let output = self.wait_job(job, "Profiling")?;
+
+        if !svg.exists() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stderr.contains("no such command") {
+                return Err(IRustError::Custom(
+                    ":profile requires cargo-flamegraph (cargo install flamegraph) and perf or dtrace"
+                        .to_string(),
+                ));
+            }
+            let mut outputs = format_eval_output(&stdout_and_stderr(output));
+            outputs.add_new_line(1);
+            return Ok(outputs);
+        }
+
+        let mut outputs = format_eval_output(&String::from_utf8_lossy(&output.stdout));
+        outputs.add_new_line(1);
+        let msg = if open_svg && open(&svg.to_string_lossy()) {
+            "Opened flamegraph"
+        } else {
+            "Flamegraph written to"
+        };
+        outputs.push(PrinterItem::new(
+            format!("{}: {}", msg, svg.display()),
+            PrinterItemType::Ok,
+        ));
+        outputs.add_new_line(1);
+
+        Ok(outputs)
+    }
+}
@@ -1,3 +1,5 @@
+use super::completion::{self, Source, Suggestion};
+use super::help;
 use super::IRustError;
 use super::Options;
 use crate::irust::IRust;
@@ -6,6 +8,8 @@ use crossterm::ClearType;
 use std::env::temp_dir;
 use std::io::Write;
 use std::process::{Child, Command, Stdio};
+mod cache;
+use cache::{split_prefix, CompletionCache};
 
 pub enum Cycle {
     Up,
@@ -13,57 +17,100 @@ pub enum Cycle {
 }
 
 pub struct Racer {
-    process: Child,
+    // None when racer is disabled or missing, the other completion sources still work
+    process: Option<Child>,
     main_file: String,
     cursor: (usize, usize),
-    // suggestions: (Name, definition)
-    suggestions: Vec<(String, String)>,
+    suggestions: Vec<Suggestion>,
     suggestion_idx: usize,
-    cmds: [String; 8],
     update_lock: bool,
+    cache: CompletionCache,
 }
 
 impl Racer {
-    pub fn start() -> Result<Racer, IRustError> {
-        let process = Command::new("racer")
-            .arg("daemon")
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::null())
-            .spawn()
-            // Disable Racer if unable to start it
-            .map_err(|_| IRustError::RacerDisabled)?;
+    pub fn start(daemon: bool) -> Result<Racer, IRustError> {
+        let process = if daemon {
+            Some(Racer::spawn_daemon()?)
+        } else {
+            None
+        };
         let main_file = temp_dir()
             .join("irust/src/main.rs")
             .to_str()
             .unwrap()
             .to_owned();
         let cursor = (2, 0);
-        let cmds = [
-            "show".to_string(),
-            "help".to_string(),
-            "pop".to_string(),
-            "del".to_string(),
-            "add".to_string(),
-            "reset".to_string(),
-            "load".to_string(),
-            "type".to_string(),
-        ];
-
         let mut racer = Racer {
             process,
             main_file,
             cursor,
             suggestions: vec![],
             suggestion_idx: 0,
-            cmds,
             update_lock: false,
+            cache: CompletionCache::default(),
         };
         racer.complete_code()?;
 
         Ok(racer)
     }
 
+    /// The daemon is kept alive for the whole session and only restarted if it dies
+    fn spawn_daemon() -> Result<Child, IRustError> {
+        Command::new("racer")
+            .arg("daemon")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            // Disable Racer if unable to start it
+            .map_err(|_| IRustError::RacerDisabled)
+    }
+
+    fn request_completion(
+        process: &mut Child,
+        cursor: (usize, usize),
+        main_file: &str,
+    ) -> std::io::Result<()> {
+        writeln!(
+            process.stdin.as_mut().unwrap(),
+            "complete-with-snippet {} {} {}",
+            cursor.0,
+            cursor.1,
+            main_file
+        )
+    }
+
+    /// Returns true and loads the suggestions if this completion was already computed
+    fn load_cached(&mut self, body: &str, buffer: &str) -> bool {
+        if self.update_lock {
+            // locked suggestions are kept as they are
+            return true;
+        }
+        let (context, prefix) = split_prefix(buffer);
+        match self.cache.get(body, context, prefix) {
+            Some(suggestions) => {
+                self.suggestions = suggestions;
+                self.goto_first_suggestion();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn cache_suggestions(&mut self, buffer: &str) {
+        let (context, prefix) = split_prefix(buffer);
+        self.cache.insert(
+            context.to_owned(),
+            prefix.to_owned(),
+            self.suggestions.clone(),
+        );
+    }
+
+    /// Needed when the completions can change without the repl body changing (new dependencies)
+    pub fn invalidate_cache(&mut self) {
+        self.cache.invalidate();
+    }
+
     fn complete_code(&mut self) -> Result<(), IRustError> {
         // check for lock
         if self.update_lock {
@@ -74,14 +121,16 @@ impl Racer {
         self.suggestions.clear();
         self.goto_first_suggestion();
 
-        let stdin = self.process.stdin.as_mut().unwrap();
-        let stdout = self.process.stdout.as_mut().unwrap();
-
-        match writeln!(
-            stdin,
-            "complete {} {} {}",
-            self.cursor.0, self.cursor.1, self.main_file
-        ) {
+        let process = match self.process.as_mut() {
+            Some(process) => process,
+            None => return Ok(()),
+        };
+        if Racer::request_completion(process, self.cursor, &self.main_file).is_err() {
+            // the daemon died, try to restart it once
+            let _ = process.kill();
+            *process = Racer::spawn_daemon()?;
+        }
+        match Racer::request_completion(process, self.cursor, &self.main_file) {
             Ok(_) => (),
             Err(_) => {
                 let _ = Options::disable_racer();
@@ -95,6 +144,7 @@ impl Racer {
         };
 
         // read till END
+        let stdout = process.stdout.as_mut().unwrap();
         let mut raw_output = vec![];
         read_until_bytes(
             &mut std::io::BufReader::new(stdout),
@@ -107,16 +157,9 @@ impl Racer {
             if suggestion == "END" {
                 break;
             }
-            let mut try_parse = || -> Option<()> {
-                let start_idx = suggestion.find("MATCH ")? + 6;
-                let mut indices = suggestion.match_indices(',');
-                let name = suggestion[start_idx..indices.nth(0)?.0].to_owned();
-                let definition = suggestion[indices.nth(3)?.0..].to_owned();
-                self.suggestions.push((name, definition[1..].to_owned()));
-                Some(())
-            };
-
-            try_parse();
+            if let Some(suggestion) = Racer::parse_match(suggestion) {
+                self.suggestions.push(suggestion);
+            }
         }
 
         // remove duplicates
@@ -126,6 +169,44 @@ impl Racer {
         Ok(())
     }
 
+    // MATCH name;snippet;line;col;path;kind;context;"docs"
+    fn parse_match(suggestion: &str) -> Option<Suggestion> {
+        let suggestion = &suggestion[suggestion.find("MATCH ")? + 6..];
+
+        // docs are printed escaped and quoted, so they are found from the end
+        // even if the context contains `;`
+        let docs_start = Racer::quoted_docs_start(suggestion)?;
+        let docs = Racer::first_docs_line(&suggestion[docs_start + 1..suggestion.len() - 1]);
+
+        let mut fields = suggestion[..docs_start - 1].splitn(7, ';');
+        let name = fields.next()?.to_owned();
+        let definition = fields.nth(5)?.to_owned();
+
+        Some((name, definition, docs, Source::Racer))
+    }
+
+    fn quoted_docs_start(suggestion: &str) -> Option<usize> {
+        if !suggestion.ends_with('"') {
+            return None;
+        }
+        // quotes inside the docs are escaped, so the last `;"` starts them
+        suggestion[..suggestion.len() - 1]
+            .rfind(";\"")
+            .map(|idx| idx + 1)
+    }
+
+    fn first_docs_line(docs: &str) -> String {
+        let line = docs
+            .split("\\n")
+            .find(|l| !l.trim().is_empty())
+            .unwrap_or("");
+        line.replace("\\\"", "\"")
+            .replace("\\'", "'")
+            .replace("\\\\", "\\")
+            .trim()
+            .to_owned()
+    }
+
     fn goto_next_suggestion(&mut self) {
         if self.suggestion_idx >= self.suggestions.len() {
             self.suggestion_idx = 0
@@ -137,19 +218,19 @@ impl Racer {
         self.suggestion_idx = self
             .suggestion_idx
             .checked_sub(1)
-            .unwrap_or_else(|| self.suggestions.len());
+            .unwrap_or(self.suggestions.len());
         if self.suggestion_idx == 0 {
             self.suggestion_idx = self.suggestions.len();
         }
     }
 
-    fn current_suggestion(&self) -> Option<(String, String)> {
+    fn current_suggestion(&self) -> Option<Suggestion> {
         if self.suggestion_idx > 1 {
             self.suggestions
                 .get(self.suggestion_idx - 1)
                 .map(ToOwned::to_owned)
         } else {
-            self.suggestions.get(0).map(ToOwned::to_owned)
+            self.suggestions.first().map(ToOwned::to_owned)
         }
     }
 
@@ -157,7 +238,7 @@ impl Racer {
         self.suggestion_idx = 0;
     }
 
-    fn full_suggestion(s: &(String, String)) -> String {
+    fn full_suggestion(s: &Suggestion) -> String {
         if !s.1.is_empty() {
             s.0.to_owned() + ": " + &s.1
         } else {
@@ -172,7 +253,7 @@ impl IRust {
         let buffer: String = self.buffer.iter().take(self.buffer.buffer_pos).collect();
 
         // return if we're not at the end of the line
-        if !self.cursor.is_at_line_end(&self) {
+        if !self.cursor.is_at_line_end(self) {
             return Ok(());
         }
 
@@ -189,18 +270,14 @@ impl IRust {
     fn show_suggestions_inner(&mut self, buffer: String) -> Result<(), IRustError> {
         if buffer.starts_with(':') {
             // Auto complete IRust commands
-            self.racer.as_mut()?.suggestions = self
-                .racer
-                .as_ref()?
-                .cmds
-                .iter()
+            self.racer.as_mut()?.suggestions = help::command_names()
                 .filter(|c| c.starts_with(&buffer[1..]))
                 // place holder for IRust command definitions
-                .map(|c| (c.to_owned(), String::new()))
+                .map(|c| (c.to_owned(), String::new(), String::new(), Source::Command))
                 .collect();
         } else {
             // Auto complete rust code
-            let mut racer = self.racer.as_mut()?;
+            let racer = self.racer.as_mut()?;
 
             racer.cursor.0 = self.repl.body.len() + StringTools::new_lines_count(&buffer);
 
@@ -213,10 +290,36 @@ impl IRust {
                 }
             }
 
-            self.repl
-                .eval_in_tmp_repl(buffer, move || -> Result<(), IRustError> {
-                    racer.complete_code().map_err(From::from)
-                })?;
+            if racer.update_lock {
+                // locked suggestions are kept as they are
+                return Ok(());
+            }
+            let body = self.repl.body.join("\n");
+            if !racer.load_cached(&body, &buffer) {
+                self.repl
+                    .eval_in_tmp_repl(buffer.clone(), || -> Result<(), IRustError> {
+                        racer.complete_code()
+                    })?;
+                racer.cache_suggestions(&buffer);
+            }
+
+            let (context, prefix) = split_prefix(&buffer);
+            let mut sources = vec![std::mem::take(&mut racer.suggestions)];
+            // after `.` and `::` only the racer and history suggestions can fit
+            let member = context.ends_with('.') || context.ends_with("::");
+            if self.options.completion_session && !member {
+                sources.push(completion::session_candidates(&self.repl.body, prefix));
+            }
+            if self.options.completion_snippets && !member {
+                sources.push(completion::snippet_candidates(prefix));
+            }
+            if self.options.completion_history && !prefix.is_empty() {
+                sources.push(completion::history_candidates(
+                    self.history.entries(),
+                    prefix,
+                ));
+            }
+            racer.suggestions = completion::merge(prefix, sources);
 
             // reset debouncer
             self.debouncer.reset_timer();
@@ -246,36 +349,57 @@ impl IRust {
     }
 
     fn write_current_suggestion(&mut self) -> Result<(), IRustError> {
-        if !self.cursor.is_at_line_end(&self) {
+        // the inline suggestion is drawn after the input
+        if !self.buffer.is_at_end() {
             return Ok(());
         }
 
-        if let Some(suggestion) = self.racer.as_ref()?.current_suggestion() {
-            self.raw_terminal.clear(ClearType::UntilNewLine)?;
-
-            let mut suggestion = suggestion.0;
+        match self.racer.as_ref()?.current_suggestion() {
+            Some(suggestion) => {
+                self.inline_suggestion = Some((self.buffer.to_string(), suggestion.0));
 
-            let buffer: String = self.buffer.iter().take(self.buffer.buffer_pos).collect();
-            StringTools::strings_unique(&buffer, &mut suggestion);
-
-            // scroll if needed
-            let height_overflow = self.cursor.screen_height_overflow_by_str(&suggestion);
-            if height_overflow > 0 {
-                self.scroll_up(height_overflow);
+                // scroll if needed
+                if let Some(rest) = self.inline_suggestion_rest() {
+                    let height_overflow = self.cursor.screen_height_overflow_by_str(&rest);
+                    if height_overflow > 0 {
+                        self.scroll_up(height_overflow);
+                    }
+                }
+                self.print_input()?;
             }
+            None => self.clear_inline_suggestion()?,
+        }
 
-            self.cursor.hide();
-            self.raw_terminal
-                .set_fg(self.options.racer_inline_suggestion_color)?;
-            self.cursor.cursor.save_position()?;
+        Ok(())
+    }
 
-            self.raw_terminal.write(&suggestion)?;
+    /// The part of the inline suggestion that is not typed yet, the suggestion is dropped
+    /// when the input diverged from it
+    pub fn inline_suggestion_rest(&mut self) -> Option<String> {
+        let (suggested_for, mut suggestion) = self.inline_suggestion.clone()?;
+        StringTools::strings_unique(&suggested_for, &mut suggestion);
 
-            self.cursor.cursor.restore_position()?;
-            self.raw_terminal.reset_color()?;
-            self.cursor.show();
+        // only typing the suggestion keeps it
+        let buffer = self.buffer.to_string();
+        let rest = if buffer.starts_with(&suggested_for) {
+            suggestion.strip_prefix(&buffer[suggested_for.len()..])
+        } else {
+            None
+        };
+        match rest {
+            Some(rest) if !rest.is_empty() => Some(rest.to_owned()),
+            _ => {
+                self.inline_suggestion = None;
+                None
+            }
         }
+    }
 
+    /// Erase the inline suggestion and the suggestions table, exp: before the input is submitted
+    pub fn clear_inline_suggestion(&mut self) -> Result<(), IRustError> {
+        if self.inline_suggestion.take().is_some() {
+            self.print_input()?;
+        }
         Ok(())
     }
 
@@ -297,10 +421,17 @@ impl IRust {
             self.options.racer_max_suggestions,
         );
 
+        let current_suggestion = self.racer.as_ref()?.current_suggestion();
+        // one more row for the docs of the selected suggestion
+        let docs = current_suggestion
+            .as_ref()
+            .map(|s| s.2.clone())
+            .filter(|docs| !docs.is_empty());
+
         // Handle screen height overflow
         let height_overflow = self
             .cursor
-            .screen_height_overflow_by_new_lines(suggestions_num + 1);
+            .screen_height_overflow_by_new_lines(suggestions_num + 1 + docs.is_some() as usize);
 
         if height_overflow != 0 {
             self.scroll_up(height_overflow);
@@ -318,7 +449,6 @@ impl IRust {
 
         self.raw_terminal
             .set_fg(self.options.racer_suggestions_table_color)?;
-        let current_suggestion = self.racer.as_ref()?.current_suggestion();
 
         for (idx, suggestion) in self
             .racer
@@ -330,12 +460,14 @@ impl IRust {
             .enumerate()
         {
             let suggestion_c = suggestion.clone();
-            // trancuate long suggestions
+            let tag = format!(" [{}]", suggestion.3.tag());
+            // trancuate long suggestions, the source tag is always shown
             let mut suggestion = Racer::full_suggestion(suggestion);
-            if suggestion.len() > max_width {
-                suggestion.truncate(max_width - 3);
-                suggestion.push_str("...");
+            let width = max_width.saturating_sub(tag.len()).max(4);
+            if suggestion.chars().count() > width {
+                suggestion = suggestion.chars().take(width - 3).collect::<String>() + "...";
             }
+            suggestion.push_str(&tag);
             // move one + idx row down
             self.cursor.cursor.move_down(idx as u16 + 1)?;
 
@@ -353,6 +485,28 @@ impl IRust {
             self.cursor.move_up(idx as u16 + 1);
         }
 
+        if let Some(mut docs) = docs {
+            let rows = self
+                .racer
+                .as_ref()?
+                .suggestions
+                .len()
+                .saturating_sub(
+                    ((self.racer.as_ref()?.suggestion_idx - 1) / suggestions_num) * suggestions_num,
+                )
+                .min(suggestions_num);
+            if docs.chars().count() > max_width {
+                docs = docs.chars().take(max_width - 3).collect::<String>() + "...";
+            }
+
+            self.cursor.cursor.move_down(rows as u16 + 1)?;
+            self.cursor.cursor.save_position()?;
+            self.raw_terminal
+                .write_with_color(&docs, self.options.racer_suggestion_docs_color)?;
+            self.cursor.cursor.restore_position()?;
+            self.cursor.move_up(rows as u16 + 1);
+        }
+
         // reset to input position and color
         self.raw_terminal.reset_color()?;
         self.cursor.restore_position()?;
@@ -362,21 +516,23 @@ impl IRust {
     }
 
     pub fn use_suggestion(&mut self) -> Result<(), IRustError> {
-        if let Some(suggestion) = self.racer.as_ref()?.current_suggestion() {
-            // suggestion => `name: definition`
-            // suggestion example => `assert!: macro_rules! assert {`
-
-            // get the name
-            let mut suggestion = suggestion.0;
-
-            // get the unique part of the name
-            let buffer: String = self
-                .buffer
-                .buffer
-                .iter()
-                .take(self.buffer.buffer_pos)
-                .collect();
-            StringTools::strings_unique(&buffer, &mut suggestion);
+        self.use_suggestion_inner(false)
+    }
+
+    /// Accept only the next word of the suggestion, exp: `_str` from `push_str` after `push`
+    pub fn use_suggestion_word(&mut self) -> Result<(), IRustError> {
+        self.use_suggestion_inner(true)
+    }
+
+    fn use_suggestion_inner(&mut self, next_word_only: bool) -> Result<(), IRustError> {
+        // only the suggestion shown after the input can be accepted
+        if !self.buffer.is_at_end() {
+            return Ok(());
+        }
+        if let Some(mut suggestion) = self.inline_suggestion_rest() {
+            if next_word_only {
+                suggestion = StringTools::next_word(&suggestion).to_owned();
+            }
 
             self.buffer.insert_str(&suggestion);
             let chars_count = StringTools::chars_count(&suggestion);
@@ -385,12 +541,25 @@ impl IRust {
                 self.cursor.move_right_unbounded();
             }
 
+            // an accepted word keeps the rest of the suggestion shown
+            if !next_word_only {
+                self.inline_suggestion = None;
+            }
             self.print_input()?;
         }
 
         Ok(())
     }
 
+    /// Characters of the word before the cursor
+    fn typed_word_len(&self) -> usize {
+        self.buffer.buffer[..self.buffer.buffer_pos]
+            .iter()
+            .rev()
+            .take_while(|c| c.is_alphanumeric() || **c == '_')
+            .count()
+    }
+
     pub fn lock_racer_update(&mut self) -> Result<(), IRustError> {
         self.racer.as_mut()?.update_lock = true;
         Ok(())
@@ -407,8 +576,12 @@ impl IRust {
 
     pub fn check_racer_callback(&mut self) -> Result<(), IRustError> {
         let mut inner = || -> Result<(), IRustError> {
+            if self.options.racer_trigger == "tab" {
+                return Ok(());
+            }
             if let Some(character) = self.buffer.previous_char() {
                 if character.is_alphanumeric()
+                    && self.typed_word_len() >= self.options.racer_min_chars
                     && !self.racer_update_locked()?
                     && self.debouncer.recv.try_recv().is_ok()
                 {
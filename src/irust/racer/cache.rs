@@ -0,0 +1,68 @@
+use super::Suggestion;
+use std::collections::HashMap;
+
+const MAX_ENTRIES: usize = 256;
+
+/// Completions are cached by (context, prefix), where prefix is the identifier being typed
+/// and context is what comes before it, entries are only valid for one repl body
+#[derive(Default)]
+pub struct CompletionCache {
+    file_state: String,
+    entries: HashMap<(String, String), Vec<Suggestion>>,
+}
+
+impl CompletionCache {
+    pub fn get(
+        &mut self,
+        file_state: &str,
+        context: &str,
+        prefix: &str,
+    ) -> Option<Vec<Suggestion>> {
+        if self.file_state != file_state {
+            self.invalidate();
+            self.file_state = file_state.to_owned();
+            return None;
+        }
+
+        if let Some(suggestions) = self.entries.get(&(context.to_owned(), prefix.to_owned())) {
+            return Some(suggestions.clone());
+        }
+
+        // racer matches by prefix, so the suggestions for `ve` contain the ones for `vec`
+        self.entries
+            .iter()
+            .filter(|((c, p), _)| c == context && !p.is_empty() && prefix.starts_with(p.as_str()))
+            .max_by_key(|((_, p), _)| p.len())
+            .map(|(_, suggestions)| {
+                suggestions
+                    .iter()
+                    .filter(|(name, _, _, _)| name.starts_with(prefix))
+                    .cloned()
+                    .collect()
+            })
+    }
+
+    pub fn insert(&mut self, context: String, prefix: String, suggestions: Vec<Suggestion>) {
+        if self.entries.len() >= MAX_ENTRIES {
+            self.entries.clear();
+        }
+        self.entries.insert((context, prefix), suggestions);
+    }
+
+    pub fn invalidate(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Split the input into (context, identifier prefix under the cursor)
+pub fn split_prefix(buffer: &str) -> (&str, &str) {
+    let prefix_start = buffer
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+        .last()
+        .map(|(idx, _)| idx)
+        .unwrap_or_else(|| buffer.len());
+
+    buffer.split_at(prefix_start)
+}
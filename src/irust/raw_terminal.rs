@@ -20,6 +20,18 @@ impl RawTerminal {
         Ok(())
     }
 
+    /// Limit the scrolling to the rows `top..=bottom` (DECSTBM), the cursor goes to the top left
+    pub fn set_scroll_region(&self, top: usize, bottom: usize) -> Result<(), IRustError> {
+        self.terminal
+            .write(format!("\x1b[{};{}r", top + 1, bottom + 1))?;
+        Ok(())
+    }
+
+    pub fn reset_scroll_region(&self) -> Result<(), IRustError> {
+        self.terminal.write("\x1b[r")?;
+        Ok(())
+    }
+
     pub fn clear(&self, clear_type: ClearType) -> Result<(), IRustError> {
         self.terminal.clear(clear_type)?;
         Ok(())
@@ -56,6 +68,29 @@ impl RawTerminal {
         Ok(())
     }
 
+    /// Give the terminal back in its normal mode, exp: to an interactive command
+    pub fn leave_raw_mode(&self) -> Result<(), IRustError> {
+        crossterm::RawScreen::disable_raw_mode()?;
+        Ok(())
+    }
+
+    pub fn enter_raw_mode(&self) -> Result<(), IRustError> {
+        // disabled by the screen of `IRust::run` when it returns
+        crossterm::RawScreen::into_raw_mode()?.keep_raw_mode_on_drop();
+        Ok(())
+    }
+
+    /// Put the terminal back in its normal state, without an `IRust` instance
+    pub fn restore() {
+        let terminal = Terminal::new();
+        let _ = crossterm::RawScreen::disable_raw_mode();
+        let _ = TerminalColor::new().reset();
+        let _ = crossterm::TerminalCursor::new().show();
+        // reset the scroll region of the pinned input, it moves the cursor so it's saved around
+        let _ = terminal.write("\x1b7\x1b[r\x1b8");
+        let _ = terminal.write("\n");
+    }
+
     pub fn exit(status: i32) {
         let _ = crossterm::RawScreen::disable_raw_mode();
         std::process::exit(status);
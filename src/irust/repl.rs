@@ -1,23 +1,87 @@
 use super::cargo_cmds::*;
+use super::classify::{classify, InputKind, LetBinding};
 use super::IRustError;
 use std::io::{self, Write};
 
+const STATE_MARK: &str = "##IRustState##";
+const TYPE_MARK: &str = "##IRustType##";
+const MAIN_FN: &str = "fn main() {";
+const LAST_OUT_NAME: &str = "__irust_last_out";
+// the output of the last evaluation, it's not reported as unused
+pub const LAST_OUT_BINDING: &str = "#[allow(unused_variables)] let __irust_last_out";
+
+/// The `eval_template` option split around its placeholders, exp:
+/// `fn main() { env_logger::init(); {body} {expression} eprintln!("done"); }`
+#[derive(Clone)]
+pub struct EvalTemplate {
+    main: String,
+    before_expression: String,
+    end: String,
+}
+
+impl EvalTemplate {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let (main, rest) = template
+            .split_once("{body}")
+            .ok_or_else(|| "missing the {body} placeholder".to_string())?;
+        if main.contains("{expression}") {
+            return Err("{expression} must come after {body}".to_string());
+        }
+        let (before_expression, end) = rest.split_once("{expression}").unwrap_or(("", rest));
+        Ok(Self {
+            main: main.trim().to_owned(),
+            before_expression: before_expression.trim().to_owned(),
+            end: end.trim().to_owned(),
+        })
+    }
+
+    // the repl code line numbers are kept: the template start replaces `fn main() {`,
+    // its end the closing brace and the code before `{expression}` starts the evaluated input line
+    fn apply(&self, body: &mut [String], input_start: usize) {
+        if let Some(main) = body
+            .iter_mut()
+            .find(|l| l.trim_start().starts_with(MAIN_FN))
+        {
+            *main = main.replacen(MAIN_FN, &self.main, 1);
+        }
+        if let Some(line) = body.get_mut(input_start) {
+            line.insert_str(0, &format!("{} ", self.before_expression));
+        }
+        if let Some(end) = body.last_mut() {
+            *end = self.end.clone();
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Repl {
     pub body: Vec<String>,
     cursor: usize,
+    /// stdout of the last successful evaluation, available to the next ones as `__irust_last_out`
+    pub last_out: String,
+    /// print the evaluated values with `Display` when they implement it, `Debug` otherwise
+    pub prefer_display: bool,
+    /// print the type of the evaluated values too, see `take_value_type`
+    pub show_types: bool,
+    /// setup and teardown code around the evaluations, see `EvalTemplate`
+    pub template: Option<EvalTemplate>,
 }
 
 impl Repl {
     pub fn new() -> Self {
         Self {
-            body: vec!["fn main() {".to_string(), "}".to_string()],
+            body: vec![MAIN_FN.to_string(), "}".to_string()],
             cursor: 1,
+            last_out: String::new(),
+            prefer_display: false,
+            show_types: false,
+            template: None,
         }
     }
 
     pub fn update_from_main_file(&mut self) -> Result<(), IRustError> {
         let main_file = std::fs::read_to_string(&*MAIN_FILE)?;
+        let main_file = Self::without_allocator(&main_file);
         let lines_num = main_file.lines().count();
         if lines_num < 2 {
             return Err(IRustError::Custom(
@@ -29,10 +93,20 @@ impl Repl {
         *self = Self {
             body: main_file.lines().map(ToOwned::to_owned).collect(),
             cursor: cursor_pos,
+            last_out: std::mem::take(&mut self.last_out),
+            prefer_display: self.prefer_display,
+            show_types: self.show_types,
+            template: self.template.take(),
         };
         Ok(())
     }
 
+    /// Go back to the code of a saved state, the settings and the last output are kept
+    pub fn restore_code(&mut self, state: &Repl) {
+        self.body = state.body.clone();
+        self.cursor = state.cursor;
+    }
+
     pub fn insert(&mut self, input: String) {
         for line in input.lines() {
             self.body.insert(self.cursor, line.to_owned());
@@ -42,7 +116,12 @@ impl Repl {
 
     pub fn reset(&mut self) {
         self.prepare_ground().expect("Error while resetting Repl");
-        *self = Self::new();
+        *self = Self {
+            prefer_display: self.prefer_display,
+            show_types: self.show_types,
+            template: self.template.take(),
+            ..Self::new()
+        };
     }
 
     pub fn show(&self) -> String {
@@ -60,38 +139,257 @@ impl Repl {
         Ok(())
     }
 
-    pub fn eval(&mut self, input: String) -> Result<String, IRustError> {
-        let eval_statement = format!("println!(\"{{:?}}\", {{\n{}\n}});", input);
-        let mut eval_result = String::new();
+    pub fn eval(&mut self, input: String) -> Result<std::process::Child, IRustError> {
+        self.eval_in_tmp_repl(self.eval_statement(&input), || Ok(cargo_run(true)?))
+    }
 
-        self.eval_in_tmp_repl(eval_statement, || -> Result<(), IRustError> {
-            eval_result = cargo_run(true)?;
-            Ok(())
-        })?;
+    /// Print the type of `expression` without evaluating it, the expression is wrapped
+    /// in a closure that is never called
+    pub fn type_of(&mut self, expression: &str) -> Result<std::process::Child, IRustError> {
+        let probe = format!(
+            "fn irust_type_of<T>(_: impl FnOnce() -> T) -> &'static str {{ std::any::type_name::<T>() }}\nprintln!(\"{{}}\", irust_type_of(|| {{\n{}\n}}));",
+            expression
+        );
+        self.eval_in_tmp_repl(probe, || Ok(cargo_run(true)?))
+    }
 
-        Ok(eval_result)
+    /// Statements printing the type name, size and alignment of a type (`is_type`)
+    /// or of an expression type, the expression is not evaluated
+    pub fn layout_probe(target: &str, is_type: bool) -> String {
+        if is_type {
+            format!(
+                "println!(\"{{}}\\n{{}}\\n{{}}\", std::any::type_name::<{t}>(), std::mem::size_of::<{t}>(), std::mem::align_of::<{t}>());",
+                t = target
+            )
+        } else {
+            format!(
+                "fn irust_layout_of<T>(_: impl FnOnce() -> T) -> (&'static str, usize, usize) {{ (std::any::type_name::<T>(), std::mem::size_of::<T>(), std::mem::align_of::<T>()) }}\nlet irust_layout = irust_layout_of(|| {{\n{}\n}});\nprintln!(\"{{}}\\n{{}}\\n{{}}\", irust_layout.0, irust_layout.1, irust_layout.2);",
+                target
+            )
+        }
+    }
+
+    /// The statement that prints the value of `input`, `input` starts on its second line
+    pub fn eval_statement(&self, input: &str) -> String {
+        if self.show_types {
+            // the type name is printed to stderr after a `TYPE_MARK`, so it's not part of the value
+            return format!(
+                "{{ let irust_value = {{\n{}\n}};\n{}\nfn irust_type_of<T>(_: &T) -> &'static str {{ std::any::type_name::<T>() }}\neprintln!(\"{}{{}}\", irust_type_of(&irust_value)); }}",
+                input,
+                self.print_value("irust_value"),
+                TYPE_MARK
+            );
+        }
+        self.print_value(&format!("{{\n{}\n}}", input))
+    }
+
+    fn print_value(&self, value: &str) -> String {
+        if !self.prefer_display {
+            return format!("println!(\"{{:?}}\", {});", value);
+        }
+        // the inherent method needs `Display` and shadows the `Debug` trait method when it applies
+        format!(
+            "println!(\"{{}}\", {{ {} IRustFmt(&{}).irust_fmt() }});",
+            concat!(
+                "struct IRustFmt<'a, T: ?Sized>(&'a T); ",
+                "trait IRustDebug { fn irust_fmt(&self) -> String; } ",
+                "impl<T: ?Sized + std::fmt::Debug> IRustDebug for IRustFmt<'_, T> { fn irust_fmt(&self) -> String { format!(\"{:?}\", self.0) } } ",
+                "impl<T: ?Sized + std::fmt::Display> IRustFmt<'_, T> { #[allow(dead_code)] fn irust_fmt(&self) -> String { format!(\"{}\", self.0) } }"
+            ),
+            value
+        )
     }
 
-    pub fn eval_in_tmp_repl(
+    /// Remove the type printed by an evaluation with `show_types` from its stderr and return it
+    pub fn take_value_type(stderr: &mut Vec<u8>) -> Option<String> {
+        let text = String::from_utf8_lossy(stderr).into_owned();
+        let line = text.lines().find(|line| line.starts_with(TYPE_MARK))?;
+        let value_type = line[TYPE_MARK.len()..].to_owned();
+        *stderr = text
+            .lines()
+            .filter(|l| !l.starts_with(TYPE_MARK))
+            .map(|l| format!("{}\n", l))
+            .collect::<String>()
+            .into_bytes();
+        Some(value_type)
+    }
+
+    /// The repl code with `input` inserted and the line where `input` starts, the repl is not modified
+    pub fn code_with(&self, input: &str) -> (String, usize) {
+        let mut body = self.body.clone();
+        body.splice(
+            self.cursor..self.cursor,
+            input.lines().map(ToOwned::to_owned),
+        );
+        self.bind_last_out(&mut body);
+        (body.join("\n"), self.cursor)
+    }
+
+    /// Like `code_with`, expressions are wrapped the same way they are evaluated
+    pub fn code_for_input(&self, input: &str) -> (String, usize) {
+        match classify(input) {
+            InputKind::Expression => {
+                let (code, line) = self.code_with(&self.eval_statement(input));
+                (code, line + 1)
+            }
+            _ => self.code_with(input),
+        }
+    }
+
+    /// Run `input` (a `let` statement) and print the bound value type and `Debug` output,
+    /// used to persist the value with `insert_persisted_let`
+    pub fn persist_probe(
+        &mut self,
+        input: &str,
+        binding: &LetBinding,
+    ) -> Result<std::process::Child, IRustError> {
+        let probe = Self::persist_probe_code(input, binding);
+        self.eval_in_tmp_repl(probe, || Ok(cargo_run(false)?))
+    }
+
+    fn persist_probe_code(input: &str, binding: &LetBinding) -> String {
+        format!(
+            "{}\n{{\nfn irust_type_of<T>(_: &T) -> &'static str {{ std::any::type_name::<T>() }}\nprintln!(\"{}\\n{{}}\\n{{:?}}\", irust_type_of(&{name}), {name});\n}}",
+            input,
+            STATE_MARK,
+            name = binding.name
+        )
+    }
+
+    /// Print the `Debug` output of every watched expression, one per line after a `STATE_MARK` line
+    pub fn eval_watches(&mut self, watches: &[String]) -> Result<std::process::Child, IRustError> {
+        let mut statements = format!("println!(\"{}\");", STATE_MARK);
+        for watch in watches {
+            statements.push_str(&format!("\nprintln!(\"{{:?}}\", {{\n{}\n}});", watch));
+        }
+
+        self.eval_in_tmp_repl(statements, || Ok(cargo_run(false)?))
+    }
+
+    /// The output of `eval_watches` for each watch, `None` if the evaluation failed
+    pub fn watches_output<'a>(output: &'a str, watches: &[String]) -> Option<Vec<&'a str>> {
+        let idx = output.rfind(STATE_MARK)?;
+        let values: Vec<&str> = output[idx + STATE_MARK.len()..]
+            .trim_start_matches('\n')
+            .lines()
+            .collect();
+        if values.len() < watches.len() {
+            return None;
+        }
+        Some(values)
+    }
+
+    /// Time `iterations` runs of `expression` in release mode after a warmup,
+    /// the durations in nanoseconds are printed one per line after a `STATE_MARK` line
+    pub fn bench(
+        &mut self,
+        expression: &str,
+        iterations: usize,
+    ) -> Result<std::process::Child, IRustError> {
+        let probe = format!(
+            "let irust_bench = || std::hint::black_box({{\n{}\n}});\nfor _ in 0..{} {{ irust_bench(); }}\nlet mut irust_times = Vec::with_capacity({});\nfor _ in 0..{} {{ let irust_start = std::time::Instant::now(); irust_bench(); irust_times.push(irust_start.elapsed().as_nanos()); }}\nprintln!(\"{}\");\nfor irust_time in irust_times {{ println!(\"{{}}\", irust_time); }}",
+            expression,
+            std::cmp::max(iterations / 10, 1),
+            iterations,
+            iterations,
+            STATE_MARK
+        );
+        self.eval_in_tmp_repl(probe, || Ok(cargo_run_release()?))
+    }
+
+    /// The durations printed by `bench`, `None` if the evaluation failed
+    pub fn bench_output(output: &str) -> Option<Vec<f64>> {
+        let idx = output.rfind(STATE_MARK)?;
+        output[idx + STATE_MARK.len()..]
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| line.parse().ok())
+            .collect()
+    }
+
+    /// If the bound value can be written back as a literal, insert `let name: Type = literal;`
+    /// instead of `input` so later evals don't recompute it
+    pub fn insert_persisted_let(&mut self, input: String, binding: &LetBinding, probe_out: &str) {
+        let persisted = probe_out
+            .rfind(STATE_MARK)
+            .map(|idx| &probe_out[idx + STATE_MARK.len()..])
+            .and_then(|state| {
+                let (ty, value) = state.trim().split_once('\n')?;
+                let (ty, literal) = Self::as_literal(ty, value)?;
+                Some(format!(
+                    "let {}{}: {} = {}; // {}",
+                    if binding.mutable { "mut " } else { "" },
+                    binding.name,
+                    ty,
+                    literal,
+                    input.split_whitespace().collect::<Vec<&str>>().join(" ")
+                ))
+            });
+
+        self.insert(persisted.unwrap_or(input));
+    }
+
+    /// Types whose `Debug` output is also a valid rust literal
+    fn as_literal<'a>(ty: &'a str, value: &str) -> Option<(&'a str, String)> {
+        match ty {
+            "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64"
+            | "u128" | "usize" | "bool" | "char" | "&str" => Some((ty, value.to_string())),
+            "f32" | "f64" if value.parse::<f64>().ok()?.is_finite() => {
+                Some((ty, value.to_string()))
+            }
+            "alloc::string::String" => Some(("String", format!("{}.to_string()", value))),
+            _ => None,
+        }
+    }
+
+    pub fn eval_in_tmp_repl<T>(
         &mut self,
         input: String,
-        mut f: impl FnMut() -> Result<(), IRustError>,
-    ) -> Result<(), IRustError> {
+        f: impl FnOnce() -> Result<T, IRustError>,
+    ) -> Result<T, IRustError> {
         let orig_body = self.body.clone();
         let orig_cursor = self.cursor;
 
         self.insert(input);
-        self.write()?;
-        f()?;
+        let mut body = self.body.clone();
+        self.bind_last_out(&mut body);
+        if let Some(template) = self.template.as_ref() {
+            template.apply(&mut body, orig_cursor);
+        }
+        Self::add_allocator(&mut body);
+        Self::add_logger(&mut body);
+        std::fs::write(&*MAIN_FILE, body.join("\n"))?;
+        let result = f();
 
         self.body = orig_body;
         self.cursor = orig_cursor;
 
-        Ok(())
+        result
+    }
+
+    // the binding is added on the `fn main() {` line, so the input line numbers don't change,
+    // only when the code uses it since the whole output is inlined in the code
+    fn bind_last_out(&self, body: &mut [String]) {
+        if !body.iter().any(|line| line.contains(LAST_OUT_NAME)) {
+            return;
+        }
+        if let Some(main) = body
+            .iter_mut()
+            .find(|l| l.trim_start().starts_with(MAIN_FN))
+        {
+            main.push_str(&format!(
+                " {}: &str = {:?};",
+                LAST_OUT_BINDING, self.last_out
+            ));
+        }
     }
 
     pub fn add_dep(&self, dep: &[String]) -> std::io::Result<std::process::Child> {
-        Ok(cargo_add(dep)?)
+        cargo_add(dep)
+    }
+
+    pub fn update(&self, krate: Option<&str>) -> std::io::Result<std::process::Child> {
+        cargo_update(krate)
     }
 
     pub fn build(&self) -> std::io::Result<std::process::Child> {
@@ -99,12 +397,81 @@ impl Repl {
     }
 
     pub fn write(&self) -> io::Result<()> {
+        let mut body = self.body.clone();
+        Self::add_allocator(&mut body);
         let mut main_file = std::fs::File::create(&*MAIN_FILE)?;
-        write!(main_file, "{}", self.body.join("\n"))?;
+        write!(main_file, "{}", body.join("\n"))?;
 
         Ok(())
     }
 
+    // the `:set allocator` item is added on the last line, after the main function
+    fn add_allocator(body: &mut [String]) {
+        let allocator = *ALLOCATOR.lock().unwrap_or_else(|e| e.into_inner());
+        if let (Some(item), Some(end)) = (allocator.item(), body.last_mut()) {
+            end.push(' ');
+            end.push_str(&item);
+        }
+    }
+
+    // with `:set log` an env_logger is initialized on the `fn main() {` line,
+    // unless the repl code sets up its own logger
+    fn add_logger(body: &mut [String]) {
+        if RUST_LOG.lock().unwrap_or_else(|e| e.into_inner()).is_none()
+            || body.iter().any(|line| {
+                [
+                    "_logger::",
+                    "set_logger",
+                    "set_boxed_logger",
+                    "tracing_subscriber",
+                ]
+                .iter()
+                .any(|logger| line.contains(logger))
+            })
+        {
+            return;
+        }
+        if let Some(main) = body
+            .iter_mut()
+            .find(|l| l.trim_start().starts_with(MAIN_FN))
+        {
+            main.push_str(" let _ = env_logger::try_init();");
+        }
+    }
+
+    // the main file without the `:set allocator` item, as written or formatted
+    fn without_allocator(main_file: &str) -> String {
+        let lines: Vec<&str> = main_file
+            .lines()
+            .map(|line| {
+                line.split(" #[global_allocator]")
+                    .next()
+                    .unwrap_or_default()
+            })
+            .filter(|line| {
+                line.trim() != "#[global_allocator]"
+                    && !line.trim_start().starts_with("static IRUST_ALLOCATOR:")
+            })
+            .collect();
+        lines.join("\n").trim_end().to_owned()
+    }
+
+    /// Remove `lines` from the body at `start`, returns false if they are not there anymore
+    pub fn remove_lines(&mut self, start: usize, lines: &[String]) -> bool {
+        if lines.is_empty() || !self.has_lines(start, lines) {
+            return false;
+        }
+        self.body.drain(start..start + lines.len());
+        if start < self.cursor {
+            self.cursor -= lines.len();
+        }
+        true
+    }
+
+    pub fn has_lines(&self, start: usize, lines: &[String]) -> bool {
+        self.body.get(start..start + lines.len()) == Some(lines)
+    }
+
     pub fn pop(&mut self) {
         if self.body.len() > 2 {
             self.body.remove(self.cursor - 1);
@@ -123,4 +490,99 @@ impl Repl {
 
         Err(IRustError::Custom("Incorrect line number".into()))
     }
+
+    /// Replace a line of the body (numbered like `del`), the input can have several lines
+    pub fn replace_line(&mut self, line_num: usize, input: &str) -> Result<(), IRustError> {
+        if line_num == 0 || line_num + 1 >= self.body.len() {
+            return Err(IRustError::Custom("Incorrect line number".into()));
+        }
+        let lines: Vec<String> = input.lines().map(ToOwned::to_owned).collect();
+        if line_num < self.cursor {
+            self.cursor = self.cursor + lines.len() - 1;
+        }
+        self.body.splice(line_num..=line_num, lines);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::classify::let_binding;
+    use super::*;
+
+    #[test]
+    fn last_out_is_bound_only_when_used() {
+        let mut repl = Repl::new();
+        repl.last_out = "1\n2\n".to_string();
+
+        let (code, _) = repl.code_with("let a = 1;");
+        assert!(!code.contains(LAST_OUT_NAME));
+
+        let (code, line) = repl.code_with("let n = __irust_last_out.lines().count();");
+        let main = code.lines().next().unwrap();
+        assert!(main.starts_with(MAIN_FN));
+        assert!(main.ends_with(r#"let __irust_last_out: &str = "1\n2\n";"#));
+        assert_eq!(line, 1);
+    }
+
+    fn persisted(input: &str, probe_out: &str) -> String {
+        let mut repl = Repl::new();
+        let binding = let_binding(input).unwrap();
+        repl.insert_persisted_let(input.to_string(), &binding, probe_out);
+        repl.body[1].clone()
+    }
+
+    #[test]
+    fn persist_probe_prints_the_type_and_value() {
+        let binding = let_binding("let mut total = (1..4).sum::<i32>();").unwrap();
+        let probe = Repl::persist_probe_code("let mut total = (1..4).sum::<i32>();", &binding);
+        assert!(probe.starts_with("let mut total = (1..4).sum::<i32>();\n{"));
+        assert!(probe.contains(
+            r###"println!("##IRustState##\n{}\n{:?}", irust_type_of(&total), total);"###
+        ));
+    }
+
+    #[test]
+    fn persisted_let_literals() {
+        assert_eq!(
+            persisted(
+                "let mut total = (1..4).sum::<i32>();",
+                "output\n##IRustState##\ni32\n6\n"
+            ),
+            "let mut total: i32 = 6; // let mut total = (1..4).sum::<i32>();"
+        );
+        assert_eq!(
+            persisted(
+                "let s = format!(\"a{}\",\n    '\"');",
+                "##IRustState##\nalloc::string::String\n\"a\\\"\"\n"
+            ),
+            r#"let s: String = "a\"".to_string(); // let s = format!("a{}", '"');"#
+        );
+        // not a literal, the input is kept
+        assert_eq!(
+            persisted(
+                "let v = vec![1];",
+                "##IRustState##\nalloc::vec::Vec<i32>\n[1]\n"
+            ),
+            "let v = vec![1];"
+        );
+        // the probe failed
+        assert_eq!(persisted("let a = f();", "error"), "let a = f();");
+    }
+
+    #[test]
+    fn as_literal_types() {
+        assert_eq!(Repl::as_literal("u8", "7"), Some(("u8", "7".to_string())));
+        assert_eq!(
+            Repl::as_literal("char", "'\\n'"),
+            Some(("char", "'\\n'".to_string()))
+        );
+        assert_eq!(
+            Repl::as_literal("f64", "1.5"),
+            Some(("f64", "1.5".to_string()))
+        );
+        assert_eq!(Repl::as_literal("f64", "NaN"), None);
+        assert_eq!(Repl::as_literal("f32", "inf"), None);
+        assert_eq!(Repl::as_literal("&[u8]", "[1]"), None);
+    }
 }
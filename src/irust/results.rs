@@ -0,0 +1,108 @@
+use super::cmd_args::CmdArgs;
+use super::highlight::highlight;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+
+// evaluated values kept for `:results`, the oldest ones are dropped
+const RESULTS_MAX: usize = 100;
+
+/// The output of an evaluated expression, numbered from the start of the session
+struct EvalResult {
+    number: usize,
+    expression: String,
+    value: String,
+}
+
+#[derive(Default)]
+pub struct Results {
+    results: Vec<EvalResult>,
+    count: usize,
+}
+
+impl Results {
+    pub fn push(&mut self, expression: String, value: String) {
+        self.count += 1;
+        self.results.push(EvalResult {
+            number: self.count,
+            expression,
+            value,
+        });
+        if self.results.len() > RESULTS_MAX {
+            self.results.remove(0);
+        }
+    }
+}
+
+impl IRust {
+    /// `:results` lists the kept values, `:result <n>` shows one of them without evaluating it again
+    pub fn results(&mut self) -> Result<Printer, IRustError> {
+        let buffer = self.buffer.to_string();
+        let args = CmdArgs::parse("results", &buffer)?;
+        let listing = buffer.trim_start().starts_with(":results");
+        match args.as_strs().as_slice() {
+            [] if listing => Ok(self.list_results()),
+            [] => Err(IRustError::Custom("No result number specified".to_string())),
+            [number] if !listing => self.show_result(number),
+            _ => Err(args.usage_error()),
+        }
+    }
+
+    fn list_results(&self) -> Printer {
+        if self.results.results.is_empty() {
+            return Printer::new(PrinterItem::new(
+                "No results yet".to_string(),
+                PrinterItemType::Warn,
+            ));
+        }
+        let mut outputs = Printer::default();
+        for result in &self.results.results {
+            // one line per result, the full value is shown by `:result <n>`
+            let value = result.value.lines().next().unwrap_or_default();
+            let value = if value.chars().count() > 60 || result.value.lines().nth(1).is_some() {
+                format!("{}..", value.chars().take(60).collect::<String>())
+            } else {
+                value.to_owned()
+            };
+            outputs.push(PrinterItem::new(
+                format!("#{} ", result.number),
+                PrinterItemType::Ok,
+            ));
+            outputs.push(PrinterItem::new(
+                result
+                    .expression
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .to_owned(),
+                PrinterItemType::Eval,
+            ));
+            outputs.push(PrinterItem::new(" => ".to_string(), PrinterItemType::Eval));
+            outputs.push(PrinterItem::new(value, PrinterItemType::Out));
+            outputs.add_new_line(1);
+        }
+        outputs
+    }
+
+    fn show_result(&self, number: &str) -> Result<Printer, IRustError> {
+        let number: usize = number
+            .trim_start_matches('#')
+            .parse()
+            .map_err(|_| IRustError::Custom(format!("Invalid result number: {}", number)))?;
+        let result = self
+            .results
+            .results
+            .iter()
+            .find(|result| result.number == number)
+            .ok_or_else(|| IRustError::Custom(format!("No result #{}", number)))?;
+
+        let mut outputs = highlight(&result.expression);
+        outputs.add_new_line(1);
+        outputs.push(PrinterItem::new(result.value.clone(), PrinterItemType::Out));
+        Ok(outputs)
+    }
+
+    /// Keep the value of an evaluated expression for `:results`
+    pub fn record_result(&mut self, expression: String, value: String) {
+        self.results.push(expression, value);
+    }
+}
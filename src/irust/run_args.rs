@@ -0,0 +1,41 @@
+use super::cargo_cmds::RUN_ARGS;
+use super::cmd_args::CmdArgs;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+
+impl IRust {
+    /// Set the arguments the evaluations are run with, exp: `:args --input data.csv -v`, `:args clear`
+    pub fn run_args(&mut self) -> Result<Printer, IRustError> {
+        let args = CmdArgs::parse("args", &self.buffer.to_string())?.into_vec();
+        let mut run_args = RUN_ARGS.lock().unwrap_or_else(|e| e.into_inner());
+
+        let msg = match args.as_slice() {
+            [] if run_args.is_empty() => "No arguments".to_string(),
+            [] => run_args
+                .iter()
+                .map(|arg| quote(arg))
+                .collect::<Vec<String>>()
+                .join(" "),
+            [clear] if clear == "clear" => {
+                run_args.clear();
+                "Ok!".to_string()
+            }
+            _ => {
+                *run_args = args;
+                "Ok!".to_string()
+            }
+        };
+
+        let mut outputs = Printer::new(PrinterItem::new(msg, PrinterItemType::Ok));
+        outputs.add_new_line(1);
+        Ok(outputs)
+    }
+}
+
+// shown quoted when needed, so it can be given back to `:args`
+fn quote(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || "\"'\\".contains(c)) {
+        return arg.to_owned();
+    }
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
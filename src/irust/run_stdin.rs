@@ -0,0 +1,102 @@
+use super::cargo_cmds::RUN_STDIN;
+use super::cmd_args::CmdArgs;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+
+// the first bytes of the data shown by `:stdin`
+const PREVIEW_LEN: usize = 200;
+
+impl IRust {
+    /// Set the data piped to the evaluations stdin, exp: `:stdin data.txt`, `:stdin clear` or a heredoc:
+    /// ```text
+    /// :stdin <<EOF
+    /// 1 2 3
+    /// EOF
+    /// ```
+    pub fn run_stdin(&mut self) -> Result<Printer, IRustError> {
+        let buffer = self.buffer.to_string();
+        let mut run_stdin = RUN_STDIN.lock().unwrap_or_else(|e| e.into_inner());
+
+        // the heredoc lines are kept as they are
+        let heredoc_args = CmdArgs::parse_code("stdin", &buffer);
+        let data = if heredoc_args.code().starts_with("<<") {
+            heredoc(heredoc_args.code())?
+        } else {
+            let args = CmdArgs::parse("stdin", &buffer)?;
+            match args.as_strs().as_slice() {
+                [] => return Ok(show_stdin(run_stdin.as_deref())),
+                ["clear"] => {
+                    *run_stdin = None;
+                    return Ok(success());
+                }
+                [file] => std::fs::read(file)
+                    .map_err(|e| IRustError::Custom(format!("{}: {}", file, e)))?,
+                _ => return Err(args.usage_error()),
+            }
+        };
+        *run_stdin = Some(data);
+        Ok(success())
+    }
+}
+
+// `<<EOF\n<lines>\nEOF`, the lines keep their line breaks like a shell heredoc
+fn heredoc(args: &str) -> Result<Vec<u8>, IRustError> {
+    let mut lines = args.lines();
+    let marker = lines
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches("<<")
+        .trim()
+        .to_owned();
+    if marker.is_empty() {
+        return Err(IRustError::Custom(
+            "Missing heredoc marker, exp: :stdin <<EOF".to_string(),
+        ));
+    }
+
+    let mut data = String::new();
+    for line in lines {
+        if line.trim_end() == marker {
+            return Ok(data.into_bytes());
+        }
+        data.push_str(line);
+        data.push('\n');
+    }
+    Err(IRustError::Custom(format!(
+        "Missing the closing `{}` line",
+        marker
+    )))
+}
+
+fn show_stdin(data: Option<&[u8]>) -> Printer {
+    let mut outputs = match data {
+        None => Printer::new(PrinterItem::new(
+            "No stdin data".to_string(),
+            PrinterItemType::Warn,
+        )),
+        Some(data) => {
+            let preview = String::from_utf8_lossy(&data[..data.len().min(PREVIEW_LEN)]);
+            let mut outputs = Printer::new(PrinterItem::new(
+                format!("{} bytes piped to the evaluations", data.len()),
+                PrinterItemType::Ok,
+            ));
+            outputs.add_new_line(1);
+            outputs.push(PrinterItem::new(
+                preview.trim_end().to_string(),
+                PrinterItemType::Eval,
+            ));
+            if data.len() > PREVIEW_LEN {
+                outputs.push(PrinterItem::new("...".to_string(), PrinterItemType::Warn));
+            }
+            outputs
+        }
+    };
+    outputs.add_new_line(1);
+    outputs
+}
+
+fn success() -> Printer {
+    let mut outputs = Printer::new(PrinterItem::new("Ok!".to_string(), PrinterItemType::Ok));
+    outputs.add_new_line(1);
+    outputs
+}
@@ -0,0 +1,222 @@
+use super::cmd_args::CmdArgs;
+use crate::irust::input::Input;
+use crate::irust::printer::Printer;
+use crate::irust::{IRust, IRustError};
+use crossterm::{style, AlternateScreen, Attribute, ClearType, InputEvent, KeyEvent};
+use regex::Regex;
+
+/// A `less` like view of the session transcript
+struct Pager {
+    lines: Vec<String>,
+    // index of the first shown line
+    top: usize,
+    // rows available for the lines, the last row is the status line
+    height: usize,
+    width: usize,
+    search: Option<Regex>,
+    // the pattern being typed after `/`
+    prompt: Option<String>,
+    message: String,
+}
+
+impl Pager {
+    fn max_top(&self) -> usize {
+        self.lines.len().saturating_sub(self.height)
+    }
+
+    fn scroll_down(&mut self, rows: usize) {
+        // a search result can be shown past the last page
+        self.top = std::cmp::min(self.top + rows, std::cmp::max(self.max_top(), self.top));
+    }
+
+    fn scroll_up(&mut self, rows: usize) {
+        self.top = self.top.saturating_sub(rows);
+    }
+
+    /// Put the next (or previous) matching line at the top, `start` included
+    fn find(&mut self, start: usize, forward: bool) {
+        let regex = match &self.search {
+            Some(regex) => regex,
+            None => {
+                self.message = "No previous search".to_string();
+                return;
+            }
+        };
+        let lines = &self.lines;
+        let found = if forward {
+            (start..lines.len()).find(|idx| regex.is_match(&lines[*idx]))
+        } else {
+            (0..std::cmp::min(start + 1, lines.len()))
+                .rev()
+                .find(|idx| regex.is_match(&lines[*idx]))
+        };
+        match found {
+            Some(idx) => self.top = idx,
+            None => self.message = "Pattern not found".to_string(),
+        }
+    }
+
+    fn set_search(&mut self, pattern: &str) {
+        match Regex::new(pattern) {
+            Ok(regex) => {
+                self.search = Some(regex);
+                self.find(self.top, true);
+            }
+            Err(e) => self.message = format!("Invalid pattern: {}", e),
+        }
+    }
+
+    /// Handle a key, returns false when the pager is quit
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        self.message.clear();
+        if let Some(mut pattern) = self.prompt.take() {
+            match key {
+                KeyEvent::Enter => {
+                    if !pattern.is_empty() {
+                        self.set_search(&pattern);
+                    }
+                }
+                KeyEvent::Esc | KeyEvent::Ctrl('c') => (),
+                KeyEvent::Backspace => {
+                    if pattern.pop().is_some() {
+                        self.prompt = Some(pattern);
+                    }
+                }
+                KeyEvent::Char(c) => {
+                    pattern.push(c);
+                    self.prompt = Some(pattern);
+                }
+                _ => self.prompt = Some(pattern),
+            }
+            return true;
+        }
+
+        match key {
+            KeyEvent::Char('q') | KeyEvent::Esc | KeyEvent::Ctrl('c') => return false,
+            KeyEvent::Char('j') | KeyEvent::Down | KeyEvent::Enter => self.scroll_down(1),
+            KeyEvent::Char('k') | KeyEvent::Up => self.scroll_up(1),
+            KeyEvent::Char(' ') | KeyEvent::Char('f') | KeyEvent::PageDown => {
+                self.scroll_down(self.height)
+            }
+            KeyEvent::Char('b') | KeyEvent::PageUp => self.scroll_up(self.height),
+            KeyEvent::Char('g') | KeyEvent::Home => self.top = 0,
+            KeyEvent::Char('G') | KeyEvent::End => self.top = self.max_top(),
+            KeyEvent::Char('/') => self.prompt = Some(String::new()),
+            KeyEvent::Char('n') => self.find(self.top + 1, true),
+            KeyEvent::Char('N') => match self.top.checked_sub(1) {
+                Some(start) => self.find(start, false),
+                None => self.message = "Pattern not found".to_string(),
+            },
+            _ => (),
+        }
+        true
+    }
+
+    fn status(&self) -> String {
+        if let Some(pattern) = &self.prompt {
+            return format!("/{}", pattern);
+        }
+        if !self.message.is_empty() {
+            return self.message.clone();
+        }
+        format!(
+            "lines {}-{}/{}  (/pattern search, n/N next/previous match, q quit)",
+            std::cmp::min(self.top + 1, self.lines.len()),
+            std::cmp::min(self.top + self.height, self.lines.len()),
+            self.lines.len()
+        )
+    }
+}
+
+impl IRust {
+    /// Browse the inputs and outputs of the session, `/pattern`, `n` and `N` search like in `less`
+    pub fn scrollback(&mut self) -> Result<Printer, IRustError> {
+        if self.options.accessible {
+            return Err(IRustError::Custom(
+                "No scrollback in accessible mode".to_string(),
+            ));
+        }
+        if self.transcript.is_empty() {
+            return Err(IRustError::Custom("The session is empty".to_string()));
+        }
+        let args = CmdArgs::parse_code("scrollback", &self.buffer.to_string());
+        let pattern = args.code();
+
+        let (width, height) = self.raw_terminal.size()?;
+        let tab = " ".repeat(self.options.tab_width);
+        let mut pager = Pager {
+            lines: self
+                .transcript
+                .iter()
+                .map(|l| l.replace('\t', &tab))
+                .collect(),
+            top: 0,
+            height: (height as usize).saturating_sub(1).max(1),
+            width: width as usize,
+            search: None,
+            prompt: None,
+            message: String::new(),
+        };
+        if pattern.is_empty() {
+            pager.top = pager.max_top();
+        } else {
+            pager.set_search(pattern);
+        }
+
+        // the alternate screen keeps the repl screen as it is, raw mode is already on
+        let screen = AlternateScreen::to_alternate(false)?;
+        self.cursor.hide();
+        loop {
+            self.draw_pager(&pager)?;
+            let key = match self.input.recv() {
+                Ok(Input::Event(InputEvent::Keyboard(key))) => key,
+                // the repl is redrawn for the new size once the pager is closed
+                Ok(Input::Resize) => {
+                    self.pending_input.push_back(Input::Resize);
+                    continue;
+                }
+                Ok(_) => continue,
+                Err(_) => break,
+            };
+            if !pager.handle_key(key) {
+                break;
+            }
+        }
+        // back to the repl screen
+        drop(screen);
+        self.cursor.show();
+        self.cursor.goto_internal_pos()?;
+
+        Ok(Printer::default())
+    }
+
+    fn draw_pager(&mut self, pager: &Pager) -> Result<(), IRustError> {
+        self.raw_terminal.clear(ClearType::All)?;
+        for row in 0..pager.height {
+            let line = match pager.lines.get(pager.top + row) {
+                Some(line) => line,
+                None => break,
+            };
+            let line: String = line.chars().take(pager.width).collect();
+            self.cursor.cursor.goto(0, row as u16)?;
+
+            // the matches are highlighted
+            let mut last_end = 0;
+            if let Some(regex) = &pager.search {
+                for found in regex.find_iter(&line) {
+                    self.raw_terminal.write(&line[last_end..found.start()])?;
+                    self.raw_terminal
+                        .write(style(found.as_str()).attr(Attribute::Reverse))?;
+                    last_end = found.end();
+                }
+            }
+            self.raw_terminal.write(&line[last_end..])?;
+        }
+
+        let status: String = pager.status().chars().take(pager.width).collect();
+        self.cursor.cursor.goto(0, pager.height as u16)?;
+        self.raw_terminal
+            .write(style(status).attr(Attribute::Reverse))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use super::cmd_args::CmdArgs;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+use std::process::{Command, Stdio};
+
+const CRATES_API: &str = "https://crates.io/api/v1/crates";
+const MAX_RESULTS: usize = 10;
+
+impl IRust {
+    pub fn search(&mut self) -> Result<Printer, IRustError> {
+        // exp: :search http client
+        let query = CmdArgs::parse_code("search", &self.buffer.to_string())
+            .code()
+            .to_string();
+        if query.is_empty() {
+            return Err(IRustError::Custom("No search query specified".to_string()));
+        }
+
+        // crates.io refuses requests without a user agent
+        let job = Command::new("curl")
+            .args(["--silent", "--fail", "--user-agent", "irust"])
+            .arg(format!(
+                "{}?per_page={}&q={}",
+                CRATES_API,
+                MAX_RESULTS,
+                url_encode(&query)
+            ))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|_| IRustError::Custom("`:search` requires curl".to_string()))?;
+        let output = self.wait_job(job, "Searching")?;
+        if !output.status.success() {
+            return Err(IRustError::Custom("Could not reach crates.io".to_string()));
+        }
+
+        let response: serde_json::Value = serde_json::from_slice(&output.stdout)
+            .map_err(|_| IRustError::Custom("Unexpected crates.io response".to_string()))?;
+        let crates = response["crates"].as_array().cloned().unwrap_or_default();
+        if crates.is_empty() {
+            return Err(IRustError::Custom(format!(
+                "No crate found for `{}`",
+                query
+            )));
+        }
+
+        self.search_results.clear();
+        let mut outputs = Printer::default();
+        for (idx, krate) in crates.iter().enumerate() {
+            let name = krate["name"].as_str().unwrap_or_default();
+            let version = krate["max_stable_version"]
+                .as_str()
+                .or_else(|| krate["max_version"].as_str())
+                .unwrap_or_default();
+            let downloads = krate["downloads"].as_u64().unwrap_or_default();
+            let description = krate["description"]
+                .as_str()
+                .unwrap_or_default()
+                .split_whitespace()
+                .collect::<Vec<&str>>()
+                .join(" ");
+
+            outputs.push(PrinterItem::new(
+                format!("{}. {} = \"{}\"", idx + 1, name, version),
+                PrinterItemType::Ok,
+            ));
+            outputs.push(PrinterItem::new(
+                format!(" ({} downloads)", thousands(downloads)),
+                PrinterItemType::Shell,
+            ));
+            outputs.add_new_line(1);
+            if !description.is_empty() {
+                outputs.push(PrinterItem::new(
+                    format!("   {}", description),
+                    PrinterItemType::Eval,
+                ));
+                outputs.add_new_line(1);
+            }
+
+            self.search_results.push(name.to_owned());
+        }
+        outputs.push(PrinterItem::new(
+            "IRust: use `:add #<number>` to add one of the results".to_string(),
+            PrinterItemType::Warn,
+        ));
+        outputs.add_new_line(1);
+
+        Ok(outputs)
+    }
+
+    /// `#2` => the name of the second crate of the last `:search`
+    pub fn search_result(&self, arg: &str) -> Result<String, IRustError> {
+        arg.trim_start_matches('#')
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| self.search_results.get(n.checked_sub(1)?))
+            .cloned()
+            .ok_or_else(|| IRustError::Custom(format!("No search result {}", arg)))
+    }
+}
+
+/// The crates.io name of `name` if a crate is published with it, `-` and `_` are the same
+/// for crates.io, exp: `tokio_util` => `tokio-util`
+pub fn crates_io_name(name: &str) -> Option<String> {
+    let output = Command::new("curl")
+        .args([
+            "--silent",
+            "--fail",
+            "--max-time",
+            "5",
+            "--user-agent",
+            "irust",
+        ])
+        .arg(format!("{}/{}", CRATES_API, url_encode(name)))
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let response: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+    response["crate"]["name"].as_str().map(ToOwned::to_owned)
+}
+
+fn url_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' => (b as char).to_string(),
+            b' ' => "+".to_string(),
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+// 1234567 => 1,234,567
+fn thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+    for (idx, digit) in digits.chars().enumerate() {
+        if idx != 0 && (digits.len() - idx) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+    out
+}
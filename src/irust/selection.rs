@@ -0,0 +1,84 @@
+use crate::irust::{IRust, IRustError};
+use crossterm::{InputEvent, KeyEvent};
+
+/// Keys that act on the selection instead of clearing it
+pub fn keeps_selection(event: &InputEvent) -> bool {
+    matches!(
+        event,
+        InputEvent::Keyboard(
+            KeyEvent::ShiftLeft
+                | KeyEvent::ShiftRight
+                | KeyEvent::ShiftUp
+                | KeyEvent::ShiftDown
+                | KeyEvent::Backspace
+                | KeyEvent::Delete
+                | KeyEvent::Ctrl('x')
+                | KeyEvent::Ctrl('y')
+                | KeyEvent::Alt('w')
+        )
+    )
+}
+
+impl IRust {
+    /// Extend the selection, selecting doesn't reach the history or accept suggestions
+    pub fn handle_select(&mut self, direction: &str) -> Result<(), IRustError> {
+        self.buffer.start_selection();
+        let row = self
+            .buffer
+            .buffer_pos_to_relative_cursor_pos(self.buffer.buffer_pos)
+            .1;
+        match direction {
+            "left" => self.handle_left()?,
+            "right" if !self.buffer.is_at_end() => self.handle_right()?,
+            "up" if row > 0 => self.handle_up()?,
+            "down" if row < self.buffer.last_row() => self.handle_down()?,
+            _ => (),
+        }
+        self.print_input()
+    }
+
+    pub fn clear_selection(&mut self) -> Result<(), IRustError> {
+        if self.buffer.clear_selection() {
+            self.print_input()?;
+        }
+        Ok(())
+    }
+
+    /// Returns whether there was a selection to delete
+    pub fn delete_selection(&mut self) -> Result<bool, IRustError> {
+        match self.buffer.remove_selection() {
+            Some(_) => {
+                self.buffer_edited()?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    pub fn handle_cut(&mut self) -> Result<(), IRustError> {
+        if let Some(text) = self.buffer.remove_selection() {
+            self.clipboard = text;
+            self.buffer_edited()?;
+        }
+        Ok(())
+    }
+
+    pub fn handle_copy(&mut self) -> Result<(), IRustError> {
+        if let Some(text) = self.buffer.selected_text() {
+            self.clipboard = text;
+            self.clear_selection()?;
+        }
+        Ok(())
+    }
+
+    /// Insert the last cut or copied text, replacing the selection
+    pub fn handle_paste(&mut self) -> Result<(), IRustError> {
+        if self.clipboard.is_empty() {
+            return Ok(());
+        }
+        self.buffer.remove_selection();
+        let clipboard = self.clipboard.clone();
+        self.buffer.insert_str(&clipboard);
+        self.buffer_edited()
+    }
+}
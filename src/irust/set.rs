@@ -0,0 +1,143 @@
+use super::cargo_cmds::{
+    write_panic_strategy, write_session_dependencies, Allocator, ALLOCATOR, PANIC_ABORT, RUST_LOG,
+};
+use super::cmd_args::CmdArgs;
+use super::help::usage_error;
+use super::options::FLAGS;
+use super::racer::Racer;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+use std::sync::atomic::Ordering;
+
+impl IRust {
+    /// Switch an option for the current session, exp: `:set autofmt on`
+    pub fn set(&mut self) -> Result<Printer, IRustError> {
+        let args = CmdArgs::parse("set", &self.buffer.to_string())?;
+        let (option, value) = match args.into_vec().as_slice() {
+            [] => return Ok(self.show_flags()),
+            [option, value] => (option.clone(), value.clone()),
+            _ => return Err(IRustError::Custom(usage_error("set"))),
+        };
+        let (option, value) = (option.as_str(), value.as_str());
+        match option {
+            "allocator" => return set_allocator(value),
+            "panic" => return set_panic_strategy(value),
+            "log" => return set_log(value),
+            _ => (),
+        }
+        let value = match value {
+            "on" | "true" => true,
+            "off" | "false" => false,
+            value => {
+                return Err(IRustError::Custom(format!(
+                    "Unknown value `{}`, expected on or off",
+                    value
+                )))
+            }
+        };
+        match self.options.flag_mut(option) {
+            Some(flag) => *flag = value,
+            None => {
+                return Err(IRustError::Custom(format!(
+                    "Unknown option `{}`, expected one of: {}, allocator, panic, log",
+                    option,
+                    FLAGS.join(", ")
+                )))
+            }
+        }
+        // the repl keeps its own copy
+        self.repl.show_types = self.options.show_types;
+        // no completion source was enabled at startup
+        if option.starts_with("completion_")
+            && value
+            && self.racer.is_err()
+            && !self.options.accessible
+        {
+            self.racer = Racer::start(false);
+        }
+
+        Ok(success())
+    }
+
+    fn show_flags(&mut self) -> Printer {
+        let mut outputs = Printer::default();
+        for option in FLAGS.iter() {
+            let value = self.options.flag_mut(option).is_some_and(|flag| *flag);
+            outputs.push(PrinterItem::new(
+                format!("{} ", option),
+                PrinterItemType::Eval,
+            ));
+            outputs.push(PrinterItem::new(
+                if value { "on" } else { "off" }.to_string(),
+                PrinterItemType::Ok,
+            ));
+            outputs.add_new_line(1);
+        }
+
+        let allocator = ALLOCATOR.lock().unwrap_or_else(|e| e.into_inner()).name();
+        let panic = if PANIC_ABORT.load(Ordering::Relaxed) {
+            "abort"
+        } else {
+            "unwind"
+        };
+        let log = RUST_LOG.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let log = log.as_deref().unwrap_or("off");
+        for (option, value) in [("allocator", allocator), ("panic", panic), ("log", log)] {
+            outputs.push(PrinterItem::new(
+                format!("{} ", option),
+                PrinterItemType::Eval,
+            ));
+            outputs.push(PrinterItem::new(value.to_string(), PrinterItemType::Ok));
+            outputs.add_new_line(1);
+        }
+        outputs
+    }
+}
+
+// the allocator crate is fetched and built with the next evaluation
+fn set_allocator(name: &str) -> Result<Printer, IRustError> {
+    let allocator = Allocator::parse(name).ok_or_else(|| {
+        IRustError::Custom(format!(
+            "Unknown allocator `{}`, expected one of: {}",
+            name,
+            Allocator::NAMES.join(", ")
+        ))
+    })?;
+    *ALLOCATOR.lock().unwrap_or_else(|e| e.into_inner()) = allocator;
+    write_session_dependencies()?;
+    Ok(success())
+}
+
+fn set_panic_strategy(strategy: &str) -> Result<Printer, IRustError> {
+    let abort = match strategy {
+        "abort" => true,
+        "unwind" => false,
+        strategy => {
+            return Err(IRustError::Custom(format!(
+                "Unknown panic strategy `{}`, expected abort or unwind",
+                strategy
+            )))
+        }
+    };
+    PANIC_ABORT.store(abort, Ordering::Relaxed);
+    write_panic_strategy()?;
+    Ok(success())
+}
+
+// `off` or a RUST_LOG filter, exp: `debug`, `my_crate=trace`, the log crates are fetched and built
+// with the next evaluation
+fn set_log(filter: &str) -> Result<Printer, IRustError> {
+    let filter = match filter {
+        "off" => None,
+        filter => Some(filter.to_owned()),
+    };
+    *RUST_LOG.lock().unwrap_or_else(|e| e.into_inner()) = filter;
+    write_session_dependencies()?;
+    Ok(success())
+}
+
+fn success() -> Printer {
+    let mut outputs = Printer::new(PrinterItem::new("Ok!".to_string(), PrinterItemType::Ok));
+    outputs.add_new_line(1);
+    outputs
+}
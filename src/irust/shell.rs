@@ -0,0 +1,290 @@
+use super::cmd_args::{split_args, CmdArgs};
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+use crossterm::ClearType;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc;
+
+// printed with the exit status after each command, it marks the end of the command output
+const DONE_MARK: &str = "##IRustShellDone##";
+
+/// The shell started by `:sh`, the `::` commands run in it so `cd` and `export` are kept
+pub struct Shell {
+    process: Child,
+    stdin: ChildStdin,
+    // the output lines, read on their own thread so a running command doesn't block IRust
+    lines: mpsc::Receiver<String>,
+    // the output of the running command so far
+    output: String,
+}
+
+impl Shell {
+    fn start() -> io::Result<Self> {
+        let mut command = Command::new("sh");
+        command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+        // its own process group, so an interrupted command is killed with the shell
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let mut process = command.spawn()?;
+        let mut stdin = process.stdin.take().unwrap();
+        let mut stdout = BufReader::new(process.stdout.take().unwrap());
+        // the errors are shown with the output
+        writeln!(stdin, "exec 2>&1")?;
+
+        let (send, lines) = mpsc::channel();
+        std::thread::spawn(move || loop {
+            let mut line = vec![];
+            match stdout.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    if send
+                        .send(String::from_utf8_lossy(&line).into_owned())
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            process,
+            stdin,
+            lines,
+            output: String::new(),
+        })
+    }
+
+    /// Start a command, `poll` returns its output once it's done
+    fn send(&mut self, cmd: &str) -> io::Result<()> {
+        // the command must not read the next ones as its input
+        writeln!(
+            self.stdin,
+            "{{ {}\n}} </dev/null\nprintf '%s%s\\n' '{}' \"$?\"",
+            cmd, DONE_MARK
+        )?;
+        self.stdin.flush()
+    }
+
+    /// The output and exit status of the command, None while it's still running
+    fn poll(&mut self) -> io::Result<Option<(String, i32)>> {
+        loop {
+            let line = match self.lines.try_recv() {
+                Ok(line) => line,
+                Err(mpsc::TryRecvError::Empty) => return Ok(None),
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "the shell exited",
+                    ))
+                }
+            };
+            // the output may not end with a new line
+            if let Some(idx) = line.find(DONE_MARK) {
+                self.output.push_str(&line[..idx]);
+                let status = line[idx + DONE_MARK.len()..].trim().parse().unwrap_or(0);
+                return Ok(Some((std::mem::take(&mut self.output), status)));
+            }
+            self.output.push_str(&line);
+        }
+    }
+}
+
+impl Drop for Shell {
+    fn drop(&mut self) {
+        // the command the shell is running too
+        #[cfg(unix)]
+        {
+            use nix::sys::signal::{killpg, Signal};
+            use nix::unistd::Pid;
+            let _ = killpg(Pid::from_raw(self.process.id() as i32), Signal::SIGKILL);
+        }
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+// an incomplete command like `for f in *; do echo $f` would leave the shell waiting for the rest
+fn check_syntax(cmd: &str) -> Result<(), IRustError> {
+    let output = Command::new("sh")
+        .arg("-n")
+        .arg("-c")
+        .arg(format!("{{ {}\n}}", cmd))
+        .stdin(Stdio::null())
+        .output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(IRustError::Custom(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+impl IRust {
+    /// `:sh` starts a shell for the `::` commands, `:sh exit` stops it
+    pub fn sh(&mut self) -> Result<Printer, IRustError> {
+        let args = CmdArgs::parse("sh", &self.buffer.to_string())?;
+        let msg = match args.as_strs().as_slice() {
+            [] if self.shell.is_some() => "The shell is already running, use `:sh exit` to stop it",
+            [] => {
+                self.shell = Some(Shell::start()?);
+                "Shell started, the `::` commands now keep their directory and variables"
+            }
+            ["exit"] | ["off"] => match self.shell.take() {
+                Some(_) => "Shell stopped",
+                None => return Err(IRustError::Custom("No shell running".to_string())),
+            },
+            _ => return Err(args.usage_error()),
+        };
+
+        let mut outputs = Printer::new(PrinterItem::new(msg.to_string(), PrinterItemType::Ok));
+        outputs.add_new_line(1);
+        Ok(outputs)
+    }
+
+    /// `::!cmd` gives the terminal to the command, for the interactive ones like `::!htop`
+    pub fn run_interactive_cmd(&mut self) -> Result<Printer, IRustError> {
+        let buffer = self.buffer.to_string();
+        let cmd = split_args(buffer.trim_start_matches("::!"))
+            .ok_or_else(|| IRustError::Custom("Unterminated quote in the command".to_string()))?;
+        let (program, args) = match cmd.split_first() {
+            Some(cmd) => cmd,
+            None => return Err(IRustError::Custom("No command specified".to_string())),
+        };
+
+        let status = if self.options.accessible {
+            Command::new(program).args(args).status()
+        } else {
+            self.cursor.show();
+            self.raw_terminal.leave_raw_mode()?;
+            // the keys typed in the command are only read by the command
+            self.pause_input();
+            let status = Command::new(program).args(args).status();
+            self.resume_input();
+            self.raw_terminal.enter_raw_mode()?;
+            self.cursor.hide();
+
+            // the command output is left in the scrollback, IRust continues on a cleared screen
+            self.raw_terminal.clear(ClearType::All)?;
+            self.cursor.pos.starting_pos = (0, 0);
+            self.output_row = 0;
+            self.cursor.goto(0, 0);
+            status
+        };
+        let status = status.map_err(|e| IRustError::Custom(format!("{}: {}", program, e)))?;
+
+        let mut outputs = if status.success() {
+            Printer::new(PrinterItem::new(
+                format!("{} exited", program),
+                PrinterItemType::Ok,
+            ))
+        } else {
+            Printer::new(PrinterItem::new(
+                format!("{} exited with {}", program, status),
+                PrinterItemType::Warn,
+            ))
+        };
+        outputs.add_new_line(1);
+        Ok(outputs)
+    }
+
+    /// Run a `::` command in the `:sh` shell
+    pub fn run_in_shell(&mut self, cmd: &str) -> Option<Result<Printer, IRustError>> {
+        let mut shell = self.shell.take()?;
+        if let Err(e) = check_syntax(cmd) {
+            self.shell = Some(shell);
+            return Some(Err(e));
+        }
+
+        let done = match shell.send(cmd) {
+            Ok(()) => self.wait_until("Running", || Ok(shell.poll()?)),
+            Err(e) => Err(e.into()),
+        };
+        let result = match done {
+            Ok(Some((output, status))) => {
+                self.shell = Some(shell);
+                let mut outputs = Printer::new(PrinterItem::new(
+                    output.trim_end_matches('\n').to_owned(),
+                    PrinterItemType::Shell,
+                ));
+                if status != 0 {
+                    outputs.add_new_line(1);
+                    outputs.push(PrinterItem::new(
+                        format!("exit status: {}", status),
+                        PrinterItemType::Warn,
+                    ));
+                }
+                Ok(outputs)
+            }
+            // Ctrl-c, the command is killed with the shell
+            Ok(None) => {
+                drop(shell);
+                match Shell::start() {
+                    Ok(shell) => {
+                        self.shell = Some(shell);
+                        Err(IRustError::Custom(
+                            "Interrupted, the shell was restarted".to_string(),
+                        ))
+                    }
+                    Err(e) => Err(IRustError::Custom(format!("Shell stopped: {}", e))),
+                }
+            }
+            // `::exit` for example, the next commands are spawned again
+            Err(e) => Err(IRustError::Custom(format!("Shell stopped: {}", e))),
+        };
+        Some(result)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn wait(shell: &mut Shell) -> (String, i32) {
+        let start = Instant::now();
+        loop {
+            if let Some(done) = shell.poll().unwrap() {
+                return done;
+            }
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "the command hangs"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn shell_keeps_its_state() {
+        let mut shell = Shell::start().unwrap();
+        shell.send("cd / && X=1").unwrap();
+        assert_eq!(wait(&mut shell), (String::new(), 0));
+        shell.send("echo $X; pwd; false").unwrap();
+        assert_eq!(wait(&mut shell), ("1\n/\n".to_string(), 1));
+    }
+
+    #[test]
+    fn running_command_doesnt_block() {
+        let mut shell = Shell::start().unwrap();
+        shell.send("sleep 10").unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(shell.poll().unwrap().is_none());
+        // dropping the shell kills the command too
+        let start = Instant::now();
+        drop(shell);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn incomplete_command_is_rejected() {
+        assert!(check_syntax("for f in *; do echo $f").is_err());
+        assert!(check_syntax("echo \"unterminated").is_err());
+        assert!(check_syntax("for f in *; do echo $f; done").is_ok());
+    }
+}
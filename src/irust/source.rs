@@ -0,0 +1,123 @@
+use super::cargo_cmds::{IRUST_DIR, MAIN_FILE};
+use super::cmd_args::CmdArgs;
+use super::highlight::highlight;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+use crate::utils::blank_comments_and_strings;
+use std::process::{Command, Stdio};
+
+impl IRust {
+    pub fn source(&mut self) -> Result<Printer, IRustError> {
+        // exp: :source std::mem::swap, :source Vec::push
+        let path = CmdArgs::parse_code("source", &self.buffer.to_string())
+            .code()
+            .to_string();
+        if path.is_empty() {
+            return Err(IRustError::Custom("No item specified".to_string()));
+        }
+
+        // racer looks the path up as if it was written at the repl cursor,
+        // the code is given as a substitute file so the repl main file is untouched
+        let (code, line) = self.repl.code_with(&path);
+        let substitute = IRUST_DIR.join("irust_source.rs");
+        std::fs::write(&substitute, code)?;
+        let job = Command::new("racer")
+            .arg("find-definition")
+            .arg((line + 1).to_string())
+            .arg(path.chars().count().to_string())
+            .arg(&*MAIN_FILE)
+            .arg(&substitute)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|_| IRustError::Custom("`:source` requires racer".to_string()))?;
+        let output = self.wait_job(job, "Looking up");
+        let _ = std::fs::remove_file(&substitute);
+        let output = output?;
+
+        // MATCH name,line,col,path,kind,context
+        let (def_line, def_file) = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find_map(|line| {
+                let mut fields = line.strip_prefix("MATCH ")?.splitn(5, ',');
+                let def_line: usize = fields.nth(1)?.parse().ok()?;
+                let def_file = fields.nth(1)?.to_owned();
+                Some((def_line, def_file))
+            })
+            .ok_or_else(|| IRustError::Custom(format!("No definition found for `{}`", path)))?;
+
+        let file = std::fs::read_to_string(&def_file)?;
+        let item = match item_source(&file, def_line) {
+            Some(item) => item,
+            None => {
+                return Err(IRustError::Custom(format!(
+                    "Could not read the item at {}:{}",
+                    def_file, def_line
+                )))
+            }
+        };
+
+        let mut outputs = Printer::new(PrinterItem::new(
+            format!("{}:{}", def_file, def_line),
+            PrinterItemType::Eval,
+        ));
+        outputs.add_new_line(1);
+        outputs.append(&mut highlight(&item));
+        outputs.add_new_line(1);
+
+        // long items are paged
+        if item.lines().count() >= self.cursor.bound.height && self.page(&outputs).is_ok() {
+            return Ok(Printer::default());
+        }
+
+        Ok(outputs)
+    }
+}
+
+/// The item starting at `line` (1-based) with its docs and attributes,
+/// None if the item doesn't end in the file
+fn item_source(file: &str, line: usize) -> Option<String> {
+    let lines: Vec<&str> = file.lines().collect();
+    let def_idx = line.checked_sub(1).filter(|idx| *idx < lines.len())?;
+
+    let start = lines[..def_idx]
+        .iter()
+        .rposition(|line| {
+            let line = line.trim_start();
+            !(line.starts_with("///") || line.starts_with("#["))
+        })
+        .map_or(0, |idx| idx + 1);
+
+    // the item ends with its closing brace, or with a `;` if it has no body
+    let rest = lines[def_idx..].join("\n");
+    let code = blank_comments_and_strings(&rest);
+    let mut braces = 0;
+    let mut delimiters = 0;
+    let mut end = None;
+    for (idx, c) in code.char_indices() {
+        match c {
+            '(' | '[' => delimiters += 1,
+            ')' | ']' => delimiters -= 1,
+            '{' => braces += 1,
+            '}' => {
+                braces -= 1;
+                if braces == 0 {
+                    end = Some(idx + 1);
+                    break;
+                }
+            }
+            ';' if braces == 0 && delimiters == 0 => {
+                end = Some(idx + 1);
+                break;
+            }
+            _ => (),
+        }
+    }
+
+    let mut item = lines[start..def_idx].join("\n");
+    if !item.is_empty() {
+        item.push('\n');
+    }
+    item.push_str(&rest[..end?]);
+    Some(item)
+}
@@ -0,0 +1,167 @@
+use super::cargo_cmds::{build_only_target, Target, LIMITS, MIRI, TARGET};
+use super::cmd_args::CmdArgs;
+use super::help::usage_error;
+use super::miri::format_miri_output;
+use crate::irust::format::{exit_status, format_eval_output};
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+use crate::utils::stdout_and_stderr;
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::Ordering;
+
+// `wasm32-wasi` was renamed, the old name is still accepted
+const WASI: &str = "wasm32-wasip1";
+// the commands the wasm binaries can be run with
+const WASM_RUNNERS: [&[&str]; 2] = [&["wasmtime"], &["wasmer", "run"]];
+
+impl IRust {
+    pub fn target(&mut self) -> Result<Printer, IRustError> {
+        // exp: :target wasm32-wasi, :target aarch64-unknown-linux-gnu --runner qemu-aarch64 -L /usr/aarch64-linux-gnu
+        // :target x86_64-unknown-linux-musl, :target host, :target
+        let mut args = CmdArgs::parse("target", &self.buffer.to_string())?.into_vec();
+        // the runner takes the rest of the arguments
+        let runner = args
+            .iter()
+            .position(|arg| arg == "--runner")
+            .map(|idx| args.split_off(idx).split_off(1));
+
+        let triple = match args.as_slice() {
+            [] if runner.is_none() => {
+                let current = match TARGET.lock().unwrap().as_ref() {
+                    Some(target) => describe(target),
+                    None => "host".to_string(),
+                };
+                return Ok(message(format!("Target: {}", current)));
+            }
+            [host] if host == "host" && runner.is_none() => {
+                *TARGET.lock().unwrap() = None;
+                return Ok(message("Target: host".to_string()));
+            }
+            [triple] => triple,
+            _ => return Err(IRustError::Custom(usage_error("target"))),
+        };
+
+        let triple = if triple == "wasm32-wasi" {
+            WASI.to_string()
+        } else {
+            triple.to_owned()
+        };
+        check_installed(&triple)?;
+
+        let runner = match runner {
+            Some(runner) if runner.is_empty() => {
+                return Err(IRustError::Custom("No runner specified".to_string()))
+            }
+            Some(runner) => {
+                let program = &runner[0];
+                if !installed(program) {
+                    return Err(IRustError::Custom(format!("`{}` not found", program)));
+                }
+                Some(runner)
+            }
+            // wasm binaries are run with the first wasm runtime found
+            None if triple.starts_with("wasm32-wasi") => Some(
+                WASM_RUNNERS
+                    .iter()
+                    .find(|runner| installed(runner[0]))
+                    .map(|runner| runner.iter().map(|arg| arg.to_string()).collect())
+                    .ok_or_else(|| {
+                        IRustError::Custom("wasm targets require wasmtime or wasmer".to_string())
+                    })?,
+            ),
+            None => None,
+        };
+
+        let target = Target { triple, runner };
+        let msg = format!("Target: {}", describe(&target));
+        *TARGET.lock().unwrap() = Some(target);
+        Ok(message(msg))
+    }
+
+    /// The evaluation output, when the target binaries can't be run a successful build is reported instead,
+    /// an evaluation killed for exceeding a limit shows what it printed before
+    pub fn format_run_output(&self, output: Output) -> Printer {
+        if MIRI.load(Ordering::Relaxed) {
+            return format_miri_output(output).unwrap_or_else(|e| {
+                Printer::new(PrinterItem::new(e.to_string(), PrinterItemType::Err))
+            });
+        }
+        if let Some(msg) = LIMITS.get().and_then(|limits| limits.exceeded(&output)) {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let mut outputs = if stdout.trim().is_empty() {
+                Printer::default()
+            } else {
+                format_eval_output(&stdout)
+            };
+            outputs.push(PrinterItem::new(msg, PrinterItemType::Err));
+            return outputs;
+        }
+
+        match build_only_target() {
+            Some(triple) if output.status.success() => Printer::new(PrinterItem::new(
+                format!("Built for {} (no runner to run it)", triple),
+                PrinterItemType::Ok,
+            )),
+            _ => {
+                let exit_status = exit_status(&String::from_utf8_lossy(&output.stderr));
+                let mut outputs = format_eval_output(&stdout_and_stderr(output));
+                // the output may be partial
+                if let Some(exit_status) = exit_status {
+                    outputs.add_new_line(1);
+                    outputs.push(PrinterItem::new(
+                        format!("IRust: {}", exit_status),
+                        PrinterItemType::Warn,
+                    ));
+                }
+                outputs
+            }
+        }
+    }
+}
+
+fn describe(target: &Target) -> String {
+    match &target.runner {
+        Some(runner) => format!("{} (run with {})", target.triple, runner.join(" ")),
+        None => format!(
+            "{} (build only, use --runner to run the inputs)",
+            target.triple
+        ),
+    }
+}
+
+fn message(msg: String) -> Printer {
+    let mut outputs = Printer::new(PrinterItem::new(msg, PrinterItemType::Ok));
+    outputs.add_new_line(1);
+    outputs
+}
+
+fn installed(program: &str) -> bool {
+    Command::new(program)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok()
+}
+
+/// The target std must be installed, without rustup cargo reports it
+fn check_installed(triple: &str) -> Result<(), IRustError> {
+    let output = match Command::new("rustup")
+        .args(["target", "list", "--installed"])
+        .output()
+    {
+        Ok(output) => output,
+        Err(_) => return Ok(()),
+    };
+    if String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .any(|installed| installed == triple)
+    {
+        Ok(())
+    } else {
+        Err(IRustError::Custom(format!(
+            "`{0}` is not installed, use `rustup target add {0}`",
+            triple
+        )))
+    }
+}
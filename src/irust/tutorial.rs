@@ -0,0 +1,124 @@
+use super::classify::{classify, InputKind};
+use super::cmd_args::CmdArgs;
+use super::help::ColoredPrinterItem;
+use crate::irust::printer::Printer;
+use crate::irust::{IRust, IRustError};
+use crossterm::Color;
+
+struct Step {
+    instruction: &'static str,
+    // shown when the input didn't pass the check
+    hint: &'static str,
+    // (input, evaluated without errors)
+    check: fn(&str, bool) -> bool,
+}
+
+const STEPS: [Step; 6] = [
+    Step {
+        instruction: "Type an expression and press Enter, its value is printed, example: 1 + 2",
+        hint: "An expression has no trailing `;`, try 1 + 2",
+        check: |input, ok| ok && classify(input) == InputKind::Expression,
+    },
+    Step {
+        instruction: "Bindings are kept for the next inputs, try: let v = vec![3, 1, 2];",
+        hint: "Start the input with `let` and end it with `;`",
+        check: |input, ok| ok && input.trim_start().starts_with("let "),
+    },
+    Step {
+        instruction: "Functions can be defined too, try: fn double(x: i32) -> i32 { x * 2 }",
+        hint: "Start the input with `fn`",
+        check: |input, ok| ok && input.trim_start().starts_with("fn "),
+    },
+    Step {
+        instruction: "Now call it, Alt-Enter adds a line break for longer inputs, try: double(21)",
+        hint: "Call the function in an expression, try double(21)",
+        check: |input, ok| ok && classify(input) == InputKind::Expression && input.contains('('),
+    },
+    Step {
+        instruction: "Type v.it and press Tab to cycle the completions (requires racer), complete it to v.iter().max() and press Enter",
+        hint: "Evaluate an expression using .iter(), try v.iter().max()",
+        check: |input, ok| ok && input.contains(".iter()"),
+    },
+    Step {
+        instruction: "Crates are added with :add (requires cargo-edit), try: :add rand",
+        hint: "Use :add <crate>, or :tutorial skip to skip this step",
+        check: |input, ok| ok && input.starts_with(":add"),
+    },
+];
+
+/// The guided tour started with `--tour` or `:tutorial`
+#[derive(Default)]
+pub struct Tutorial {
+    step: usize,
+}
+
+impl IRust {
+    pub fn start_tutorial(&mut self) {
+        self.tutorial = Some(Tutorial::default());
+    }
+
+    pub fn tutorial(&mut self) -> Result<Printer, IRustError> {
+        // exp: :tutorial, :tutorial skip, :tutorial stop
+        let args = CmdArgs::parse("tutorial", &self.buffer.to_string())?;
+        match args.as_strs().as_slice() {
+            [] => self.start_tutorial(),
+            ["skip"] => match self.tutorial.as_mut() {
+                Some(tutorial) => tutorial.step += 1,
+                None => return Err(IRustError::Custom("No tutorial running".to_string())),
+            },
+            ["stop"] => {
+                self.tutorial = None;
+                let mut outputs = Printer::new("Tutorial stopped".to_output(Color::DarkYellow));
+                outputs.add_new_line(1);
+                return Ok(outputs);
+            }
+            _ => return Err(args.usage_error()),
+        }
+        Ok(self.tutorial_step())
+    }
+
+    /// Check the input against the current step, and show the next one if it passed
+    pub fn check_tutorial_input(&mut self, input: &str, ok: bool) -> Result<(), IRustError> {
+        let step = match self.tutorial.as_ref() {
+            Some(tutorial) if !input.trim().is_empty() && !input.starts_with(":tutorial") => {
+                &STEPS[tutorial.step]
+            }
+            _ => return Ok(()),
+        };
+
+        let outputs = if (step.check)(input, ok) {
+            if let Some(tutorial) = self.tutorial.as_mut() {
+                tutorial.step += 1;
+            }
+            self.tutorial_step()
+        } else {
+            let mut outputs = Printer::new(step.hint.to_output(Color::DarkCyan));
+            outputs.add_new_line(1);
+            outputs
+        };
+        self.print_output(outputs)
+    }
+
+    /// The current step instruction, the tutorial ends after the last one
+    pub fn tutorial_step(&mut self) -> Printer {
+        let step = self.tutorial.as_ref().map_or(0, |tutorial| tutorial.step);
+        let mut outputs = match STEPS.get(step) {
+            Some(current) => {
+                let mut outputs = Printer::new(
+                    format!("Tutorial {}/{}: ", step + 1, STEPS.len()).to_output(Color::DarkYellow),
+                );
+                outputs.push(current.instruction.to_output(Color::DarkCyan));
+                outputs
+            }
+            None => {
+                self.tutorial = None;
+                Printer::new(
+                    "Tutorial done! Use :help to list all the commands"
+                        .to_output(Color::DarkYellow),
+                )
+            }
+        };
+        outputs.add_new_line(1);
+        outputs
+    }
+}
@@ -0,0 +1,60 @@
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::repl::Repl;
+use crate::irust::{IRust, IRustError};
+
+// older states are forgotten
+const MAX_STATES: usize = 100;
+
+/// The successive states of the repl, `:undo` and `:redo` move between them
+#[derive(Default)]
+pub struct UndoHistory {
+    states: Vec<Repl>,
+    current: usize,
+}
+
+impl UndoHistory {
+    fn record(&mut self, repl: &Repl) {
+        // a change after an undo drops the undone states
+        self.states.truncate(self.current + 1);
+        self.states.push(repl.clone());
+        if self.states.len() > MAX_STATES {
+            self.states.remove(0);
+        }
+        self.current = self.states.len() - 1;
+    }
+}
+
+impl IRust {
+    /// Must be called after every change of the repl code
+    pub fn repl_changed(&mut self, message: &str) -> Result<(), IRustError> {
+        self.undo_history.record(&self.repl);
+        self.commit_repl(message)
+    }
+
+    pub fn undo(&mut self) -> Result<Printer, IRustError> {
+        if self.undo_history.current == 0 {
+            return Err(IRustError::Custom("Nothing to undo".to_string()));
+        }
+        self.undo_history.current -= 1;
+        self.restore_undo_state(":undo")
+    }
+
+    pub fn redo(&mut self) -> Result<Printer, IRustError> {
+        if self.undo_history.current + 1 >= self.undo_history.states.len() {
+            return Err(IRustError::Custom("Nothing to redo".to_string()));
+        }
+        self.undo_history.current += 1;
+        self.restore_undo_state(":redo")
+    }
+
+    fn restore_undo_state(&mut self, message: &str) -> Result<Printer, IRustError> {
+        self.repl
+            .restore_code(&self.undo_history.states[self.undo_history.current]);
+        self.commit_repl(message)?;
+
+        let mut outputs = Printer::new(PrinterItem::new("Ok!".to_string(), PrinterItemType::Ok));
+        outputs.add_new_line(1);
+
+        Ok(outputs)
+    }
+}
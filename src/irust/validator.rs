@@ -0,0 +1,223 @@
+use super::cargo_cmds::cargo_check;
+use super::completion;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+use crossterm::{ClearType, Color};
+use std::sync::mpsc;
+
+struct Insertion {
+    id: usize,
+    lines: Vec<String>,
+    code: String,
+    // index of the first inserted line in the code
+    start: usize,
+}
+
+// an insertion still in the repl code
+struct Inserted {
+    id: usize,
+    start: usize,
+    lines: Vec<String>,
+}
+
+/// Checks the statements and items inserted in the repl code in the background,
+/// the ones that don't compile are removed so the next evaluations are not broken by them
+pub struct Validator {
+    send: mpsc::Sender<Insertion>,
+    // (id of the insertion, its error)
+    recv: mpsc::Receiver<(usize, String)>,
+    // kept up to date with the repl code, to find the rejected ones and the ones using them
+    inserted: Vec<Inserted>,
+    next_id: usize,
+    // (removed input, its error) not reported yet
+    rejected: Vec<(String, String)>,
+}
+
+impl Validator {
+    pub fn start() -> Self {
+        let (send, insertions) = mpsc::channel::<Insertion>();
+        let (results, recv) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            while let Ok(insertion) = insertions.recv() {
+                let error = cargo_check(&insertion.code).ok().and_then(|output| {
+                    insertion_error(&String::from_utf8_lossy(&output.stderr), &insertion)
+                });
+                if let Some(error) = error {
+                    if results.send((insertion.id, error)).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Self {
+            send,
+            recv,
+            inserted: Vec::new(),
+            next_id: 0,
+            rejected: Vec::new(),
+        }
+    }
+}
+
+// the first error located in the inserted lines, exp:
+// src/main.rs:3:17: error[E0308]: mismatched types
+fn insertion_error(stderr: &str, insertion: &Insertion) -> Option<String> {
+    stderr.lines().find_map(|line| {
+        let mut parts = line.strip_prefix("src/main.rs:")?.splitn(3, ':');
+        let line: usize = parts.next()?.parse().ok()?;
+        let message = parts.nth(1)?.trim();
+        let idx = line.checked_sub(1)?;
+        let inserted = idx >= insertion.start && idx < insertion.start + insertion.lines.len();
+        if inserted && message.starts_with("error") {
+            Some(message.to_owned())
+        } else {
+            None
+        }
+    })
+}
+
+impl IRust {
+    /// Check the input just inserted in the repl code in the background
+    pub fn validate_insertion(&mut self, input: &str) {
+        let validator = match self.validator.as_mut() {
+            Some(validator) => validator,
+            None => return,
+        };
+        let (code, end) = self.repl.code_with("");
+        let lines: Vec<String> = input.lines().map(ToOwned::to_owned).collect();
+        let start = end - lines.len();
+        let id = validator.next_id;
+        validator.next_id += 1;
+
+        // the ones changed since, with :pop or :undo for example, can't be followed anymore
+        let repl = &self.repl;
+        validator
+            .inserted
+            .retain(|inserted| repl.has_lines(inserted.start, &inserted.lines));
+        validator.inserted.push(Inserted {
+            id,
+            start,
+            lines: lines.clone(),
+        });
+        let _ = validator.send.send(Insertion {
+            id,
+            lines,
+            code,
+            start,
+        });
+    }
+
+    /// Remove the insertions that failed to compile, they are reported once the prompt is empty
+    pub fn check_validation_callback(&mut self) -> Result<(), IRustError> {
+        let validator = match self.validator.as_mut() {
+            Some(validator) => validator,
+            None => return Ok(()),
+        };
+        let mut removed = vec![];
+        while let Ok((id, error)) = validator.recv.try_recv() {
+            removed.push((id, error));
+        }
+        for (id, error) in removed {
+            self.reject_insertion(id, error)?;
+        }
+
+        // don't write over what is being typed
+        if !self.buffer.is_empty() {
+            return Ok(());
+        }
+        let rejected = match self.validator.as_mut() {
+            Some(validator) if !validator.rejected.is_empty() => {
+                std::mem::take(&mut validator.rejected)
+            }
+            _ => return Ok(()),
+        };
+
+        let mut outputs = Printer::default();
+        for (input, error) in rejected {
+            outputs.push(PrinterItem::new(
+                format!(
+                    "IRust: removed `{}` from the repl code, it doesn't compile",
+                    input
+                ),
+                PrinterItemType::Warn,
+            ));
+            outputs.add_new_line(1);
+            outputs.push(PrinterItem::new(error, PrinterItemType::Err));
+            outputs.add_new_line(1);
+        }
+
+        // replace the empty prompt with the notice, then write a new one
+        self.cursor.hide();
+        self.cursor.goto_start();
+        self.raw_terminal.clear(ClearType::CurrentLine)?;
+        outputs.add_new_line(1);
+        self.print_output(outputs)?;
+        self.print_input()?;
+        self.write_from_terminal_start(super::IN, Color::Yellow)?;
+        self.cursor.show();
+
+        Ok(())
+    }
+
+    // Remove the insertion from the repl code, then the later ones using what it declared
+    fn reject_insertion(&mut self, id: usize, error: String) -> Result<(), IRustError> {
+        let validator = match self.validator.as_mut() {
+            Some(validator) => validator,
+            None => return Ok(()),
+        };
+        // already removed as depending on a rejected insertion, or dropped after a change
+        let rejected = match validator.inserted.iter().position(|i| i.id == id) {
+            Some(idx) => validator.inserted.remove(idx),
+            None => return Ok(()),
+        };
+        // the input may have been removed since, with :pop or :undo for example
+        if !self.repl.remove_lines(rejected.start, &rejected.lines) {
+            return Ok(());
+        }
+        let input = rejected.lines.join("\n");
+        self.repl_changed(&format!("rejected {}", input))?;
+
+        let validator = match self.validator.as_mut() {
+            Some(validator) => validator,
+            None => return Ok(()),
+        };
+        validator.rejected.push((input, error));
+        for inserted in &mut validator.inserted {
+            if inserted.start > rejected.start {
+                inserted.start -= rejected.lines.len();
+            }
+        }
+
+        let declared: Vec<&str> = rejected
+            .lines
+            .iter()
+            .flat_map(|line| completion::declared_names(line))
+            .collect();
+        loop {
+            let validator = match self.validator.as_ref() {
+                Some(validator) => validator,
+                None => return Ok(()),
+            };
+            let dependent = validator
+                .inserted
+                .iter()
+                .filter(|inserted| inserted.start >= rejected.start)
+                .find_map(|inserted| {
+                    let name = inserted
+                        .lines
+                        .iter()
+                        .flat_map(|line| completion::identifiers(line))
+                        .find(|word| declared.contains(word))?;
+                    Some((inserted.id, name))
+                });
+            match dependent {
+                Some((id, name)) => {
+                    self.reject_insertion(id, format!("it uses `{}` from a removed line", name))?
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+}
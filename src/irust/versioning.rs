@@ -0,0 +1,171 @@
+use super::cargo_cmds::{IRUST_DIR, MAIN_FILE, TMP_DIR};
+use super::cmd_args::CmdArgs;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+use once_cell::sync::Lazy;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+// kept outside of the repl crate, `:reset` deletes and creates the crate again
+static GIT_DIR: Lazy<PathBuf> = Lazy::new(|| TMP_DIR.join("irust_versions.git"));
+// commit messages are the first line of the input
+const MAX_MESSAGE_LEN: usize = 60;
+
+fn git(args: &[&str]) -> Result<Output, IRustError> {
+    git_in(&GIT_DIR, &IRUST_DIR, args)
+}
+
+fn git_in(git_dir: &Path, work_tree: &Path, args: &[&str]) -> Result<Output, IRustError> {
+    let output = Command::new("git")
+        .current_dir(work_tree)
+        .arg("--git-dir")
+        .arg(git_dir)
+        .arg("--work-tree")
+        .arg(work_tree)
+        // the user may have no git identity configured
+        .args(["-c", "user.name=irust", "-c", "user.email=irust@localhost"])
+        .args(args)
+        .output()
+        .map_err(|_| IRustError::Custom("`git_versioning` requires git".to_string()))?;
+    if output.status.success() {
+        Ok(output)
+    } else {
+        Err(IRustError::Custom(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        ))
+    }
+}
+
+fn commit(git_dir: &Path, work_tree: &Path, message: &str) -> Result<(), IRustError> {
+    if !git_dir.exists() {
+        git_in(git_dir, work_tree, &["init", "--quiet"])?;
+    }
+
+    let mut message = message.lines().next().unwrap_or_default().trim().to_owned();
+    if message.chars().count() > MAX_MESSAGE_LEN {
+        message = message
+            .chars()
+            .take(MAX_MESSAGE_LEN - 3)
+            .collect::<String>()
+            + "...";
+    }
+    git_in(git_dir, work_tree, &["add", "src/main.rs", "Cargo.toml"])?;
+    git_in(
+        git_dir,
+        work_tree,
+        &["commit", "--quiet", "--allow-empty", "-m", &message],
+    )?;
+    Ok(())
+}
+
+impl IRust {
+    /// Commit the repl code to the history repository if `git_versioning` is set
+    pub fn commit_repl(&mut self, message: &str) -> Result<(), IRustError> {
+        if !self.options.git_versioning {
+            return Ok(());
+        }
+
+        self.repl.write()?;
+        commit(&GIT_DIR, &IRUST_DIR, message)
+    }
+
+    pub fn log(&mut self) -> Result<Printer, IRustError> {
+        if !self.options.git_versioning {
+            return Err(IRustError::Custom(
+                "`:log` requires the `git_versioning` option".to_string(),
+            ));
+        }
+
+        let output = git(&["log", "--oneline", "--no-decorate"])?;
+        let mut outputs = Printer::new(PrinterItem::new(
+            String::from_utf8_lossy(&output.stdout)
+                .trim_end()
+                .to_owned(),
+            PrinterItemType::Shell,
+        ));
+        outputs.add_new_line(1);
+
+        Ok(outputs)
+    }
+
+    pub fn revert(&mut self) -> Result<Printer, IRustError> {
+        // exp: :revert 3f2a1c0
+        if !self.options.git_versioning {
+            return Err(IRustError::Custom(
+                "`:revert` requires the `git_versioning` option".to_string(),
+            ));
+        }
+        let args = CmdArgs::parse("revert", &self.buffer.to_string())?;
+        let hash = match args.as_strs().as_slice() {
+            [hash] => hash.to_string(),
+            [] => return Err(IRustError::Custom("No commit specified".to_string())),
+            _ => return Err(args.usage_error()),
+        };
+
+        let output = git(&["show", &format!("{}:src/main.rs", hash)])?;
+        std::fs::write(&*MAIN_FILE, &output.stdout)?;
+        self.repl.update_from_main_file()?;
+        self.repl_changed(&format!("revert to {}", hash))?;
+
+        let mut outputs = Printer::new(PrinterItem::new("Ok!".to_string(), PrinterItemType::Ok));
+        outputs.add_new_line(1);
+
+        Ok(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn write_crate(work_tree: &Path, code: &str) {
+        fs::create_dir_all(work_tree.join("src")).unwrap();
+        fs::write(
+            work_tree.join("Cargo.toml"),
+            "[package]\nname = \"irust\"\n",
+        )
+        .unwrap();
+        fs::write(work_tree.join("src/main.rs"), code).unwrap();
+    }
+
+    #[test]
+    fn history_survives_reset() {
+        let dir = std::env::temp_dir().join(format!("irust_versioning_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let (git_dir, work_tree) = (dir.join("versions.git"), dir.join("irust"));
+
+        write_crate(&work_tree, "fn main() {\n    let a = 1;\n}\n");
+        commit(&git_dir, &work_tree, "let a = 1;").unwrap();
+        // what `:reset` does to the repl crate
+        fs::remove_dir_all(&work_tree).unwrap();
+        write_crate(&work_tree, "fn main() {\n}\n");
+        commit(&git_dir, &work_tree, ":reset").unwrap();
+
+        let log = git_in(&git_dir, &work_tree, &["log", "--format=%s"]).unwrap();
+        assert_eq!(String::from_utf8_lossy(&log.stdout), ":reset\nlet a = 1;\n");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn long_message_is_cut() {
+        let dir = std::env::temp_dir().join(format!("irust_versioning_msg_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let (git_dir, work_tree) = (dir.join("versions.git"), dir.join("irust"));
+
+        write_crate(&work_tree, "fn main() {}\n");
+        commit(
+            &git_dir,
+            &work_tree,
+            &format!("{}\nsecond line", "x".repeat(100)),
+        )
+        .unwrap();
+
+        let log = git_in(&git_dir, &work_tree, &["log", "--format=%s"]).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&log.stdout).trim_end(),
+            format!("{}...", "x".repeat(MAX_MESSAGE_LEN - 3))
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,126 @@
+use super::cargo_cmds::path_dependencies;
+use crate::irust::format::format_eval_output;
+use crate::irust::printer::{Printer, PrinterItem, PrinterItemType};
+use crate::irust::{IRust, IRustError};
+use crate::utils::stdout_and_stderr;
+use crossterm::{ClearType, Color};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime};
+
+const SCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Polls the local (path) dependencies of the repl and reports the ones that changed on disk
+pub struct Watcher {
+    recv: mpsc::Receiver<PathBuf>,
+    // a change was seen, but it was not reported yet
+    changed: Option<PathBuf>,
+}
+
+impl Watcher {
+    pub fn start() -> Self {
+        let (send, recv) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            let mut last_modified: Vec<(PathBuf, SystemTime)> = vec![];
+            loop {
+                // dependencies can be added at any moment, so they are looked up on every scan
+                for path in path_dependencies() {
+                    let modified = match latest_modification(&path) {
+                        Some(modified) => modified,
+                        None => continue,
+                    };
+                    match last_modified.iter_mut().find(|(p, _)| *p == path) {
+                        Some((_, last)) if *last != modified => {
+                            *last = modified;
+                            if send.send(path).is_err() {
+                                return;
+                            }
+                        }
+                        Some(_) => (),
+                        None => last_modified.push((path, modified)),
+                    }
+                }
+                std::thread::sleep(SCAN_INTERVAL);
+            }
+        });
+
+        Self {
+            recv,
+            changed: None,
+        }
+    }
+}
+
+// the newest modification time of the crate sources
+fn latest_modification(path: &Path) -> Option<SystemTime> {
+    let mut latest = std::fs::metadata(path.join("Cargo.toml"))
+        .ok()?
+        .modified()
+        .ok()?;
+    let mut dirs = vec![path.join("src")];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if let Ok(modified) = entry.metadata().and_then(|m| m.modified()) {
+                latest = std::cmp::max(latest, modified);
+            }
+        }
+    }
+    Some(latest)
+}
+
+impl IRust {
+    /// Report changed local dependencies, and re-run the last evaluation if `rerun_on_change` is set
+    pub fn check_watch_callback(&mut self) -> Result<(), IRustError> {
+        while let Ok(path) = self.watcher.recv.try_recv() {
+            self.watcher.changed = Some(path);
+        }
+
+        // don't write over what is being typed
+        if !self.buffer.is_empty() {
+            return Ok(());
+        }
+        let path = match self.watcher.changed.take() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let mut outputs = Printer::new(PrinterItem::new(
+            format!(
+                "IRust: {} changed, it will be rebuilt on the next evaluation",
+                path.display()
+            ),
+            PrinterItemType::Warn,
+        ));
+        outputs.add_new_line(1);
+
+        if let (true, Some(expression)) = (self.options.rerun_on_change, self.last_eval.clone()) {
+            let eval = || -> Result<Printer, IRustError> {
+                let job = self.repl.eval(expression)?;
+                Ok(format_eval_output(&stdout_and_stderr(
+                    self.wait_job(job, "Evaluating")?,
+                )))
+            };
+            match eval() {
+                Ok(mut eval_output) => outputs.append(&mut eval_output),
+                Err(e) => outputs.push(PrinterItem::new(e.to_string(), PrinterItemType::Err)),
+            }
+            outputs.add_new_line(1);
+        }
+
+        // replace the empty prompt with the notice, then write a new one
+        self.cursor.hide();
+        self.cursor.goto_start();
+        self.raw_terminal.clear(ClearType::CurrentLine)?;
+        outputs.add_new_line(1);
+        self.print_output(outputs)?;
+        self.print_input()?;
+        self.write_from_terminal_start(super::IN, Color::Yellow)?;
+        self.cursor.show();
+
+        Ok(())
+    }
+}
@@ -42,6 +42,7 @@ impl IRust {
         self.raw_terminal.clear(ClearType::All)?;
         self.buffer.goto_start();
         self.cursor.pos.starting_pos = (0, 0);
+        self.output_row = 0;
         self.cursor.goto(4, 0);
         self.cursor.bound.reset();
         self.print_input()?;
@@ -52,9 +53,21 @@ impl IRust {
         let _ = self.raw_terminal.scroll_up(n as u16);
         self.cursor.move_up(n as u16);
         self.cursor.pos.starting_pos.1 = self.cursor.pos.starting_pos.1.saturating_sub(n);
+        self.output_row = self.output_row.saturating_sub(n);
+        // the output can't be redrawn once it leaves the screen
+        if let Some(scrolled) = self.scrolled_output.as_mut() {
+            if scrolled.rows.start < n {
+                self.scrolled_output = None;
+            } else {
+                scrolled.rows = scrolled.rows.start - n..scrolled.rows.end - n;
+            }
+        }
     }
 
     pub fn write_from_next_line(&mut self) -> Result<(), IRustError> {
+        if self.input_is_full() {
+            return Ok(());
+        }
         self.buffer.insert('\n');
         self.print_input()?;
         self.cursor.goto(4, self.cursor.pos.current_pos.1 + 1);
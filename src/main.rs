@@ -8,8 +8,28 @@ use crate::args::handle_args;
 use irust::IRust;
 
 fn main() {
-    let _ = handle_args();
+    let args = match handle_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(project) = args.project {
+        IRust::set_project(project);
+    }
 
+    if args.accessible {
+        IRust::set_accessible();
+    }
+
+    IRust::set_panic_hook();
     let mut irust = IRust::new();
+    if let Some(script) = args.preload {
+        irust.preload(script);
+    }
+    if args.tour {
+        irust.start_tutorial();
+    }
     irust.run().expect("IRust Out");
 }
@@ -1,3 +1,8 @@
+use syn::spanned::Spanned;
+
+// bytes per line of the hexdump
+const HEXDUMP_WIDTH: usize = 16;
+
 pub fn stdout_and_stderr(out: std::process::Output) -> String {
     let out = if !out.stdout.is_empty() {
         out.stdout
@@ -5,44 +10,250 @@ pub fn stdout_and_stderr(out: std::process::Output) -> String {
         out.stderr
     };
 
+    // raw bytes would mess up the terminal state
+    if is_binary(&out) {
+        return format!(
+            "IRust: binary output ({} bytes)\n{}",
+            out.len(),
+            hexdump(&out)
+        );
+    }
     String::from_utf8(out).unwrap_or_default()
 }
 
-pub fn remove_main(script: &str) -> String {
+/// Invalid utf-8, or text made of control characters for more than a tenth
+fn is_binary(bytes: &[u8]) -> bool {
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(_) => return true,
+    };
+    // line breaks, tabs and escape sequences (colors) are expected in outputs
+    let controls = text
+        .chars()
+        .filter(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t' | '\x1b'))
+        .count();
+    controls * 10 > text.chars().count()
+}
+
+/// `offset  hex bytes  |ascii|` lines, like `hexdump -C`
+fn hexdump(bytes: &[u8]) -> String {
+    bytes
+        .chunks(HEXDUMP_WIDTH)
+        .enumerate()
+        .map(|(idx, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!(
+                "{:08x}  {:<width$}  |{}|",
+                idx * HEXDUMP_WIDTH,
+                hex.join(" "),
+                ascii,
+                width = HEXDUMP_WIDTH * 3 - 1
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The script with its `main` function removed, the body of a plain `fn main()` is kept
+/// as top-level statements, any other main (`async`, with attributes like `#[tokio::main]`,
+/// returning a `Result`) is kept as a `script_main` function, returns true in this case
+pub fn remove_main(script: &str) -> (String, bool) {
+    // `#!/usr/bin/env rust-script`, but not an inner attribute
+    let script = match script.strip_prefix("#!") {
+        Some(rest) if !rest.trim_start().starts_with('[') => rest
+            .split_once('\n')
+            .map(|(_, rest)| rest)
+            .unwrap_or_default(),
+        _ => script,
+    };
+
+    let file = match syn::parse_file(script) {
+        Ok(file) => file,
+        Err(_) => return (remove_main_text(script), false),
+    };
+    let main = file.items.iter().find_map(|item| match item {
+        syn::Item::Fn(f) if f.sig.ident == "main" => Some(f),
+        _ => None,
+    });
+    let main = match main {
+        Some(main) => main,
+        None => return (script.to_owned(), false),
+    };
+
+    let start = byte_offset(script, main.span().start());
+    let end = byte_offset(script, main.span().end());
+    let plain = main.sig.asyncness.is_none()
+        && main.attrs.iter().all(|attr| attr.path.is_ident("doc"))
+        && main.sig.inputs.is_empty()
+        && matches!(main.sig.output, syn::ReturnType::Default);
+
+    let mut script = script.to_owned();
+    if plain {
+        let open = byte_offset(script.as_str(), main.block.brace_token.span.start());
+        let close = byte_offset(script.as_str(), main.block.brace_token.span.end()) - 1;
+        let body = script[open + 1..close].to_owned();
+        script.replace_range(start..end, &body);
+        (script, false)
+    } else {
+        let fn_token = byte_offset(script.as_str(), main.sig.ident.span().start());
+        script.replace_range(fn_token..fn_token + "main".len(), "script_main");
+        (script, true)
+    }
+}
+
+// the byte index of a span line (1-based) and column (in chars)
+fn byte_offset(s: &str, position: proc_macro2::LineColumn) -> usize {
+    let line_start: usize = s
+        .split_inclusive('\n')
+        .take(position.line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+    line_start
+        + s[line_start..]
+            .char_indices()
+            .nth(position.column)
+            .map_or(s.len() - line_start, |(idx, _)| idx)
+}
+
+// the fallback for the scripts syn can't parse: the braces of `fn main() {` are removed
+fn remove_main_text(script: &str) -> String {
     const MAIN_FN: &str = "fn main() {";
 
-    let mut script = remove_comments(script);
+    // look for main in the code only, but keep the comments in the result
+    let code = blank_comments_and_strings(script);
 
-    let main_start = match script.find(MAIN_FN) {
-        Some(idx) if balanced_quotes(&script[..idx]) => idx,
-        _ => return script,
+    let main_start = match code.find(MAIN_FN) {
+        Some(idx) => idx,
+        None => return script.to_owned(),
     };
 
-    let open_tag = main_start + MAIN_FN.len();
+    let open_tag = main_start + MAIN_FN.len() - 1;
 
     let mut close_tag = None;
 
     // look for closing tag
-    let mut tag_score = 1;
-    for (idx, character) in script[open_tag + 1..].chars().enumerate() {
+    let mut tag_score = 0;
+    for (idx, character) in code[open_tag..].char_indices() {
         if character == '{' {
             tag_score += 1;
         }
         if character == '}' {
             tag_score -= 1;
             if tag_score == 0 {
-                close_tag = Some(idx);
+                close_tag = Some(open_tag + idx);
                 break;
             }
         }
     }
+
+    let mut script = script.to_owned();
     if let Some(close_tag) = close_tag {
-        script.remove(open_tag + close_tag + 1);
+        script.remove(close_tag);
         script.replace_range(main_start..=open_tag, "");
     }
     script
 }
 
+/// Replace comments and the content of strings and chars literals with spaces
+/// so the code structure can be searched, byte indices are kept the same
+pub fn blank_comments_and_strings(s: &str) -> String {
+    let blank = |c: char| {
+        if c == '\n' {
+            "\n".to_owned()
+        } else {
+            " ".repeat(c.len_utf8())
+        }
+    };
+
+    let chars: Vec<char> = s.chars().collect();
+    let mut out = String::with_capacity(s.len());
+    let mut idx = 0;
+    while idx < chars.len() {
+        match (chars[idx], chars.get(idx + 1)) {
+            ('/', Some('/')) => {
+                while idx < chars.len() && chars[idx] != '\n' {
+                    out += &blank(chars[idx]);
+                    idx += 1;
+                }
+            }
+            ('/', Some('*')) => {
+                // block comments can be nested
+                let mut depth = 0;
+                while idx < chars.len() {
+                    match (chars[idx], chars.get(idx + 1)) {
+                        ('/', Some('*')) => depth += 1,
+                        ('*', Some('/')) => depth -= 1,
+                        _ => (),
+                    }
+                    if depth == 0 {
+                        out += "  ";
+                        idx += 2;
+                        break;
+                    }
+                    out += &blank(chars[idx]);
+                    idx += 1;
+                }
+            }
+            ('"', _) => {
+                out.push('"');
+                idx += 1;
+                while idx < chars.len() && chars[idx] != '"' {
+                    if chars[idx] == '\\' && idx + 1 < chars.len() {
+                        out += &blank(chars[idx]);
+                        idx += 1;
+                    }
+                    out += &blank(chars[idx]);
+                    idx += 1;
+                }
+                if idx < chars.len() {
+                    out.push('"');
+                    idx += 1;
+                }
+            }
+            // 'a' or '\n', but not a lifetime
+            ('\'', Some(_)) => {
+                let len = match (chars.get(idx + 1), chars.get(idx + 2)) {
+                    (Some('\\'), _) => chars
+                        .get(idx + 3..)
+                        .and_then(|rest| rest.iter().position(|c| *c == '\''))
+                        .map(|end| end + 3),
+                    (_, Some('\'')) => Some(2),
+                    _ => None,
+                };
+                match len {
+                    Some(len) => {
+                        out.push('\'');
+                        for c in &chars[idx + 1..idx + len] {
+                            out += &blank(*c);
+                        }
+                        out.push('\'');
+                        idx += len + 1;
+                    }
+                    None => {
+                        out.push('\'');
+                        idx += 1;
+                    }
+                }
+            }
+            (c, _) => {
+                out.push(c);
+                idx += 1;
+            }
+        }
+    }
+    out
+}
+
 pub struct StringTools {}
 
 impl StringTools {
@@ -105,6 +316,16 @@ impl StringTools {
         }
     }
 
+    /// Leading separators followed by one word, exp: `_str` from `_str_mut`
+    pub fn next_word(s: &str) -> &str {
+        let word_start = s.find(|c: char| c.is_alphanumeric()).unwrap_or(s.len());
+        let word_end = s[word_start..]
+            .find(|c: char| !c.is_alphanumeric())
+            .map(|idx| idx + word_start)
+            .unwrap_or(s.len());
+        &s[..word_end]
+    }
+
     pub fn unmatched_brackets(s: &str) -> bool {
         let s = remove_comments(s);
         let mut braces = std::collections::HashMap::new();
@@ -117,45 +338,29 @@ impl StringTools {
         let mut previous_char = ' ';
         for character in s.chars() {
             match character {
-                '(' => {
-                    if !quote && !double_quote {
-                        *braces.get_mut(&'(').unwrap() += 1;
-                    }
+                '(' if !quote && !double_quote => {
+                    *braces.get_mut(&'(').unwrap() += 1;
                 }
-                ')' => {
-                    if !quote && !double_quote {
-                        *braces.get_mut(&'(').unwrap() -= 1;
-                    }
+                ')' if !quote && !double_quote => {
+                    *braces.get_mut(&'(').unwrap() -= 1;
                 }
-                '[' => {
-                    if !quote && !double_quote {
-                        *braces.get_mut(&'[').unwrap() += 1;
-                    }
+                '[' if !quote && !double_quote => {
+                    *braces.get_mut(&'[').unwrap() += 1;
                 }
-                ']' => {
-                    if !quote && !double_quote {
-                        *braces.get_mut(&'[').unwrap() -= 1;
-                    }
+                ']' if !quote && !double_quote => {
+                    *braces.get_mut(&'[').unwrap() -= 1;
                 }
-                '{' => {
-                    if !quote && !double_quote {
-                        *braces.get_mut(&'{').unwrap() += 1;
-                    }
+                '{' if !quote && !double_quote => {
+                    *braces.get_mut(&'{').unwrap() += 1;
                 }
-                '}' => {
-                    if !quote && !double_quote {
-                        *braces.get_mut(&'{').unwrap() -= 1;
-                    }
+                '}' if !quote && !double_quote => {
+                    *braces.get_mut(&'{').unwrap() -= 1;
                 }
-                '"' => {
-                    if previous_char != '\\' {
-                        double_quote = !double_quote;
-                    }
+                '"' if previous_char != '\\' => {
+                    double_quote = !double_quote;
                 }
-                '\'' => {
-                    if previous_char != '\\' {
-                        quote = !quote;
-                    }
+                '\'' if previous_char != '\\' => {
+                    quote = !quote;
                 }
                 _ => (),
             }
@@ -256,6 +461,67 @@ fn remove_comments(s: &str) -> String {
         .collect()
 }
 
-fn balanced_quotes(s: &str) -> bool {
-    s.match_indices(|p| p == '"' || p == '\'').count() % 2 == 0
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remove_main_keeps_the_body() {
+        let (script, wrapped) = remove_main("use std::fmt;\nfn main() {\n    let x = 1;\n}\n");
+        assert_eq!(script, "use std::fmt;\n\n    let x = 1;\n\n");
+        assert!(!wrapped);
+    }
+
+    #[test]
+    fn remove_main_strips_the_shebang() {
+        let (script, _) = remove_main("#!/usr/bin/env rust-script\nfn main() {\n    f();\n}");
+        assert_eq!(script, "\n    f();\n");
+    }
+
+    #[test]
+    fn remove_main_keeps_an_inner_attribute() {
+        let (script, _) = remove_main("#![allow(unused)]\nfn main() {\n    f();\n}");
+        assert_eq!(script, "#![allow(unused)]\n\n    f();\n");
+    }
+
+    #[test]
+    fn remove_main_non_ascii_columns() {
+        let (script, _) = remove_main("/* é ü */ fn main() { let s = \"ß\"; }\nstruct A;");
+        assert_eq!(script, "/* é ü */  let s = \"ß\"; \nstruct A;");
+    }
+
+    #[test]
+    fn remove_main_renames_other_mains() {
+        let (script, wrapped) = remove_main("#[tokio::main]\nasync fn main() {}");
+        assert_eq!(script, "#[tokio::main]\nasync fn script_main() {}");
+        assert!(wrapped);
+
+        let (script, wrapped) = remove_main("fn main() -> Result<(), ()> { Ok(()) }");
+        assert_eq!(script, "fn script_main() -> Result<(), ()> { Ok(()) }");
+        assert!(wrapped);
+    }
+
+    #[test]
+    fn remove_main_without_a_body() {
+        let script = "fn main() {\n    let x = (1;\n";
+        assert_eq!(remove_main(script), (script.to_owned(), false));
+
+        let script = "fn main();\nlet x = 1;";
+        assert_eq!(remove_main(script), (script.to_owned(), false));
+
+        let script = "fn helper() {}";
+        assert_eq!(remove_main(script), (script.to_owned(), false));
+    }
+
+    #[test]
+    fn byte_offset_counts_chars() {
+        let s = "ab\néé x\n";
+        let at = |line, column| byte_offset(s, proc_macro2::LineColumn { line, column });
+        assert_eq!(at(1, 1), 1);
+        assert_eq!(at(2, 0), 3);
+        assert_eq!(at(2, 2), 7);
+        assert_eq!(at(2, 3), 8);
+        // past the end of the text
+        assert_eq!(at(3, 5), s.len());
+    }
 }